@@ -0,0 +1,301 @@
+//! Evaluates the achievements seeded by migration 2 against practice
+//! history.
+//!
+//! Progress and `unlocked_at` are refreshed after every committed session,
+//! new skill and saved reflection; each newly unlocked achievement is sent to
+//! the webview as [`UNLOCKED_EVENT`]. Built-in achievements are matched on
+//! their `type` column and user-defined ones carry a [`rules::Rule`]; rows of
+//! a type this module does not know are left alone.
+
+pub mod rules;
+
+use chrono::NaiveDate;
+use sqlx::SqlitePool;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::analytics;
+use crate::database::{self, Db};
+use crate::error::Result;
+use crate::repository::achievements::AchievementRecord;
+use crate::repository::subskills::SKILL_TREE;
+
+/// Emitted once per achievement when it unlocks, with the updated record.
+pub const UNLOCKED_EVENT: &str = "achievement-unlocked";
+
+/// Hours in the user's time zone that count as "after midnight" and "before
+/// 6 AM". They do not overlap, so one late session does not unlock both.
+const NIGHT_OWL_HOURS: (i64, i64) = (0, 4);
+const EARLY_BIRD_HOURS: (i64, i64) = (4, 6);
+
+/// Everything the built-in achievements are measured against.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct History {
+    pub total_minutes: i64,
+    pub best_skill_minutes: i64,
+    pub skills: i64,
+    pub practiced_skills: i64,
+    pub longest_streak: i64,
+    pub night_sessions: i64,
+    pub early_sessions: i64,
+    pub best_day_sessions: i64,
+    pub best_week_sessions: i64,
+}
+
+/// Progress towards an achievement of type `kind`, before capping at its
+/// target. `None` for types without a built-in rule.
+pub fn progress(kind: &str, history: &History) -> Option<i64> {
+    Some(match kind {
+        "first_hour" | "first_100_hours" | "first_1000_hours" => history.total_minutes,
+        "skill_mastery" => history.best_skill_minutes,
+        "streak_7_days" | "streak_30_days" | "streak_100_days" | "streak_365_days" => {
+            history.longest_streak
+        }
+        "first_skill" => history.skills,
+        "five_skills" | "ten_skills" => history.practiced_skills,
+        "night_owl" => history.night_sessions,
+        "early_bird" => history.early_sessions,
+        "focused" => history.best_day_sessions,
+        "dedicated" => history.best_week_sessions,
+        _ => return None,
+    })
+}
+
+/// Most sessions in any Monday-to-Sunday week. `days` must be sorted.
+pub fn best_week(days: &[(NaiveDate, i64)]) -> i64 {
+    let mut best = 0;
+    let mut week = None;
+    let mut sessions = 0;
+    for (date, count) in days {
+        let start = analytics::week_start(*date);
+        if week != Some(start) {
+            week = Some(start);
+            sessions = 0;
+        }
+        sessions += count;
+        best = best.max(sessions);
+    }
+    best
+}
+
+impl History {
+    pub async fn load(pool: &SqlitePool) -> Result<Self> {
+        let (total_minutes, skills): (i64, i64) =
+            sqlx::query_as("SELECT COALESCE(SUM(current_minutes), 0), COUNT(*) FROM skills")
+                .fetch_one(pool)
+                .await?;
+        // A parent skill is measured on the time of its sub-skills too.
+        let (best_skill_minutes,): (i64,) = sqlx::query_as(&format!(
+            "WITH RECURSIVE {SKILL_TREE}
+             SELECT COALESCE(MAX(minutes), 0) FROM (
+                 SELECT SUM(k.current_minutes) AS minutes
+                 FROM skill_tree t JOIN skills k ON k.id = t.skill_id
+                 GROUP BY t.ancestor_id
+             )"
+        ))
+        .fetch_one(pool)
+        .await?;
+
+        let (practiced_skills, night_sessions, early_sessions): (i64, i64, i64) = sqlx::query_as(
+            "SELECT COUNT(DISTINCT skill_id),
+                    COALESCE(SUM(hour >= ?1 AND hour < ?2), 0),
+                    COALESCE(SUM(hour >= ?3 AND hour < ?4), 0)
+             FROM (
+                 SELECT skill_id, local_hour AS hour
+                 FROM timer_sessions
+                 WHERE completed = 1 AND type = 'pomodoro' AND duration > 0
+             )",
+        )
+        .bind(NIGHT_OWL_HOURS.0)
+        .bind(NIGHT_OWL_HOURS.1)
+        .bind(EARLY_BIRD_HOURS.0)
+        .bind(EARLY_BIRD_HOURS.1)
+        .fetch_one(pool)
+        .await?;
+
+        let days: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT date, total_sessions FROM daily_activities
+             WHERE total_minutes > 0
+             ORDER BY date",
+        )
+        .fetch_all(pool)
+        .await?;
+        let days: Vec<(NaiveDate, i64)> = days
+            .into_iter()
+            .filter_map(|(date, sessions)| Some((date.parse().ok()?, sessions)))
+            .collect();
+        let dates: Vec<NaiveDate> = days.iter().map(|(date, _)| *date).collect();
+        let today = dates.last().copied().unwrap_or_default();
+
+        Ok(History {
+            total_minutes,
+            best_skill_minutes,
+            skills,
+            practiced_skills,
+            longest_streak: analytics::streaks(&dates, today).longest,
+            night_sessions,
+            early_sessions,
+            best_day_sessions: days
+                .iter()
+                .map(|(_, sessions)| *sessions)
+                .max()
+                .unwrap_or(0),
+            best_week_sessions: best_week(&days),
+        })
+    }
+}
+
+/// Brings every known achievement up to date with practice history and
+/// returns the ones that unlocked. With `revoke`, achievements the history no
+/// longer supports are locked again, as after deleting sessions.
+pub async fn evaluate(pool: &SqlitePool, revoke: bool) -> Result<Vec<AchievementRecord>> {
+    let history = History::load(pool).await?;
+    let records: Vec<AchievementRecord> = sqlx::query_as("SELECT * FROM achievements")
+        .fetch_all(pool)
+        .await?;
+
+    let mut measured = Vec::new();
+    for record in records {
+        let progress = match &record.rule {
+            Some(rule) => rule.progress(&rule.days(pool).await?),
+            None => match progress(&record.kind, &history) {
+                Some(progress) => progress,
+                None => continue,
+            },
+        };
+        measured.push((record, progress));
+    }
+
+    let now = database::now_iso();
+    let mut tx = pool.begin().await?;
+    let mut unlocked = Vec::new();
+    for (mut record, progress) in measured {
+        let progress = progress.clamp(0, record.target);
+        let earned = progress >= record.target;
+        let unlocked_at = match &record.unlocked_at {
+            None if earned => Some(now.clone()),
+            Some(_) if revoke && !earned => None,
+            at => at.clone(),
+        };
+        if progress == record.progress && unlocked_at == record.unlocked_at {
+            continue;
+        }
+
+        sqlx::query("UPDATE achievements SET progress = ?, unlocked_at = ? WHERE id = ?")
+            .bind(progress)
+            .bind(&unlocked_at)
+            .bind(&record.id)
+            .execute(&mut *tx)
+            .await?;
+
+        let newly_unlocked = record.unlocked_at.is_none() && unlocked_at.is_some();
+        record.progress = progress;
+        record.unlocked_at = unlocked_at;
+        if newly_unlocked {
+            unlocked.push(record);
+        }
+    }
+
+    tx.commit().await?;
+    Ok(unlocked)
+}
+
+fn announce(app: &AppHandle, unlocked: &[AchievementRecord]) {
+    for achievement in unlocked {
+        if let Err(err) = app.emit(UNLOCKED_EVENT, achievement) {
+            log::warn!("failed to emit achievement unlock: {err}");
+        }
+    }
+}
+
+/// Re-evaluates after a change to practice data. Failures are logged rather
+/// than returned, so they never undo the change that triggered them.
+pub async fn refresh(app: &AppHandle) {
+    let pool = &app.state::<Db>().0;
+    match evaluate(pool, false).await {
+        Ok(unlocked) => announce(app, &unlocked),
+        Err(err) => log::error!("failed to evaluate achievements: {err}"),
+    }
+}
+
+/// Recomputes every achievement from scratch, relocking any that history no
+/// longer supports.
+#[tauri::command]
+pub async fn recompute_achievements(
+    app: AppHandle,
+    db: State<'_, Db>,
+) -> Result<Vec<AchievementRecord>> {
+    let unlocked = evaluate(&db.0, true).await?;
+    announce(&app, &unlocked);
+    Ok(unlocked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(date: &str, sessions: i64) -> (NaiveDate, i64) {
+        (date.parse().unwrap(), sessions)
+    }
+
+    #[test]
+    fn every_seeded_type_has_a_rule() {
+        let history = History::default();
+        for kind in [
+            "first_hour",
+            "first_100_hours",
+            "first_1000_hours",
+            "skill_mastery",
+            "streak_7_days",
+            "streak_30_days",
+            "streak_100_days",
+            "streak_365_days",
+            "first_skill",
+            "five_skills",
+            "ten_skills",
+            "night_owl",
+            "early_bird",
+            "focused",
+            "dedicated",
+        ] {
+            assert_eq!(progress(kind, &history), Some(0), "{kind}");
+        }
+        assert_eq!(progress("custom", &history), None);
+    }
+
+    #[test]
+    fn rules_read_the_matching_measure() {
+        let history = History {
+            total_minutes: 6100,
+            best_skill_minutes: 4000,
+            skills: 3,
+            practiced_skills: 2,
+            longest_streak: 12,
+            night_sessions: 1,
+            early_sessions: 0,
+            best_day_sessions: 9,
+            best_week_sessions: 31,
+        };
+        assert_eq!(progress("first_100_hours", &history), Some(6100));
+        assert_eq!(progress("skill_mastery", &history), Some(4000));
+        assert_eq!(progress("first_skill", &history), Some(3));
+        assert_eq!(progress("five_skills", &history), Some(2));
+        assert_eq!(progress("streak_30_days", &history), Some(12));
+        assert_eq!(progress("night_owl", &history), Some(1));
+        assert_eq!(progress("focused", &history), Some(9));
+        assert_eq!(progress("dedicated", &history), Some(31));
+    }
+
+    #[test]
+    fn best_week_splits_on_monday() {
+        let days = [
+            day("2024-03-04", 10),
+            day("2024-03-10", 15),
+            day("2024-03-11", 20),
+            day("2024-03-12", 2),
+            day("2024-03-25", 30),
+        ];
+        assert_eq!(best_week(&days), 30);
+        assert_eq!(best_week(&days[..4]), 25);
+        assert_eq!(best_week(&[]), 0);
+    }
+}
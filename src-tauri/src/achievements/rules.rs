@@ -0,0 +1,264 @@
+//! Rules for user-defined achievements.
+//!
+//! A rule names a metric, an optional skill, a calendar window and a
+//! threshold: "1200 minutes of Guitar in a month" is
+//! `{ "metric": "minutes", "skillId": "skill_…", "window": "month", "threshold": 1200 }`.
+//! Progress is the best total in any single window, so a goal stays
+//! unlocked once some month reached it. A rule on a skill counts the time of
+//! its sub-skills too.
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use super::{EARLY_BIRD_HOURS, NIGHT_OWL_HOURS};
+use crate::analytics;
+use crate::error::{Error, Result};
+use crate::repository::subskills::SKILL_TREE;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Metric {
+    Minutes,
+    Hours,
+    Sessions,
+    EarlyBirdSessions,
+    NightOwlSessions,
+    PracticeDays,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Window {
+    Day,
+    Week,
+    Month,
+    AllTime,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rule {
+    pub metric: Metric,
+    #[serde(default)]
+    pub skill_id: Option<String>,
+    pub window: Window,
+    pub threshold: i64,
+}
+
+/// Practice on one day, limited to the rule's skill when it has one.
+#[derive(Clone, Debug, Default, PartialEq, Eq, sqlx::FromRow)]
+pub struct DayTotals {
+    pub date: String,
+    pub minutes: i64,
+    pub sessions: i64,
+    pub night_sessions: i64,
+    pub early_sessions: i64,
+}
+
+impl Window {
+    /// First day of the window containing `date`; `None` for all time.
+    fn start(self, date: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Window::Day => Some(date),
+            Window::Week => Some(analytics::week_start(date)),
+            Window::Month => date.with_day(1),
+            Window::AllTime => None,
+        }
+    }
+
+    fn days(self) -> Option<i64> {
+        match self {
+            Window::Day => Some(1),
+            Window::Week => Some(7),
+            Window::Month => Some(31),
+            Window::AllTime => None,
+        }
+    }
+}
+
+impl Rule {
+    /// Checks the rule on its own; whether the skill exists is up to the
+    /// caller.
+    pub fn validate(&self) -> Result<()> {
+        if self.threshold < 1 {
+            return Err(Error::Invalid("threshold must be at least 1".into()));
+        }
+        if self.metric == Metric::PracticeDays {
+            if let Some(days) = self.window.days() {
+                if self.threshold > days {
+                    return Err(Error::Invalid(format!(
+                        "threshold is more than the {days} days in the window"
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn value(&self, day: &DayTotals) -> i64 {
+        match self.metric {
+            Metric::Minutes | Metric::Hours => day.minutes,
+            Metric::Sessions => day.sessions,
+            Metric::EarlyBirdSessions => day.early_sessions,
+            Metric::NightOwlSessions => day.night_sessions,
+            Metric::PracticeDays => i64::from(day.minutes > 0),
+        }
+    }
+
+    /// Best total over any one window in `days`.
+    pub fn progress(&self, days: &[DayTotals]) -> i64 {
+        let mut windows: BTreeMap<Option<NaiveDate>, i64> = BTreeMap::new();
+        for day in days {
+            let Ok(date) = day.date.parse() else {
+                continue;
+            };
+            *windows.entry(self.window.start(date)).or_default() += self.value(day);
+        }
+        let best = windows.into_values().max().unwrap_or(0);
+        match self.metric {
+            Metric::Hours => best / 60,
+            _ => best,
+        }
+    }
+
+    /// Per-day totals the rule is measured on. Rules over all skills that do
+    /// not depend on the time of day read `daily_activities`; the rest group
+    /// `timer_sessions`.
+    pub async fn days(&self, pool: &SqlitePool) -> Result<Vec<DayTotals>> {
+        let by_hour = matches!(
+            self.metric,
+            Metric::EarlyBirdSessions | Metric::NightOwlSessions
+        );
+        if self.skill_id.is_none() && !by_hour {
+            return Ok(sqlx::query_as(
+                "SELECT date, total_minutes AS minutes, total_sessions AS sessions,
+                        0 AS night_sessions, 0 AS early_sessions
+                 FROM daily_activities
+                 ORDER BY date",
+            )
+            .fetch_all(pool)
+            .await?);
+        }
+
+        Ok(sqlx::query_as(&format!(
+            "WITH RECURSIVE {SKILL_TREE}
+             SELECT local_date AS date,
+                    SUM(duration) AS minutes,
+                    SUM(pomodoro) AS sessions,
+                    COALESCE(SUM(pomodoro AND hour >= ?2 AND hour < ?3), 0) AS night_sessions,
+                    COALESCE(SUM(pomodoro AND hour >= ?4 AND hour < ?5), 0) AS early_sessions
+             FROM (
+                 SELECT local_date, duration, skill_id, local_hour AS hour,
+                        type = 'pomodoro' AND duration > 0 AS pomodoro
+                 FROM timer_sessions
+                 WHERE completed = 1 AND type IN ('pomodoro', 'adjustment')
+             )
+             WHERE ?1 IS NULL
+                OR skill_id IN (SELECT skill_id FROM skill_tree WHERE ancestor_id = ?1)
+             GROUP BY local_date
+             ORDER BY local_date"
+        ))
+        .bind(&self.skill_id)
+        .bind(NIGHT_OWL_HOURS.0)
+        .bind(NIGHT_OWL_HOURS.1)
+        .bind(EARLY_BIRD_HOURS.0)
+        .bind(EARLY_BIRD_HOURS.1)
+        .fetch_all(pool)
+        .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(metric: Metric, window: Window, threshold: i64) -> Rule {
+        Rule {
+            metric,
+            skill_id: None,
+            window,
+            threshold,
+        }
+    }
+
+    fn day(date: &str, minutes: i64, sessions: i64) -> DayTotals {
+        DayTotals {
+            date: date.into(),
+            minutes,
+            sessions,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parses_the_documented_shape() {
+        let parsed: Rule = serde_json::from_str(
+            r#"{"metric":"hours","skillId":"skill_1","window":"month","threshold":20}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            parsed,
+            Rule {
+                metric: Metric::Hours,
+                skill_id: Some("skill_1".into()),
+                window: Window::Month,
+                threshold: 20,
+            }
+        );
+        assert!(serde_json::from_str::<Rule>(
+            r#"{"metric":"pages","window":"month","threshold":20}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn validation_rejects_unreachable_rules() {
+        assert!(rule(Metric::Minutes, Window::Day, 0).validate().is_err());
+        assert!(rule(Metric::PracticeDays, Window::Week, 8)
+            .validate()
+            .is_err());
+        assert!(rule(Metric::PracticeDays, Window::Week, 7)
+            .validate()
+            .is_ok());
+        assert!(rule(Metric::PracticeDays, Window::AllTime, 500)
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn progress_is_the_best_single_window() {
+        let days = [
+            day("2024-01-30", 300, 6),
+            day("2024-01-31", 300, 6),
+            day("2024-02-01", 200, 4),
+            day("2024-02-29", 500, 10),
+        ];
+        assert_eq!(rule(Metric::Hours, Window::Month, 20).progress(&days), 11);
+        assert_eq!(rule(Metric::Minutes, Window::Day, 1).progress(&days), 500);
+        // 2024-01-29 to 2024-02-04 is one Monday-to-Sunday week.
+        assert_eq!(rule(Metric::Sessions, Window::Week, 1).progress(&days), 16);
+        assert_eq!(
+            rule(Metric::PracticeDays, Window::AllTime, 1).progress(&days),
+            4
+        );
+        assert_eq!(rule(Metric::Minutes, Window::Month, 1).progress(&[]), 0);
+    }
+
+    #[test]
+    fn hour_metrics_read_their_own_counts() {
+        let days = [DayTotals {
+            date: "2024-03-04".into(),
+            minutes: 100,
+            sessions: 4,
+            night_sessions: 1,
+            early_sessions: 3,
+        }];
+        let early = rule(Metric::EarlyBirdSessions, Window::Week, 5);
+        assert_eq!(early.progress(&days), 3);
+        let night = rule(Metric::NightOwlSessions, Window::Week, 5);
+        assert_eq!(night.progress(&days), 1);
+    }
+}
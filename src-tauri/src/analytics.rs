@@ -0,0 +1,580 @@
+//! Streaks, weekly breakdowns and per-skill progress for the dashboard and
+//! profile, in the shapes declared in `src/types/analytics.ts`.
+//!
+//! SQL does the grouping by day and skill; the functions here only fold
+//! those rows, so they are tested without a database.
+//!
+//! Each report can be narrowed to the sessions that count for one tag, and
+//! [`tag_breakdown`] groups the time by tag instead of by skill. Progress
+//! and streaks for a skill include the time of its sub-skills.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use chrono::{Duration, NaiveDate, Weekday};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::State;
+
+use crate::database::Db;
+use crate::error::Result;
+use crate::repository::subskills::SKILL_TREE;
+use crate::repository::tags::TAGGED_SESSIONS;
+use crate::timezone;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DayActivity {
+    pub date: String,
+    pub minutes: i64,
+    pub sessions: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ProgressPoint {
+    pub date: String,
+    pub hours: f64,
+}
+
+/// Total hours logged on a skill at the end of each practice day.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillProgress {
+    pub skill_id: String,
+    pub skill_name: String,
+    pub data: Vec<ProgressPoint>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillShare {
+    pub skill_id: String,
+    pub skill_name: String,
+    pub minutes: i64,
+    pub percentage: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeeklyStats {
+    pub week_start: String,
+    pub total_minutes: i64,
+    pub total_sessions: i64,
+    pub skill_breakdown: Vec<SkillShare>,
+}
+
+/// Minutes that count for one tag. Sessions with several tags count for
+/// each, so the percentages can add up to more than 100.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagShare {
+    pub tag_id: String,
+    pub tag_name: String,
+    pub color: Option<String>,
+    pub minutes: i64,
+    pub sessions: i64,
+    pub percentage: f64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Streaks {
+    pub current: i64,
+    pub longest: i64,
+}
+
+/// Completed pomodoro minutes for one skill on one day.
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct SkillDay {
+    pub date: String,
+    pub skill_id: String,
+    pub skill_name: String,
+    pub minutes: i64,
+    pub sessions: i64,
+}
+
+/// Current and longest run of consecutive days in `dates`, which must be
+/// sorted ascending. The current streak still counts when today has no
+/// practice yet but yesterday does.
+pub fn streaks(dates: &[NaiveDate], today: NaiveDate) -> Streaks {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for date in dates {
+        run = match previous {
+            Some(previous) if *date - previous == Duration::days(1) => run + 1,
+            Some(previous) if *date == previous => run,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(*date);
+    }
+
+    let current = match previous {
+        Some(last) if last == today || last == today - Duration::days(1) => run,
+        _ => 0,
+    };
+    Streaks { current, longest }
+}
+
+/// Monday of the ISO week containing `date`.
+pub fn week_start(date: NaiveDate) -> NaiveDate {
+    date.week(Weekday::Mon).first_day()
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    date.parse().ok()
+}
+
+fn percentage(part: i64, total: i64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    (part as f64 * 1000.0 / total as f64).round() / 10.0
+}
+
+/// One entry per week that has practice, oldest first, with skills ordered
+/// by minutes.
+pub fn weekly(rows: &[SkillDay]) -> Vec<WeeklyStats> {
+    // week -> skill id -> (name, minutes, sessions)
+    let mut weeks: BTreeMap<NaiveDate, BTreeMap<&str, (&str, i64, i64)>> = BTreeMap::new();
+    for row in rows {
+        let Some(date) = parse_date(&row.date) else {
+            continue;
+        };
+        let skill = weeks
+            .entry(week_start(date))
+            .or_default()
+            .entry(&row.skill_id)
+            .or_insert((&row.skill_name, 0, 0));
+        skill.1 += row.minutes;
+        skill.2 += row.sessions;
+    }
+
+    weeks
+        .into_iter()
+        .map(|(start, skills)| {
+            let total_minutes = skills.values().map(|(_, minutes, _)| minutes).sum();
+            let total_sessions = skills.values().map(|(_, _, sessions)| sessions).sum();
+            let mut skill_breakdown: Vec<SkillShare> = skills
+                .into_iter()
+                .map(|(id, (name, minutes, _))| SkillShare {
+                    skill_id: id.to_owned(),
+                    skill_name: name.to_owned(),
+                    minutes,
+                    percentage: percentage(minutes, total_minutes),
+                })
+                .collect();
+            skill_breakdown.sort_by_key(|share| Reverse(share.minutes));
+            WeeklyStats {
+                week_start: start.to_string(),
+                total_minutes,
+                total_sessions,
+                skill_breakdown,
+            }
+        })
+        .collect()
+}
+
+/// Cumulative hours per skill over `rows`, starting from each skill's total
+/// before the first row. `totals` holds `(id, name, current_minutes)` for
+/// every skill; skills without rows get an empty series.
+pub fn progress(rows: &[SkillDay], totals: &[(String, String, i64)]) -> Vec<SkillProgress> {
+    totals
+        .iter()
+        .map(|(id, name, current_minutes)| {
+            let days: Vec<&SkillDay> = rows.iter().filter(|row| &row.skill_id == id).collect();
+            let in_range: i64 = days.iter().map(|row| row.minutes).sum();
+            let mut minutes = current_minutes - in_range;
+            let data = days
+                .iter()
+                .map(|row| {
+                    minutes += row.minutes;
+                    ProgressPoint {
+                        date: row.date.clone(),
+                        hours: (minutes as f64 / 60.0 * 100.0).round() / 100.0,
+                    }
+                })
+                .collect();
+            SkillProgress {
+                skill_id: id.clone(),
+                skill_name: name.clone(),
+                data,
+            }
+        })
+        .collect()
+}
+
+/// Rows for completed pomodoros and corrections on or after `since`,
+/// ordered by date. Corrections add minutes but not sessions.
+/// With `roll_up`, a session also counts for every skill above its own, so
+/// a parent's rows include its sub-skills' time.
+async fn skill_days(
+    pool: &SqlitePool,
+    since: NaiveDate,
+    tag: Option<&str>,
+    roll_up: bool,
+) -> Result<Vec<SkillDay>> {
+    Ok(sqlx::query_as(&format!(
+        "WITH RECURSIVE {SKILL_TREE}, {TAGGED_SESSIONS}
+         SELECT s.local_date AS date, t.ancestor_id AS skill_id, k.name AS skill_name,
+                SUM(s.duration) AS minutes, SUM(s.type = 'pomodoro') AS sessions
+         FROM timer_sessions s
+         JOIN skill_tree t ON t.skill_id = s.skill_id AND (?3 OR t.ancestor_id = s.skill_id)
+         JOIN skills k ON k.id = t.ancestor_id
+         WHERE s.completed = 1 AND s.type IN ('pomodoro', 'adjustment') AND s.local_date >= ?1
+           AND (?2 IS NULL OR s.id IN (SELECT session_id FROM tagged_sessions WHERE tag_id = ?2))
+         GROUP BY s.local_date, t.ancestor_id
+         ORDER BY s.local_date, t.ancestor_id"
+    ))
+    .bind(since.to_string())
+    .bind(tag)
+    .bind(roll_up)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Minutes and sessions per practice day on or after `since`, limited to a
+/// tag and to a skill with its sub-skills. Without either this is
+/// `daily_activities`, which the triggers keep in step with the sessions.
+async fn practice_days(
+    pool: &SqlitePool,
+    since: &str,
+    tag: Option<&str>,
+    skill: Option<&str>,
+) -> Result<Vec<(String, i64, i64)>> {
+    if tag.is_none() && skill.is_none() {
+        return Ok(sqlx::query_as(
+            "SELECT date, total_minutes, total_sessions FROM daily_activities
+             WHERE date >= ? AND total_minutes > 0
+             ORDER BY date",
+        )
+        .bind(since)
+        .fetch_all(pool)
+        .await?);
+    }
+    Ok(sqlx::query_as(&format!(
+        "WITH RECURSIVE {SKILL_TREE}, {TAGGED_SESSIONS}
+         SELECT s.local_date, SUM(s.duration) AS minutes, SUM(s.type = 'pomodoro') AS sessions
+         FROM timer_sessions s
+         WHERE s.completed = 1 AND s.type IN ('pomodoro', 'adjustment') AND s.local_date >= ?1
+           AND (?2 IS NULL OR s.id IN (SELECT session_id FROM tagged_sessions WHERE tag_id = ?2))
+           AND (?3 IS NULL OR s.skill_id IN (SELECT skill_id FROM skill_tree WHERE ancestor_id = ?3))
+         GROUP BY s.local_date
+         HAVING minutes > 0
+         ORDER BY s.local_date"
+    ))
+    .bind(since)
+    .bind(tag)
+    .bind(skill)
+    .fetch_all(pool)
+    .await?)
+}
+
+pub async fn activity_days(
+    pool: &SqlitePool,
+    days: i64,
+    tag: Option<&str>,
+) -> Result<Vec<DayActivity>> {
+    let since = timezone::days_before(timezone::load(pool).await?.today(), days)?;
+    Ok(practice_days(pool, &since.to_string(), tag, None)
+        .await?
+        .into_iter()
+        .map(|(date, minutes, sessions)| DayActivity {
+            date,
+            minutes,
+            sessions,
+        })
+        .collect())
+}
+
+/// Streaks of days with practice, or with practice that counts for `tag`.
+pub async fn practice_streaks(
+    pool: &SqlitePool,
+    tag: Option<&str>,
+    skill: Option<&str>,
+) -> Result<Streaks> {
+    // Every date sorts after the empty string.
+    let dates = practice_days(pool, "", tag, skill).await?;
+    let dates: Vec<NaiveDate> = dates
+        .iter()
+        .filter_map(|(date, ..)| parse_date(date))
+        .collect();
+    Ok(streaks(&dates, timezone::load(pool).await?.today()))
+}
+
+/// The last `weeks` weeks including the current one.
+pub async fn weekly_stats(
+    pool: &SqlitePool,
+    weeks: i64,
+    tag: Option<&str>,
+) -> Result<Vec<WeeklyStats>> {
+    let today = timezone::load(pool).await?.today();
+    let since = timezone::days_before(week_start(today), (weeks.max(1) - 1).saturating_mul(7))?;
+    Ok(weekly(&skill_days(pool, since, tag, false).await?))
+}
+
+/// Hours per skill over the last `days`, each including its sub-skills. With
+/// a tag, only time that counts for it is added up, from all of history.
+pub async fn skill_progress(
+    pool: &SqlitePool,
+    days: i64,
+    tag: Option<&str>,
+) -> Result<Vec<SkillProgress>> {
+    let since = timezone::days_before(timezone::load(pool).await?.today(), days)?;
+    let totals: Vec<(String, String, i64)> = match tag {
+        None => {
+            sqlx::query_as(&format!(
+                "WITH RECURSIVE {SKILL_TREE}
+                 SELECT k.id, k.name, SUM(below.current_minutes)
+                 FROM skills k
+                 JOIN skill_tree t ON t.ancestor_id = k.id
+                 JOIN skills below ON below.id = t.skill_id
+                 GROUP BY k.id
+                 ORDER BY k.created_at"
+            ))
+            .fetch_all(pool)
+            .await?
+        }
+        Some(tag) => {
+            sqlx::query_as(&format!(
+                "WITH RECURSIVE {SKILL_TREE}, {TAGGED_SESSIONS}
+                 SELECT k.id, k.name, COALESCE(SUM(s.duration), 0)
+                 FROM skills k
+                 JOIN skill_tree t ON t.ancestor_id = k.id
+                 LEFT JOIN timer_sessions s
+                   ON s.skill_id = t.skill_id AND s.completed = 1
+                  AND s.type IN ('pomodoro', 'adjustment')
+                  AND s.id IN (SELECT session_id FROM tagged_sessions WHERE tag_id = ?)
+                 GROUP BY k.id
+                 ORDER BY k.created_at"
+            ))
+            .bind(tag)
+            .fetch_all(pool)
+            .await?
+        }
+    };
+    Ok(progress(
+        &skill_days(pool, since, tag, true).await?,
+        &totals,
+    ))
+}
+
+/// Completed time per tag over the last `days`, most first. Corrections
+/// count for minutes but not sessions.
+pub async fn tag_breakdown(pool: &SqlitePool, days: i64) -> Result<Vec<TagShare>> {
+    let since = timezone::days_before(timezone::load(pool).await?.today(), days)?;
+    let (total,): (i64,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(duration), 0) FROM timer_sessions
+         WHERE completed = 1 AND type IN ('pomodoro', 'adjustment') AND local_date >= ?",
+    )
+    .bind(since.to_string())
+    .fetch_one(pool)
+    .await?;
+    let rows: Vec<(String, String, Option<String>, i64, i64)> = sqlx::query_as(&format!(
+        "WITH {TAGGED_SESSIONS}
+         SELECT g.id, g.name, g.color, SUM(s.duration) AS minutes, SUM(s.type = 'pomodoro')
+         FROM tagged_sessions t
+         JOIN timer_sessions s ON s.id = t.session_id
+         JOIN tags g ON g.id = t.tag_id
+         WHERE s.completed = 1 AND s.type IN ('pomodoro', 'adjustment') AND s.local_date >= ?
+         GROUP BY g.id
+         ORDER BY minutes DESC, g.name"
+    ))
+    .bind(since.to_string())
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(tag_id, tag_name, color, minutes, sessions)| TagShare {
+            tag_id,
+            tag_name,
+            color,
+            minutes,
+            sessions,
+            percentage: percentage(minutes, total),
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub async fn get_activity_days(
+    db: State<'_, Db>,
+    days: i64,
+    tag_id: Option<String>,
+) -> Result<Vec<DayActivity>> {
+    activity_days(&db.0, days, tag_id.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn get_streaks(
+    db: State<'_, Db>,
+    tag_id: Option<String>,
+    skill_id: Option<String>,
+) -> Result<Streaks> {
+    practice_streaks(&db.0, tag_id.as_deref(), skill_id.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn get_weekly_stats(
+    db: State<'_, Db>,
+    weeks: i64,
+    tag_id: Option<String>,
+) -> Result<Vec<WeeklyStats>> {
+    weekly_stats(&db.0, weeks, tag_id.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn get_skill_progress(
+    db: State<'_, Db>,
+    days: i64,
+    tag_id: Option<String>,
+) -> Result<Vec<SkillProgress>> {
+    skill_progress(&db.0, days, tag_id.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn get_tag_breakdown(db: State<'_, Db>, days: i64) -> Result<Vec<TagShare>> {
+    tag_breakdown(&db.0, days).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn dates(list: &[&str]) -> Vec<NaiveDate> {
+        list.iter().map(|s| date(s)).collect()
+    }
+
+    fn row(day: &str, skill: &str, minutes: i64, sessions: i64) -> SkillDay {
+        SkillDay {
+            date: day.into(),
+            skill_id: skill.into(),
+            skill_name: skill.to_uppercase(),
+            minutes,
+            sessions,
+        }
+    }
+
+    #[test]
+    fn no_practice_means_no_streak() {
+        assert_eq!(streaks(&[], date("2024-03-10")), Streaks::default());
+    }
+
+    #[test]
+    fn streak_runs_through_today() {
+        let days = dates(&["2024-03-07", "2024-03-08", "2024-03-09", "2024-03-10"]);
+        assert_eq!(
+            streaks(&days, date("2024-03-10")),
+            Streaks {
+                current: 4,
+                longest: 4
+            }
+        );
+    }
+
+    #[test]
+    fn streak_survives_until_today_is_over() {
+        let days = dates(&["2024-03-08", "2024-03-09"]);
+        assert_eq!(streaks(&days, date("2024-03-10")).current, 2);
+    }
+
+    #[test]
+    fn missed_day_breaks_current_streak() {
+        let days = dates(&["2024-03-01", "2024-03-02", "2024-03-03", "2024-03-08"]);
+        assert_eq!(
+            streaks(&days, date("2024-03-10")),
+            Streaks {
+                current: 0,
+                longest: 3
+            }
+        );
+    }
+
+    #[test]
+    fn longest_streak_crosses_month_and_year() {
+        let days = dates(&[
+            "2023-12-30",
+            "2023-12-31",
+            "2024-01-01",
+            "2024-02-28",
+            "2024-02-29",
+            "2024-03-01",
+            "2024-03-02",
+        ]);
+        assert_eq!(
+            streaks(&days, date("2024-03-02")),
+            Streaks {
+                current: 4,
+                longest: 4
+            }
+        );
+    }
+
+    #[test]
+    fn duplicate_dates_do_not_extend_streak() {
+        let days = dates(&["2024-03-09", "2024-03-09", "2024-03-10"]);
+        assert_eq!(streaks(&days, date("2024-03-10")).current, 2);
+    }
+
+    #[test]
+    fn weeks_start_on_monday() {
+        assert_eq!(week_start(date("2024-03-10")), date("2024-03-04"));
+        assert_eq!(week_start(date("2024-03-11")), date("2024-03-11"));
+        assert_eq!(week_start(date("2024-01-03")), date("2024-01-01"));
+    }
+
+    #[test]
+    fn weekly_breakdown_groups_by_week_and_skill() {
+        let rows = [
+            row("2024-03-04", "piano", 50, 2),
+            row("2024-03-05", "go", 25, 1),
+            row("2024-03-10", "piano", 75, 3),
+            row("2024-03-11", "go", 30, 1),
+        ];
+        let weeks = weekly(&rows);
+        assert_eq!(weeks.len(), 2);
+
+        assert_eq!(weeks[0].week_start, "2024-03-04");
+        assert_eq!(weeks[0].total_minutes, 150);
+        assert_eq!(weeks[0].total_sessions, 6);
+        let shares: Vec<(&str, i64, f64)> = weeks[0]
+            .skill_breakdown
+            .iter()
+            .map(|s| (s.skill_id.as_str(), s.minutes, s.percentage))
+            .collect();
+        assert_eq!(shares, [("piano", 125, 83.3), ("go", 25, 16.7)]);
+        assert_eq!(weeks[0].skill_breakdown[0].skill_name, "PIANO");
+
+        assert_eq!(weeks[1].week_start, "2024-03-11");
+        assert_eq!(weeks[1].skill_breakdown[0].percentage, 100.0);
+    }
+
+    #[test]
+    fn progress_is_cumulative_from_prior_total() {
+        let rows = [
+            row("2024-03-01", "piano", 30, 1),
+            row("2024-03-02", "go", 60, 2),
+            row("2024-03-03", "piano", 90, 3),
+        ];
+        let totals = [
+            ("piano".to_string(), "Piano".to_string(), 720),
+            ("go".to_string(), "Go".to_string(), 60),
+            ("chess".to_string(), "Chess".to_string(), 0),
+        ];
+        let progress = progress(&rows, &totals);
+
+        let piano: Vec<(&str, f64)> = progress[0]
+            .data
+            .iter()
+            .map(|p| (p.date.as_str(), p.hours))
+            .collect();
+        assert_eq!(piano, [("2024-03-01", 10.5), ("2024-03-03", 12.0)]);
+        assert_eq!(progress[1].data.len(), 1);
+        assert_eq!(progress[1].data[0].hours, 1.0);
+        assert!(progress[2].data.is_empty());
+    }
+}
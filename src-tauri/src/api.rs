@@ -0,0 +1,329 @@
+//! Opt-in HTTP API for editor plugins, Stream Deck scripts and shell hooks.
+//!
+//! Off until enabled in settings. It listens on `127.0.0.1` only, and every
+//! request needs `Authorization: Bearer <token>` with the token shown in
+//! settings. Responses are JSON in the same shapes the Tauri commands
+//! return:
+//!
+//! ```text
+//! GET  /skills
+//! GET  /tasks?skill_id=…
+//! GET  /tasks/unblocked?skill_id=…
+//! GET  /sessions?skill_id=…
+//! GET  /timer
+//! POST /timer/start      {"skillId": "…", "taskId": "…", "type": "pomodoro"}
+//! POST /timer/pause
+//! POST /timer/resume
+//! POST /timer/stop
+//! GET  /reports/week?weeks=1&tag_id=…
+//! GET  /reports/activity?days=365&tag_id=…
+//! GET  /reports/streaks?tag_id=…&skill_id=…
+//! GET  /reports/tags?days=30
+//! GET  /reports/projections
+//! ```
+//!
+//! Timer routes drive the same [`Timer`] as the window, which is told about
+//! each change through [`timer::TICK_EVENT`].
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::async_runtime::{self, JoinHandle, Mutex};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::net::TcpListener;
+use tokio::task::JoinSet;
+
+use crate::analytics;
+use crate::database::Db;
+use crate::error::{Error, Result};
+use crate::projection;
+use crate::repository::settings::{self, ApiSettings, UpdateApiSettingsInput};
+use crate::repository::{dependencies, sessions, skills, tasks};
+use crate::timer::{self, Timer, TimerSnapshot, TimerType};
+
+/// Managed state holding the listener task while the API is on. Aborting it
+/// also closes every connection it accepted.
+#[derive(Default)]
+pub struct Server(Mutex<Option<JoinHandle<()>>>);
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StartRequest {
+    skill_id: String,
+    task_id: Option<String>,
+    #[serde(rename = "type")]
+    kind: Option<TimerType>,
+}
+
+enum Failure {
+    Unauthorized,
+    NotFound,
+    BadRequest(String),
+    App(Error),
+}
+
+impl From<Error> for Failure {
+    fn from(err: Error) -> Self {
+        Failure::App(err)
+    }
+}
+
+type Reply = std::result::Result<serde_json::Value, Failure>;
+
+fn to_json(value: impl Serialize) -> Reply {
+    serde_json::to_value(value).map_err(|err| Failure::BadRequest(err.to_string()))
+}
+
+/// Whether `header` is `Bearer <token>`, compared without short-circuiting.
+fn authorized(header: Option<&str>, token: &str) -> bool {
+    let Some(given) = header.and_then(|h| h.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn parse_query(query: Option<&str>) -> HashMap<String, String> {
+    form_urlencoded::parse(query.unwrap_or("").as_bytes())
+        .into_owned()
+        .collect()
+}
+
+fn number(
+    query: &HashMap<String, String>,
+    key: &str,
+    default: i64,
+) -> std::result::Result<i64, Failure> {
+    match query.get(key) {
+        Some(value) => value
+            .parse()
+            .map_err(|_| Failure::BadRequest(format!("`{key}` must be a number"))),
+        None => Ok(default),
+    }
+}
+
+/// Tells the window about a timer change it did not make itself.
+fn broadcast(app: &AppHandle, snapshot: &TimerSnapshot) {
+    if let Err(err) = app.emit(timer::TICK_EVENT, snapshot) {
+        log::warn!("failed to emit timer state: {err}");
+    }
+}
+
+async fn route(app: &AppHandle, request: Request<Incoming>) -> Reply {
+    let method = request.method().clone();
+    let path = request.uri().path().trim_matches('/').to_owned();
+    let query = parse_query(request.uri().query());
+    let body = request
+        .into_body()
+        .collect()
+        .await
+        .map_err(|err| Failure::BadRequest(err.to_string()))?
+        .to_bytes();
+
+    let pool = &app.state::<Db>().0;
+    let segments: Vec<&str> = path.split('/').collect();
+    let skill_id = query.get("skill_id").map(String::as_str);
+    let tag_id = query.get("tag_id").map(String::as_str);
+
+    let snapshot = match (&method, segments.as_slice()) {
+        (&Method::GET, ["skills"]) => return to_json(skills::list(pool).await?),
+        (&Method::GET, ["tasks"]) => return to_json(tasks::list(pool, skill_id).await?),
+        (&Method::GET, ["tasks", "unblocked"]) => {
+            return to_json(dependencies::unblocked(pool, skill_id).await?)
+        }
+        (&Method::GET, ["sessions"]) => return to_json(sessions::list(pool, skill_id).await?),
+        (&Method::GET, ["timer"]) => {
+            return to_json(timer::timer_state(app.state::<Timer>()).await?)
+        }
+        (&Method::GET, ["reports", "week"]) => {
+            let weeks = number(&query, "weeks", 1)?;
+            return to_json(analytics::weekly_stats(pool, weeks, tag_id).await?);
+        }
+        (&Method::GET, ["reports", "activity"]) => {
+            let days = number(&query, "days", 365)?;
+            return to_json(analytics::activity_days(pool, days, tag_id).await?);
+        }
+        (&Method::GET, ["reports", "streaks"]) => {
+            return to_json(analytics::practice_streaks(pool, tag_id, skill_id).await?)
+        }
+        (&Method::GET, ["reports", "tags"]) => {
+            let days = number(&query, "days", 30)?;
+            return to_json(analytics::tag_breakdown(pool, days).await?);
+        }
+        (&Method::GET, ["reports", "projections"]) => {
+            return to_json(projection::projections(pool, None).await?)
+        }
+        (&Method::POST, ["timer", "start"]) => {
+            let start: StartRequest = serde_json::from_slice(&body)
+                .map_err(|err| Failure::BadRequest(err.to_string()))?;
+            timer::timer_start(
+                app.clone(),
+                app.state::<Timer>(),
+                start.kind.unwrap_or(TimerType::Pomodoro),
+                start.task_id,
+                start.skill_id,
+            )
+            .await?
+        }
+        (&Method::POST, ["timer", "pause"]) => {
+            timer::timer_pause(app.clone(), app.state::<Timer>()).await?
+        }
+        (&Method::POST, ["timer", "resume"]) => {
+            timer::timer_resume(app.clone(), app.state::<Timer>()).await?
+        }
+        (&Method::POST, ["timer", "stop"]) => {
+            timer::timer_stop(app.clone(), app.state::<Timer>()).await?
+        }
+        _ => return Err(Failure::NotFound),
+    };
+
+    broadcast(app, &snapshot);
+    to_json(snapshot)
+}
+
+async fn handle(
+    app: AppHandle,
+    token: String,
+    request: Request<Incoming>,
+) -> std::result::Result<Response<Full<Bytes>>, Infallible> {
+    let header = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    let reply = if authorized(header, &token) {
+        route(&app, request).await
+    } else {
+        Err(Failure::Unauthorized)
+    };
+
+    let (status, body) = match reply {
+        Ok(value) => (StatusCode::OK, value),
+        Err(failure) => {
+            let (status, message) = match failure {
+                Failure::Unauthorized => {
+                    (StatusCode::UNAUTHORIZED, "missing or wrong token".into())
+                }
+                Failure::NotFound => (StatusCode::NOT_FOUND, "no such route".into()),
+                Failure::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+                Failure::App(err @ Error::Timer(_)) => (StatusCode::CONFLICT, err.to_string()),
+                Failure::App(err @ Error::Invalid(_)) => (StatusCode::BAD_REQUEST, err.to_string()),
+                Failure::App(err) => {
+                    log::error!("api request failed: {err}");
+                    (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+                }
+            };
+            (status, json!({ "error": message }))
+        }
+    };
+
+    let response = Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(body.to_string())))
+        .expect("static response parts are valid");
+    Ok(response)
+}
+
+/// Accepts connections until aborted. They are held in a [`JoinSet`] owned
+/// by this task, so aborting it drops them too and a keep-alive client
+/// cannot go on using a token that was changed or an API that was turned
+/// off.
+async fn serve(app: AppHandle, listener: TcpListener, token: String) {
+    let mut connections = JoinSet::new();
+    loop {
+        while connections.try_join_next().is_some() {}
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                log::warn!("api accept failed: {err}");
+                continue;
+            }
+        };
+        let (app, token) = (app.clone(), token.clone());
+        connections.spawn(async move {
+            let service = service_fn(move |request| handle(app.clone(), token.clone(), request));
+            if let Err(err) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                log::debug!("api connection closed: {err}");
+            }
+        });
+    }
+}
+
+/// Stops the server if it is running and starts it again when the settings
+/// say it should be on.
+pub async fn apply(app: &AppHandle) -> Result<ApiSettings> {
+    let pool = &app.state::<Db>().0;
+    let api = settings::api(pool).await?;
+    let mut server = app.state::<Server>().inner().0.lock().await;
+    if let Some(task) = server.take() {
+        task.abort();
+    }
+
+    if let (true, Some(token)) = (api.api_enabled, api.api_token.clone()) {
+        let listener = TcpListener::bind(("127.0.0.1", api.api_port as u16)).await?;
+        log::info!("api listening on 127.0.0.1:{}", api.api_port);
+        *server = Some(async_runtime::spawn(serve(app.clone(), listener, token)));
+    }
+    Ok(api)
+}
+
+#[tauri::command]
+pub async fn get_api_settings(db: State<'_, Db>) -> Result<ApiSettings> {
+    settings::api(&db.0).await
+}
+
+#[tauri::command]
+pub async fn update_api_settings(
+    app: AppHandle,
+    db: State<'_, Db>,
+    input: UpdateApiSettingsInput,
+) -> Result<ApiSettings> {
+    settings::update_api(&db.0, input).await?;
+    apply(&app).await
+}
+
+#[tauri::command]
+pub async fn regenerate_api_token(app: AppHandle, db: State<'_, Db>) -> Result<ApiSettings> {
+    settings::regenerate_api_token(&db.0).await?;
+    apply(&app).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_must_match_exactly() {
+        assert!(authorized(Some("Bearer abc123"), "abc123"));
+        assert!(!authorized(Some("Bearer abc124"), "abc123"));
+        assert!(!authorized(Some("Bearer abc"), "abc123"));
+        assert!(!authorized(Some("abc123"), "abc123"));
+        assert!(!authorized(None, "abc123"));
+    }
+
+    #[test]
+    fn query_values_are_decoded() {
+        let query = parse_query(Some("skill_id=skill_1&weeks=4&name=Jazz%20Guitar"));
+        assert_eq!(query["skill_id"], "skill_1");
+        assert_eq!(number(&query, "weeks", 1).ok(), Some(4));
+        assert_eq!(number(&query, "days", 30).ok(), Some(30));
+        assert_eq!(query["name"], "Jazz Guitar");
+        assert!(number(&query, "name", 1).is_err());
+    }
+}
@@ -0,0 +1,646 @@
+//! Versioned backup archives.
+//!
+//! An [`Archive`] holds every row of every table as JSON objects keyed by
+//! column, together with the schema version it was taken at, the app version
+//! and a SHA-256 checksum per table. Rows are read and written through
+//! SQLite's JSON functions, so columns added by later migrations are carried
+//! without touching this module; only new tables have to be added to
+//! [`TABLES`]. Credentials in [`PRIVATE_COLUMNS`] are never written to an
+//! archive, and a restore keeps the target's own.
+//!
+//! [`restore`] works on an empty or a live database. Rows are matched by
+//! primary key: missing rows are added, identical rows are left alone, and
+//! rows that differ are handled by the [`ConflictPolicy`]. A row that
+//! collides with another on a different UNIQUE column, such as a reflection
+//! for a date that already has one, is reported as a conflict and skipped
+//! under every policy, so nothing is replaced out from under its children.
+//! Skill totals and daily activity are worked out again from the sessions
+//! the database ends up with. With `dry_run` everything runs inside a
+//! transaction that is rolled back, so the report is exactly what a real
+//! restore would do.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use sqlx::{SqliteConnection, SqlitePool};
+use tauri::{AppHandle, State};
+
+use crate::achievements as unlocks;
+use crate::database::{self, Db};
+use crate::error::{Error, Result};
+use crate::repository::activities;
+use crate::timezone;
+
+/// Value of [`Archive::format`].
+pub const FORMAT: &str = "10k-hours-backup";
+
+/// Bumped when the archive layout itself changes.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Tables in the order they are restored, parents before the rows that
+/// reference them. `active_timer` is left out: a running session belongs to
+/// the process that started it.
+pub const TABLES: &[&str] = &[
+    "user_settings",
+    "skills",
+    "subskills",
+    "tags",
+    "tasks",
+    "subtasks",
+    "task_dependencies",
+    "task_tags",
+    "timer_sessions",
+    "session_tags",
+    "daily_activities",
+    "reflections",
+    "reflection_skills",
+    "reflection_tags",
+    "achievements",
+];
+
+/// Columns left out of archives and restores: the local API's bearer token
+/// and where it listens, and the Spotify session. A shared backup file must
+/// not hand these out.
+pub const PRIVATE_COLUMNS: &[(&str, &[&str])] = &[(
+    "user_settings",
+    &[
+        "api_enabled",
+        "api_port",
+        "api_token",
+        "spotify_access_token",
+        "spotify_refresh_token",
+        "spotify_token_expiry",
+    ],
+)];
+
+pub type Row = Map<String, Value>;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Archive {
+    pub format: String,
+    pub format_version: u32,
+    pub app_version: String,
+    pub schema_version: i64,
+    pub created_at: String,
+    pub tables: Vec<TableDump>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableDump {
+    pub name: String,
+    pub checksum: String,
+    pub rows: Vec<Row>,
+}
+
+/// What to do with a row whose primary key exists with different values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictPolicy {
+    /// Keep the row already in the database.
+    #[default]
+    Skip,
+    /// Overwrite it with the row from the archive.
+    Replace,
+    /// Abort the restore without changing anything.
+    Fail,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreOptions {
+    #[serde(default)]
+    pub on_conflict: ConflictPolicy,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableChanges {
+    pub table: String,
+    pub added: i64,
+    pub updated: i64,
+    pub unchanged: i64,
+    pub skipped: i64,
+    /// Primary keys of rows that differ from the database, joined with `/`.
+    pub conflicts: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreReport {
+    pub dry_run: bool,
+    pub tables: Vec<TableChanges>,
+}
+
+/// Hex SHA-256 of the rows as compact JSON.
+pub fn checksum(rows: &[Row]) -> String {
+    let json = serde_json::to_vec(rows).expect("JSON values always serialize");
+    format!("{:x}", Sha256::digest(json))
+}
+
+impl Archive {
+    /// Checks the header and every table checksum.
+    pub fn verify(&self) -> Result<()> {
+        if self.format != FORMAT {
+            return Err(Error::Invalid("not a 10,000 hours backup".into()));
+        }
+        if self.format_version > FORMAT_VERSION {
+            return Err(Error::Invalid(format!(
+                "backup format {} is newer than this app understands",
+                self.format_version
+            )));
+        }
+        for table in &self.tables {
+            if !TABLES.contains(&table.name.as_str()) {
+                return Err(Error::Invalid(format!("unknown table `{}`", table.name)));
+            }
+            if checksum(&table.rows) != table.checksum {
+                return Err(Error::Invalid(format!(
+                    "checksum mismatch in `{}`; the backup is damaged",
+                    table.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn table(&self, name: &str) -> Option<&TableDump> {
+        self.tables.iter().find(|table| table.name == name)
+    }
+}
+
+struct Column {
+    name: String,
+    primary_key: bool,
+}
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// `json_extract` path for a top-level key.
+fn path(name: &str) -> String {
+    format!("'$.\"{}\"'", name.replace('\'', "''").replace('"', "\\\""))
+}
+
+/// The columns of `table` that archives carry, leaving out [`PRIVATE_COLUMNS`].
+async fn columns(conn: &mut SqliteConnection, table: &str) -> Result<Vec<Column>> {
+    let rows: Vec<(String, i64)> =
+        sqlx::query_as("SELECT name, pk FROM pragma_table_info(?) ORDER BY cid")
+            .bind(table)
+            .fetch_all(&mut *conn)
+            .await?;
+    if rows.is_empty() {
+        return Err(Error::Invalid(format!("table `{table}` does not exist")));
+    }
+    let private = PRIVATE_COLUMNS
+        .iter()
+        .find(|(name, _)| *name == table)
+        .map_or(&[][..], |(_, columns)| *columns);
+    Ok(rows
+        .into_iter()
+        .filter(|(name, _)| !private.contains(&name.as_str()))
+        .map(|(name, pk)| Column {
+            name,
+            primary_key: pk > 0,
+        })
+        .collect())
+}
+
+fn json_object(columns: &[Column]) -> String {
+    let pairs: Vec<String> = columns
+        .iter()
+        .map(|column| {
+            format!(
+                "'{}', {}",
+                column.name.replace('\'', "''"),
+                quote(&column.name)
+            )
+        })
+        .collect();
+    format!("json_object({})", pairs.join(", "))
+}
+
+/// `pk1 IS json_extract(?1, '$.pk1') AND …`. Every table in [`TABLES`] has a
+/// primary key.
+fn key_filter(columns: &[Column]) -> String {
+    let keys: Vec<String> = columns
+        .iter()
+        .filter(|column| column.primary_key)
+        .map(|column| {
+            format!(
+                "{} IS json_extract(?1, {})",
+                quote(&column.name),
+                path(&column.name)
+            )
+        })
+        .collect();
+    keys.join(" AND ")
+}
+
+fn key_label(columns: &[Column], row: &Row) -> String {
+    columns
+        .iter()
+        .filter(|column| column.primary_key)
+        .map(|column| match row.get(&column.name) {
+            Some(Value::String(text)) => text.clone(),
+            Some(value) => value.to_string(),
+            None => "null".into(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+async fn schema_version(conn: &mut SqliteConnection) -> Result<i64> {
+    let (version,): (Option<i64>,) =
+        sqlx::query_as("SELECT MAX(version) FROM _sqlx_migrations WHERE success = 1")
+            .fetch_one(&mut *conn)
+            .await?;
+    Ok(version.unwrap_or(0))
+}
+
+async fn dump_table(conn: &mut SqliteConnection, table: &str) -> Result<TableDump> {
+    let columns = columns(conn, table).await?;
+    let order: Vec<String> = columns
+        .iter()
+        .filter(|column| column.primary_key)
+        .map(|column| quote(&column.name))
+        .collect();
+    let order = order.join(", ");
+    let json: Vec<(String,)> = sqlx::query_as(&format!(
+        "SELECT {} FROM {} ORDER BY {order}",
+        json_object(&columns),
+        quote(table)
+    ))
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let rows = json
+        .into_iter()
+        .map(|(text,)| serde_json::from_str(&text))
+        .collect::<std::result::Result<Vec<Row>, _>>()
+        .map_err(|err| Error::Invalid(format!("could not read `{table}`: {err}")))?;
+    Ok(TableDump {
+        name: table.to_owned(),
+        checksum: checksum(&rows),
+        rows,
+    })
+}
+
+/// Takes a consistent snapshot of every table in [`TABLES`].
+pub async fn create(pool: &SqlitePool) -> Result<Archive> {
+    let mut tx = pool.begin().await?;
+    let schema_version = schema_version(&mut tx).await?;
+    let mut tables = Vec::with_capacity(TABLES.len());
+    for table in TABLES {
+        tables.push(dump_table(&mut tx, table).await?);
+    }
+    tx.rollback().await?;
+
+    Ok(Archive {
+        format: FORMAT.into(),
+        format_version: FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").into(),
+        schema_version,
+        created_at: database::now_iso(),
+        tables,
+    })
+}
+
+async fn restore_table(
+    conn: &mut SqliteConnection,
+    dump: &TableDump,
+    policy: ConflictPolicy,
+) -> Result<(TableChanges, Vec<Row>)> {
+    let columns = columns(conn, &dump.name).await?;
+    let table = quote(&dump.name);
+    let filter = key_filter(&columns);
+    let select = format!(
+        "SELECT {} FROM {table} WHERE {filter}",
+        json_object(&columns)
+    );
+    let mut changes = TableChanges {
+        table: dump.name.clone(),
+        ..Default::default()
+    };
+    let mut written = Vec::new();
+
+    for row in &dump.rows {
+        // Columns the archive does not have keep their defaults; columns
+        // this schema no longer has are dropped.
+        let present: Vec<&Column> = columns
+            .iter()
+            .filter(|column| row.contains_key(&column.name))
+            .collect();
+        let incoming: Row = present
+            .iter()
+            .map(|column| (column.name.clone(), row[&column.name].clone()))
+            .collect();
+        let json = Value::Object(incoming.clone()).to_string();
+
+        let existing: Option<(String,)> = sqlx::query_as(&select)
+            .bind(&json)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+        if let Some((existing,)) = existing {
+            let existing: Row = serde_json::from_str(&existing)
+                .map_err(|err| Error::Invalid(format!("could not read `{}`: {err}", dump.name)))?;
+            if incoming
+                .iter()
+                .all(|(name, value)| existing.get(name) == Some(value))
+            {
+                changes.unchanged += 1;
+                continue;
+            }
+            changes.conflicts.push(key_label(&columns, row));
+            match policy {
+                ConflictPolicy::Skip => changes.skipped += 1,
+                ConflictPolicy::Fail => {
+                    return Err(Error::Invalid(format!(
+                        "`{}` row {} differs from the backup",
+                        dump.name,
+                        key_label(&columns, row)
+                    )))
+                }
+                ConflictPolicy::Replace => {
+                    // An UPDATE rather than INSERT OR REPLACE, so the
+                    // aggregate triggers see the old row leave.
+                    let assignments: Vec<String> = present
+                        .iter()
+                        .filter(|column| !column.primary_key)
+                        .map(|column| {
+                            format!(
+                                "{} = json_extract(?1, {})",
+                                quote(&column.name),
+                                path(&column.name)
+                            )
+                        })
+                        .collect();
+                    // OR IGNORE leaves the row as it is when the new values
+                    // collide with another row on a UNIQUE column.
+                    let updated = if assignments.is_empty() {
+                        1
+                    } else {
+                        sqlx::query(&format!(
+                            "UPDATE OR IGNORE {table} SET {} WHERE {filter}",
+                            assignments.join(", ")
+                        ))
+                        .bind(&json)
+                        .execute(&mut *conn)
+                        .await?
+                        .rows_affected()
+                    };
+                    if updated == 0 {
+                        changes.skipped += 1;
+                    } else {
+                        changes.updated += 1;
+                        written.push(incoming);
+                    }
+                }
+            }
+            continue;
+        }
+
+        let names: Vec<String> = present.iter().map(|column| quote(&column.name)).collect();
+        let values: Vec<String> = present
+            .iter()
+            .map(|column| format!("json_extract(?1, {})", path(&column.name)))
+            .collect();
+        // A row can still collide on another UNIQUE column, such as a
+        // reflection for a date that already has one under another id.
+        // Replacing that row would delete its children with it, so the
+        // archive's row is skipped instead.
+        let verb = match policy {
+            ConflictPolicy::Skip | ConflictPolicy::Replace => "INSERT OR IGNORE",
+            ConflictPolicy::Fail => "INSERT",
+        };
+        let inserted = sqlx::query(&format!(
+            "{verb} INTO {table} ({}) VALUES ({})",
+            names.join(", "),
+            values.join(", ")
+        ))
+        .bind(&json)
+        .execute(&mut *conn)
+        .await;
+        let inserted = match inserted {
+            Ok(done) => done.rows_affected(),
+            // The row it belongs to was skipped above, so it goes too.
+            Err(sqlx::Error::Database(err))
+                if err.is_foreign_key_violation() && policy != ConflictPolicy::Fail =>
+            {
+                0
+            }
+            Err(err) => {
+                return Err(Error::Invalid(format!(
+                    "`{}` row {} cannot be restored: {err}",
+                    dump.name,
+                    key_label(&columns, row)
+                )))
+            }
+        };
+        if inserted == 0 {
+            changes.conflicts.push(key_label(&columns, row));
+            changes.skipped += 1;
+        } else {
+            changes.added += 1;
+            written.push(incoming);
+        }
+    }
+
+    Ok((changes, written))
+}
+
+/// Restores `archive` into `pool`. See the module docs for how rows are
+/// matched.
+pub async fn restore(
+    pool: &SqlitePool,
+    archive: &Archive,
+    options: RestoreOptions,
+) -> Result<RestoreReport> {
+    archive.verify()?;
+
+    let mut tx = pool.begin().await?;
+    let current = schema_version(&mut tx).await?;
+    if archive.schema_version > current {
+        return Err(Error::Invalid(format!(
+            "backup is from schema version {}, newer than this app's {current}; update the app first",
+            archive.schema_version
+        )));
+    }
+
+    let mut tables = Vec::new();
+    let mut skills = Vec::new();
+    for name in TABLES {
+        let Some(dump) = archive.table(name) else {
+            continue;
+        };
+        let (changes, written) = restore_table(&mut tx, dump, options.on_conflict).await?;
+        if *name == "skills" {
+            skills = written;
+        }
+        tables.push(changes);
+    }
+
+    // Restored sessions went through the aggregate triggers and were added
+    // on top of the skill totals restored before them. A skill the archive
+    // wrote keeps the minutes it had without sessions behind them, plus
+    // those of every session now here, whichever side it came from.
+    let archived = session_minutes(archive);
+    for skill in skills {
+        let (Some(id), Some(minutes)) = (
+            skill.get("id").and_then(Value::as_str),
+            skill.get("current_minutes").and_then(Value::as_i64),
+        ) else {
+            continue;
+        };
+        sqlx::query(
+            "UPDATE skills SET current_minutes = ?1 + (
+                SELECT COALESCE(SUM(duration), 0) FROM timer_sessions
+                WHERE skill_id = ?2 AND completed = 1 AND type IN ('pomodoro', 'adjustment')
+             )
+             WHERE id = ?2",
+        )
+        .bind(minutes - archived.get(id).copied().unwrap_or(0))
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    }
+    // Days the archive overwrote may have lost sessions that are only here.
+    activities::recompute_in(&mut tx).await?;
+
+    if options.dry_run {
+        tx.rollback().await?;
+    } else {
+        tx.commit().await?;
+        // Sessions from archives older than migration 13 have no local day,
+        // and the restored settings may name another zone.
+        timezone::rebucket(pool).await?;
+    }
+    Ok(RestoreReport {
+        dry_run: options.dry_run,
+        tables,
+    })
+}
+
+/// Minutes per skill of the archive's counted sessions, the ones the
+/// aggregate triggers add to `skills.current_minutes`.
+fn session_minutes(archive: &Archive) -> HashMap<&str, i64> {
+    let mut minutes = HashMap::new();
+    let rows = archive
+        .table("timer_sessions")
+        .map(|dump| dump.rows.as_slice())
+        .unwrap_or_default();
+    for row in rows {
+        let counted = row.get("completed").and_then(Value::as_i64) == Some(1)
+            && matches!(
+                row.get("type").and_then(Value::as_str),
+                Some("pomodoro" | "adjustment")
+            );
+        if let (true, Some(skill), Some(duration)) = (
+            counted,
+            row.get("skill_id").and_then(Value::as_str),
+            row.get("duration").and_then(Value::as_i64),
+        ) {
+            *minutes.entry(skill).or_insert(0) += duration;
+        }
+    }
+    minutes
+}
+
+/// Writes a fresh archive of `pool` to `path` as pretty-printed JSON.
+pub async fn write(pool: &SqlitePool, path: &Path) -> Result<Archive> {
+    let archive = create(pool).await?;
+    let json = serde_json::to_vec_pretty(&archive).expect("archives always serialize");
+    std::fs::write(path, json)?;
+    Ok(archive)
+}
+
+/// Reads and verifies an archive written by [`write`].
+pub fn read(path: &Path) -> Result<Archive> {
+    let archive: Archive = serde_json::from_slice(&std::fs::read(path)?)
+        .map_err(|err| Error::Invalid(format!("not a readable backup: {err}")))?;
+    archive.verify()?;
+    Ok(archive)
+}
+
+#[tauri::command]
+pub async fn create_backup(db: State<'_, Db>) -> Result<Archive> {
+    create(&db.0).await
+}
+
+#[tauri::command]
+pub async fn restore_backup(
+    app: AppHandle,
+    db: State<'_, Db>,
+    archive: Archive,
+    options: RestoreOptions,
+) -> Result<RestoreReport> {
+    let report = restore(&db.0, &archive, options).await?;
+    if !report.dry_run {
+        unlocks::refresh(&app).await;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive() -> Archive {
+        let rows: Vec<Row> =
+            vec![serde_json::from_str(r#"{"id":"skill_1","name":"Piano"}"#).unwrap()];
+        Archive {
+            format: FORMAT.into(),
+            format_version: FORMAT_VERSION,
+            app_version: "0.1.0".into(),
+            schema_version: 12,
+            created_at: "2024-05-01T10:00:00.000Z".into(),
+            tables: vec![TableDump {
+                name: "skills".into(),
+                checksum: checksum(&rows),
+                rows,
+            }],
+        }
+    }
+
+    #[test]
+    fn verify_accepts_an_untouched_archive() {
+        assert!(archive().verify().is_ok());
+    }
+
+    #[test]
+    fn verify_catches_edited_rows() {
+        let mut edited = archive();
+        edited.tables[0].rows[0].insert("name".into(), "Violin".into());
+        let err = edited.verify().unwrap_err().to_string();
+        assert!(err.contains("checksum mismatch in `skills`"), "{err}");
+    }
+
+    #[test]
+    fn verify_rejects_foreign_and_future_files() {
+        let mut other = archive();
+        other.format = "something-else".into();
+        assert!(other.verify().is_err());
+
+        let mut future = archive();
+        future.format_version = FORMAT_VERSION + 1;
+        assert!(future.verify().is_err());
+
+        let mut unknown = archive();
+        unknown.tables[0].name = "sqlite_master".into();
+        assert!(unknown.verify().is_err());
+    }
+
+    #[test]
+    fn json_paths_quote_column_names() {
+        assert_eq!(path("type"), r#"'$."type"'"#);
+        assert_eq!(quote("order"), r#""order""#);
+    }
+}
@@ -0,0 +1,498 @@
+//! `tenk`: the tracker from a terminal.
+//!
+//! Opens the app's own `app.db` (or `$TENK_DB`) and applies migrations the
+//! same way the app does. Sessions are claimed through the `active_timer`
+//! table, so `tenk` and a running app never have overlapping sessions. A
+//! session started here has no countdown; it runs until `tenk stop` and is
+//! credited up to its planned length.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+use ten_k_hours_app_lib::achievements;
+use ten_k_hours_app_lib::analytics;
+use ten_k_hours_app_lib::backup::{self, ConflictPolicy, RestoreOptions};
+use ten_k_hours_app_lib::database;
+use ten_k_hours_app_lib::error::{Error, Result};
+use ten_k_hours_app_lib::integrity;
+use ten_k_hours_app_lib::repository::sessions::{self, NewSession, Owner, Running};
+use ten_k_hours_app_lib::repository::skills::{self, SkillRecord};
+use ten_k_hours_app_lib::repository::tasks::{self, TaskRecord};
+use ten_k_hours_app_lib::snapshot;
+use ten_k_hours_app_lib::timer::{TimerSettings, TimerType};
+use ten_k_hours_app_lib::timezone;
+
+const USAGE: &str = "\
+usage: tenk <command>
+
+commands:
+  start <skill> [task]     start a pomodoro, optionally on a task
+  stop                     stop the running session and keep the time worked
+  status                   show the running session
+  log <duration> <skill>   record time by hand, e.g. `tenk log 45m guitar`
+  skills                   list skills and their progress
+  report --week            summarise this week's practice
+  backup <file>            write every table to a backup archive
+  restore <file> [--dry-run] [--replace | --fail]
+                           restore an archive; rows that differ are kept
+                           unless --replace is given, --fail stops on them
+  snapshots                list the automatic database snapshots
+  snapshot [restore <name>]
+                           take a snapshot now, or go back to an earlier one
+  downgrade <version>      revert the schema for an older app version; any
+                           later tenk command or app start upgrades it again
+
+Skills and tasks are matched by id, by name, or by an unambiguous prefix.
+
+environment:
+  TENK_DB                  database to use instead of the app's app.db";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match tauri::async_runtime::block_on(run(&args)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("tenk: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(args: &[String]) -> Result<()> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    if matches!(args.as_slice(), [] | ["help" | "-h" | "--help"]) {
+        println!("{USAGE}");
+        return Ok(());
+    }
+
+    let pool = open().await?;
+    match args.as_slice() {
+        ["start", skill] => start(&pool, skill, None).await,
+        ["start", skill, task @ ..] => start(&pool, skill, Some(&task.join(" "))).await,
+        ["stop"] => stop(&pool).await,
+        ["status"] => status(&pool).await,
+        ["log", duration, skill @ ..] if !skill.is_empty() => {
+            log(&pool, duration, &skill.join(" ")).await
+        }
+        ["skills"] => list_skills(&pool).await,
+        ["report"] | ["report", "--week"] => report(&pool).await,
+        ["backup", file] => save_backup(&pool, Path::new(file)).await,
+        ["restore", file, flags @ ..] => restore(&pool, Path::new(file), flags).await,
+        ["snapshots"] => list_snapshots(),
+        ["snapshot"] => take_snapshot(&pool).await,
+        ["snapshot", "restore", name] => restore_snapshot(&pool, name).await,
+        ["downgrade", version] => downgrade(&pool, version).await,
+        _ => Err(Error::Invalid(format!("unrecognised arguments\n\n{USAGE}"))),
+    }
+}
+
+fn db_path() -> Result<PathBuf> {
+    match env::var_os("TENK_DB") {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => database::default_path().ok_or_else(|| {
+            Error::Invalid("no config directory on this system; set TENK_DB".into())
+        }),
+    }
+}
+
+async fn open() -> Result<SqlitePool> {
+    let pool = database::open(&db_path()?).await?;
+    for orphan in integrity::repair(&pool).await? {
+        eprintln!("tenk: {orphan}");
+    }
+    timezone::init(&pool).await?;
+    Ok(pool)
+}
+
+// ============ MATCHING ============
+
+/// Picks the one item whose id or name is `query`, or failing that, whose
+/// name starts with it. Case is ignored.
+fn pick<'a, T>(
+    items: &'a [T],
+    query: &str,
+    what: &str,
+    key: impl Fn(&T) -> (&str, &str),
+) -> Result<&'a T> {
+    let query = query.to_lowercase();
+    if let Some(item) = items.iter().find(|item| {
+        let (id, name) = key(item);
+        id == query || name.to_lowercase() == query
+    }) {
+        return Ok(item);
+    }
+
+    let matches: Vec<&T> = items
+        .iter()
+        .filter(|item| key(item).1.to_lowercase().starts_with(&query))
+        .collect();
+    match matches.as_slice() {
+        [item] => Ok(item),
+        [] => Err(Error::Invalid(format!("no {what} matches `{query}`"))),
+        _ => {
+            let names: Vec<&str> = matches.iter().map(|item| key(item).1).collect();
+            Err(Error::Invalid(format!(
+                "`{query}` matches more than one {what}: {}",
+                names.join(", ")
+            )))
+        }
+    }
+}
+
+async fn find_skill(pool: &SqlitePool, query: &str) -> Result<SkillRecord> {
+    let skills = skills::list(pool).await?;
+    pick(&skills, query, "skill", |s| (&s.id, &s.name)).cloned()
+}
+
+async fn find_task(pool: &SqlitePool, skill: &SkillRecord, query: &str) -> Result<TaskRecord> {
+    let tasks: Vec<TaskRecord> = tasks::list(pool, Some(&skill.id))
+        .await?
+        .into_iter()
+        .filter(|task| task.status != "completed")
+        .collect();
+    pick(&tasks, query, "open task", |t| (&t.id, &t.title)).cloned()
+}
+
+// ============ FORMATTING ============
+
+/// Parses `45m`, `1h`, `1h30m` or a bare number of minutes. `None` for
+/// anything else, including totals too large to hold.
+fn parse_minutes(text: &str) -> Option<i64> {
+    if let Ok(minutes) = text.parse::<i64>() {
+        return Some(minutes).filter(|minutes| *minutes > 0);
+    }
+    let (hours, rest) = match text.split_once('h') {
+        Some((hours, rest)) => (hours.parse::<i64>().ok()?, rest),
+        None => (0, text),
+    };
+    let minutes = match rest {
+        "" => 0,
+        rest => rest.strip_suffix('m')?.parse::<i64>().ok()?,
+    };
+    hours
+        .checked_mul(60)?
+        .checked_add(minutes)
+        .filter(|total| *total > 0)
+}
+
+fn hours_minutes(minutes: i64) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{m}m"),
+        (h, 0) => format!("{h}h"),
+        (h, m) => format!("{h}h {m:02}m"),
+    }
+}
+
+fn elapsed_minutes(start_time: &str) -> i64 {
+    DateTime::parse_from_rfc3339(start_time)
+        .map(|start| (Utc::now() - start.with_timezone(&Utc)).num_minutes())
+        .unwrap_or(0)
+        .max(0)
+}
+
+async fn announce_achievements(pool: &SqlitePool) -> Result<()> {
+    for achievement in achievements::evaluate(pool, false).await? {
+        println!("Achievement unlocked: {}", achievement.name);
+    }
+    Ok(())
+}
+
+// ============ COMMANDS ============
+
+async fn start(pool: &SqlitePool, skill: &str, task: Option<&str>) -> Result<()> {
+    let skill = find_skill(pool, skill).await?;
+    let task = match task {
+        Some(task) => Some(find_task(pool, &skill, task).await?),
+        None => None,
+    };
+    let planned = TimerSettings::load(pool)
+        .await?
+        .minutes(TimerType::Pomodoro);
+
+    sessions::start(
+        pool,
+        &NewSession {
+            owner: Owner::Cli,
+            id: &database::generate_id("session"),
+            task_id: task.as_ref().map(|t| t.id.as_str()),
+            skill_id: &skill.id,
+            start_time: &database::now_iso(),
+            planned_minutes: planned,
+            kind: TimerType::Pomodoro.as_str(),
+        },
+    )
+    .await?;
+
+    match task {
+        Some(task) => println!("Started {planned}m on {} ({}).", skill.name, task.title),
+        None => println!("Started {planned}m on {}.", skill.name),
+    }
+    Ok(())
+}
+
+async fn stop(pool: &SqlitePool) -> Result<()> {
+    let Some(Running { owner, session }) = sessions::running(pool).await? else {
+        println!("No session is running.");
+        return Ok(());
+    };
+    if owner == Owner::App {
+        return Err(Error::Timer("running in the app; stop it there"));
+    }
+
+    let planned = session.planned_duration.unwrap_or(session.duration);
+    let worked = elapsed_minutes(&session.start_time).min(planned);
+    if session.kind == TimerType::Pomodoro.as_str() && worked > 0 {
+        sessions::complete(pool, &session.id, worked, worked >= planned).await?;
+        println!("Stopped after {}.", hours_minutes(worked));
+        announce_achievements(pool).await?;
+    } else {
+        sessions::delete(pool, &session.id).await?;
+        println!("Stopped; nothing to record.");
+    }
+    Ok(())
+}
+
+async fn status(pool: &SqlitePool) -> Result<()> {
+    let Some(Running { owner, session }) = sessions::running(pool).await? else {
+        println!("No session is running.");
+        return Ok(());
+    };
+
+    let skill = skills::get(pool, &session.skill_id).await?;
+    let task = match &session.task_id {
+        Some(id) => tasks::get(pool, id).await?,
+        None => None,
+    };
+    let planned = session.planned_duration.unwrap_or(session.duration);
+    let elapsed = elapsed_minutes(&session.start_time);
+
+    let mut line = format!(
+        "{} on {}",
+        session.kind,
+        skill.map_or(session.skill_id.clone(), |s| s.name)
+    );
+    if let Some(task) = task {
+        line.push_str(&format!(" ({})", task.title));
+    }
+    let source = match owner {
+        Owner::App => "in the app",
+        Owner::Cli => "from tenk",
+    };
+    println!(
+        "{line}: {} of {} elapsed, started {source}.",
+        hours_minutes(elapsed),
+        hours_minutes(planned)
+    );
+    if owner == Owner::Cli && elapsed >= planned {
+        println!("Time is up; run `tenk stop` to record it.");
+    }
+    Ok(())
+}
+
+async fn log(pool: &SqlitePool, duration: &str, skill: &str) -> Result<()> {
+    let minutes = parse_minutes(duration).ok_or_else(|| {
+        Error::Invalid(format!("`{duration}` is not a duration like 45m or 1h30m"))
+    })?;
+    let skill = find_skill(pool, skill).await?;
+    sessions::record_manual(pool, &skill.id, None, minutes).await?;
+
+    let total = skills::get(pool, &skill.id)
+        .await?
+        .map_or(0, |s| s.current_minutes);
+    println!(
+        "Logged {} on {} ({} total).",
+        hours_minutes(minutes),
+        skill.name,
+        hours_minutes(total)
+    );
+    announce_achievements(pool).await
+}
+
+async fn list_skills(pool: &SqlitePool) -> Result<()> {
+    let skills = skills::list(pool).await?;
+    if skills.is_empty() {
+        println!("No skills yet.");
+    }
+    let width = skills.iter().map(|s| s.name.len()).max().unwrap_or(0);
+    for skill in skills {
+        let goal = skill.goal_hours * 60;
+        let percent = if goal > 0 {
+            skill.current_minutes as f64 * 100.0 / goal as f64
+        } else {
+            0.0
+        };
+        println!(
+            "{} {:width$}  {:>10} / {}h  {percent:5.1}%",
+            if skill.is_active { '*' } else { ' ' },
+            skill.name,
+            hours_minutes(skill.current_minutes),
+            skill.goal_hours,
+        );
+    }
+    Ok(())
+}
+
+async fn report(pool: &SqlitePool) -> Result<()> {
+    let streaks = analytics::practice_streaks(pool, None, None).await?;
+    let Some(week) = analytics::weekly_stats(pool, 1, None).await?.pop() else {
+        println!("No practice this week yet.");
+        println!(
+            "Streak: {} days (longest {}).",
+            streaks.current, streaks.longest
+        );
+        return Ok(());
+    };
+
+    println!(
+        "Week of {}: {} over {} sessions.",
+        week.week_start,
+        hours_minutes(week.total_minutes),
+        week.total_sessions
+    );
+    let width = week
+        .skill_breakdown
+        .iter()
+        .map(|s| s.skill_name.len())
+        .max()
+        .unwrap_or(0);
+    for share in &week.skill_breakdown {
+        println!(
+            "  {:width$}  {:>8}  {:5.1}%",
+            share.skill_name,
+            hours_minutes(share.minutes),
+            share.percentage
+        );
+    }
+    println!(
+        "Streak: {} days (longest {}).",
+        streaks.current, streaks.longest
+    );
+    Ok(())
+}
+
+async fn save_backup(pool: &SqlitePool, path: &Path) -> Result<()> {
+    let archive = backup::write(pool, path).await?;
+    let rows: usize = archive.tables.iter().map(|table| table.rows.len()).sum();
+    println!(
+        "Wrote {rows} rows from {} tables to {}.",
+        archive.tables.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+async fn restore(pool: &SqlitePool, path: &Path, flags: &[&str]) -> Result<()> {
+    let mut options = RestoreOptions::default();
+    for flag in flags {
+        match *flag {
+            "--dry-run" => options.dry_run = true,
+            "--replace" => options.on_conflict = ConflictPolicy::Replace,
+            "--fail" => options.on_conflict = ConflictPolicy::Fail,
+            other => return Err(Error::Invalid(format!("unknown option `{other}`"))),
+        }
+    }
+
+    let report = backup::restore(pool, &backup::read(path)?, options).await?;
+    let width = report
+        .tables
+        .iter()
+        .map(|t| t.table.len())
+        .max()
+        .unwrap_or(0);
+    for changes in &report.tables {
+        println!(
+            "  {:width$}  {:>5} new  {:>5} replaced  {:>5} kept  {:>5} same",
+            changes.table, changes.added, changes.updated, changes.skipped, changes.unchanged
+        );
+    }
+    if report.dry_run {
+        println!("Dry run; nothing was changed.");
+    } else {
+        announce_achievements(pool).await?;
+    }
+    Ok(())
+}
+
+fn list_snapshots() -> Result<()> {
+    let snapshots = snapshot::list(&snapshot::dir(&db_path()?))?;
+    if snapshots.is_empty() {
+        println!("No snapshots yet.");
+    }
+    for snapshot in snapshots {
+        println!(
+            "{}  {:>8} KiB  {}",
+            snapshot.taken_at.format("%Y-%m-%d %H:%M"),
+            snapshot.size / 1024,
+            snapshot.name
+        );
+    }
+    Ok(())
+}
+
+async fn take_snapshot(pool: &SqlitePool) -> Result<()> {
+    let dir = snapshot::dir(&db_path()?);
+    let taken = snapshot::take(pool, &dir, "manual").await?;
+    snapshot::prune(&dir, snapshot::Retention::default())?;
+    println!("Saved {}.", dir.join(taken.name).display());
+    Ok(())
+}
+
+async fn restore_snapshot(pool: &SqlitePool, name: &str) -> Result<()> {
+    let restored = snapshot::restore(pool, &snapshot::dir(&db_path()?), name).await?;
+    println!(
+        "Restored the snapshot from {}. The data it replaced was snapshotted first.",
+        restored.taken_at.format("%Y-%m-%d %H:%M")
+    );
+    Ok(())
+}
+
+async fn downgrade(pool: &SqlitePool, version: &str) -> Result<()> {
+    let version: i64 = version
+        .parse()
+        .map_err(|_| Error::Invalid(format!("`{version}` is not a schema version")))?;
+    let snapshot = database::downgrade(pool, &db_path()?, version).await?;
+    println!(
+        "Reverted the database to schema version {version}. The data before was saved as {}.",
+        snapshot.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_minutes("45"), Some(45));
+        assert_eq!(parse_minutes("45m"), Some(45));
+        assert_eq!(parse_minutes("2h"), Some(120));
+        assert_eq!(parse_minutes("1h30m"), Some(90));
+        assert_eq!(parse_minutes("0m"), None);
+        assert_eq!(parse_minutes("1h30"), None);
+        assert_eq!(parse_minutes("guitar"), None);
+        assert_eq!(parse_minutes("153722867280912931h"), None);
+        assert_eq!(
+            parse_minutes("153722867280912930h9223372036854775807m"),
+            None
+        );
+    }
+
+    #[test]
+    fn picks_exact_then_unique_prefix() {
+        fn key<'a>(item: &'a (&str, &str)) -> (&'a str, &'a str) {
+            (item.0, item.1)
+        }
+        let items = [("a", "Guitar"), ("b", "Go"), ("c", "Piano")];
+
+        assert_eq!(pick(&items, "go", "skill", key).unwrap().0, "b");
+        assert_eq!(pick(&items, "pi", "skill", key).unwrap().0, "c");
+        assert_eq!(pick(&items, "a", "skill", key).unwrap().0, "a");
+        assert!(pick(&items, "g", "skill", key).is_err());
+        assert!(pick(&items, "drums", "skill", key).is_err());
+    }
+}
@@ -0,0 +1,361 @@
+//! Tauri commands exposing the [`repository`](crate::repository) layer to the
+//! webview, which no longer gets raw SQL access.
+//!
+//! Commands that change practice data re-evaluate achievements once the
+//! change is committed.
+
+use tauri::{AppHandle, State};
+
+use crate::achievements as unlocks;
+use crate::database::Db;
+use crate::error::Result;
+use crate::repository::achievements::{self, AchievementRecord, CreateAchievementInput};
+use crate::repository::activities::{self, DailyActivityRecord, ProfileStats};
+use crate::repository::data::{self, DataExport};
+use crate::repository::dependencies::{self, DependencyRecord};
+use crate::repository::reflections::{self, ReflectionWithSkills, SaveReflectionInput};
+use crate::repository::sessions::{
+    self, DayMinutes, SkillMinutes, TimerSessionRecord, UpdateSessionInput,
+};
+use crate::repository::settings::{self, UpdateSettingsInput, UserSettingsRecord};
+use crate::repository::skills::{self, CreateSkillInput, SkillRecord, UpdateSkillInput};
+use crate::repository::subskills::{self, SkillRollup, SubskillRecord};
+use crate::repository::subtasks::{self, Rollup, SubtaskRecord};
+use crate::repository::tags::{self, CreateTagInput, TagLink, TagRecord, Tagged, UpdateTagInput};
+use crate::repository::tasks::{self, CreateTaskInput, TaskRecord, UpdateTaskInput};
+
+// Skills
+
+#[tauri::command]
+pub async fn list_skills(db: State<'_, Db>) -> Result<Vec<SkillRecord>> {
+    skills::list(&db.0).await
+}
+
+#[tauri::command]
+pub async fn create_skill(
+    app: AppHandle,
+    db: State<'_, Db>,
+    input: CreateSkillInput,
+) -> Result<SkillRecord> {
+    let skill = skills::create(&db.0, input).await?;
+    unlocks::refresh(&app).await;
+    Ok(skill)
+}
+
+#[tauri::command]
+pub async fn update_skill(db: State<'_, Db>, input: UpdateSkillInput) -> Result<SkillRecord> {
+    skills::update(&db.0, input).await
+}
+
+#[tauri::command]
+pub async fn delete_skill(db: State<'_, Db>, id: String) -> Result<()> {
+    skills::delete(&db.0, &id).await
+}
+
+#[tauri::command]
+pub async fn set_active_skill(db: State<'_, Db>, id: Option<String>) -> Result<()> {
+    skills::set_active(&db.0, id.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn list_subskills(db: State<'_, Db>) -> Result<Vec<SubskillRecord>> {
+    subskills::list(&db.0).await
+}
+
+#[tauri::command]
+pub async fn set_skill_parent(
+    app: AppHandle,
+    db: State<'_, Db>,
+    skill_id: String,
+    parent_id: Option<String>,
+) -> Result<()> {
+    subskills::set_parent(&db.0, &skill_id, parent_id.as_deref()).await?;
+    // A parent now counts its new sub-skill's time toward mastery.
+    unlocks::refresh(&app).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn skill_rollups(db: State<'_, Db>) -> Result<Vec<SkillRollup>> {
+    subskills::rollups(&db.0).await
+}
+
+// Tasks
+
+#[tauri::command]
+pub async fn list_tasks(db: State<'_, Db>, skill_id: Option<String>) -> Result<Vec<TaskRecord>> {
+    tasks::list(&db.0, skill_id.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn create_task(db: State<'_, Db>, input: CreateTaskInput) -> Result<TaskRecord> {
+    tasks::create(&db.0, input).await
+}
+
+#[tauri::command]
+pub async fn update_task(db: State<'_, Db>, input: UpdateTaskInput) -> Result<TaskRecord> {
+    tasks::update(&db.0, input).await
+}
+
+#[tauri::command]
+pub async fn set_task_status(db: State<'_, Db>, id: String, status: String) -> Result<TaskRecord> {
+    tasks::set_status(&db.0, &id, &status).await
+}
+
+#[tauri::command]
+pub async fn delete_task(db: State<'_, Db>, id: String) -> Result<()> {
+    tasks::delete(&db.0, &id).await
+}
+
+#[tauri::command]
+pub async fn list_task_series(db: State<'_, Db>, series_id: String) -> Result<Vec<TaskRecord>> {
+    tasks::series(&db.0, &series_id).await
+}
+
+#[tauri::command]
+pub async fn reorder_tasks(db: State<'_, Db>, ids: Vec<String>) -> Result<()> {
+    tasks::reorder(&db.0, &ids).await
+}
+
+// Subtasks and dependencies
+
+#[tauri::command]
+pub async fn list_subtasks(db: State<'_, Db>) -> Result<Vec<SubtaskRecord>> {
+    subtasks::list(&db.0).await
+}
+
+#[tauri::command]
+pub async fn set_task_parent(
+    db: State<'_, Db>,
+    task_id: String,
+    parent_id: Option<String>,
+) -> Result<()> {
+    subtasks::set_parent(&db.0, &task_id, parent_id.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn task_rollups(db: State<'_, Db>, skill_id: Option<String>) -> Result<Vec<Rollup>> {
+    subtasks::rollups(&db.0, skill_id.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn list_task_dependencies(db: State<'_, Db>) -> Result<Vec<DependencyRecord>> {
+    dependencies::list(&db.0).await
+}
+
+#[tauri::command]
+pub async fn add_task_dependency(
+    db: State<'_, Db>,
+    task_id: String,
+    blocked_by_id: String,
+) -> Result<()> {
+    dependencies::add(&db.0, &task_id, &blocked_by_id).await
+}
+
+#[tauri::command]
+pub async fn remove_task_dependency(
+    db: State<'_, Db>,
+    task_id: String,
+    blocked_by_id: String,
+) -> Result<()> {
+    dependencies::remove(&db.0, &task_id, &blocked_by_id).await
+}
+
+#[tauri::command]
+pub async fn unblocked_tasks(
+    db: State<'_, Db>,
+    skill_id: Option<String>,
+) -> Result<Vec<TaskRecord>> {
+    dependencies::unblocked(&db.0, skill_id.as_deref()).await
+}
+
+// Tags
+
+#[tauri::command]
+pub async fn list_tags(db: State<'_, Db>) -> Result<Vec<TagRecord>> {
+    tags::list(&db.0).await
+}
+
+#[tauri::command]
+pub async fn create_tag(db: State<'_, Db>, input: CreateTagInput) -> Result<TagRecord> {
+    tags::create(&db.0, input).await
+}
+
+#[tauri::command]
+pub async fn update_tag(db: State<'_, Db>, input: UpdateTagInput) -> Result<TagRecord> {
+    tags::update(&db.0, input).await
+}
+
+#[tauri::command]
+pub async fn delete_tag(db: State<'_, Db>, id: String) -> Result<()> {
+    tags::delete(&db.0, &id).await
+}
+
+#[tauri::command]
+pub async fn list_tag_links(db: State<'_, Db>, kind: Tagged) -> Result<Vec<TagLink>> {
+    tags::links(&db.0, kind).await
+}
+
+#[tauri::command]
+pub async fn set_tags(
+    db: State<'_, Db>,
+    kind: Tagged,
+    item_id: String,
+    tag_ids: Vec<String>,
+) -> Result<()> {
+    tags::set(&db.0, kind, &item_id, &tag_ids).await
+}
+
+// Timer sessions
+
+#[tauri::command]
+pub async fn list_sessions(
+    db: State<'_, Db>,
+    skill_id: Option<String>,
+) -> Result<Vec<TimerSessionRecord>> {
+    sessions::list(&db.0, skill_id.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn record_manual_session(
+    app: AppHandle,
+    db: State<'_, Db>,
+    skill_id: String,
+    task_id: Option<String>,
+    minutes: i64,
+) -> Result<TimerSessionRecord> {
+    let session = sessions::record_manual(&db.0, &skill_id, task_id.as_deref(), minutes).await?;
+    unlocks::refresh(&app).await;
+    Ok(session)
+}
+
+#[tauri::command]
+pub async fn update_session(
+    app: AppHandle,
+    db: State<'_, Db>,
+    input: UpdateSessionInput,
+) -> Result<()> {
+    sessions::update(&db.0, input).await?;
+    unlocks::refresh(&app).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn minutes_by_skill_on(db: State<'_, Db>, date: String) -> Result<Vec<SkillMinutes>> {
+    sessions::minutes_by_skill_on(&db.0, &date).await
+}
+
+#[tauri::command]
+pub async fn minutes_by_day(db: State<'_, Db>, days: i64) -> Result<Vec<DayMinutes>> {
+    sessions::minutes_by_day(&db.0, days).await
+}
+
+#[tauri::command]
+pub async fn skill_minutes_on(db: State<'_, Db>, skill_id: String, date: String) -> Result<i64> {
+    sessions::skill_minutes_on(&db.0, &skill_id, &date).await
+}
+
+// Daily activity
+
+#[tauri::command]
+pub async fn list_daily_activities(
+    db: State<'_, Db>,
+    days: i64,
+) -> Result<Vec<DailyActivityRecord>> {
+    activities::list(&db.0, days).await
+}
+
+#[tauri::command]
+pub async fn recompute_aggregates(app: AppHandle, db: State<'_, Db>) -> Result<()> {
+    activities::recompute(&db.0).await?;
+    unlocks::refresh(&app).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_profile_stats(db: State<'_, Db>) -> Result<ProfileStats> {
+    activities::profile_stats(&db.0).await
+}
+
+// Reflections
+
+#[tauri::command]
+pub async fn list_reflection_dates(db: State<'_, Db>) -> Result<Vec<String>> {
+    reflections::dates(&db.0).await
+}
+
+#[tauri::command]
+pub async fn get_reflection(
+    db: State<'_, Db>,
+    date: String,
+) -> Result<Option<ReflectionWithSkills>> {
+    reflections::get_by_date(&db.0, &date).await
+}
+
+#[tauri::command]
+pub async fn save_reflection(
+    app: AppHandle,
+    db: State<'_, Db>,
+    input: SaveReflectionInput,
+) -> Result<ReflectionWithSkills> {
+    let reflection = reflections::save(&db.0, input).await?;
+    unlocks::refresh(&app).await;
+    Ok(reflection)
+}
+
+// Achievements
+
+#[tauri::command]
+pub async fn list_achievements(db: State<'_, Db>) -> Result<Vec<AchievementRecord>> {
+    achievements::list(&db.0).await
+}
+
+#[tauri::command]
+pub async fn create_achievement(
+    app: AppHandle,
+    db: State<'_, Db>,
+    input: CreateAchievementInput,
+) -> Result<AchievementRecord> {
+    let achievement = achievements::create(&db.0, input).await?;
+    unlocks::refresh(&app).await;
+    Ok(achievement)
+}
+
+#[tauri::command]
+pub async fn delete_achievement(db: State<'_, Db>, id: String) -> Result<()> {
+    achievements::delete(&db.0, &id).await
+}
+
+// Settings
+
+#[tauri::command]
+pub async fn get_settings(db: State<'_, Db>) -> Result<UserSettingsRecord> {
+    settings::get(&db.0).await
+}
+
+#[tauri::command]
+pub async fn update_settings(
+    db: State<'_, Db>,
+    input: UpdateSettingsInput,
+) -> Result<UserSettingsRecord> {
+    settings::update(&db.0, input).await
+}
+
+// Data management
+
+#[tauri::command]
+pub async fn export_data(db: State<'_, Db>) -> Result<DataExport> {
+    data::export(&db.0).await
+}
+
+#[tauri::command]
+pub async fn import_data(app: AppHandle, db: State<'_, Db>, backup: DataExport) -> Result<()> {
+    data::import(&db.0, backup).await?;
+    unlocks::refresh(&app).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn clear_data(db: State<'_, Db>) -> Result<()> {
+    data::clear(&db.0).await
+}
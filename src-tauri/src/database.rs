@@ -1,5 +1,35 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::time::{Duration, SystemTime};
+
+use serde::Serialize;
+use sqlx::error::BoxDynError;
+use sqlx::migrate::{
+    MigrateError, Migration as SqlxMigration, MigrationSource, MigrationType, Migrator,
+};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+use tauri::{AppHandle, Manager};
 use tauri_plugin_sql::{Migration, MigrationKind};
 
+use crate::error::{Error, Result};
+use crate::snapshot;
+
+/// Database file inside the app config directory, shared with the
+/// `sqlite:app.db` connection registered on the SQL plugin.
+pub const DB_FILE: &str = "app.db";
+
+/// Bundle identifier from `tauri.conf.json`, which names the app config
+/// directory. The CLI uses it to find the database without a Tauri app.
+pub const APP_IDENTIFIER: &str = "com.10khours.app";
+
+/// Connection pool managed as Tauri state for the backend modules.
+pub struct Db(pub SqlitePool);
+
+/// Schema history, oldest first. Every version from 3 on is followed by the
+/// `Down` migration that [`revert`] runs to undo it.
 pub fn get_migrations() -> Vec<Migration> {
     vec![
         Migration {
@@ -164,6 +194,16 @@ pub fn get_migrations() -> Vec<Migration> {
             ",
             kind: MigrationKind::Up,
         },
+        Migration {
+            version: 3,
+            description: "add_timer_session_columns",
+            sql: "
+                -- Sessions go back to not knowing their planned length or type
+                ALTER TABLE timer_sessions DROP COLUMN session_type;
+                ALTER TABLE timer_sessions DROP COLUMN planned_duration;
+            ",
+            kind: MigrationKind::Down,
+        },
         Migration {
             version: 4,
             description: "make_task_id_nullable",
@@ -202,6 +242,45 @@ pub fn get_migrations() -> Vec<Migration> {
             ",
             kind: MigrationKind::Up,
         },
+        Migration {
+            version: 4,
+            description: "make_task_id_nullable",
+            sql: "
+                -- Every session needs a task again, so sessions without one are dropped.
+                -- Columns are listed by name and put back in their version 3 order.
+                CREATE TABLE IF NOT EXISTS timer_sessions_old (
+                    id TEXT PRIMARY KEY,
+                    task_id TEXT NOT NULL,
+                    skill_id TEXT NOT NULL,
+                    start_time TEXT NOT NULL,
+                    end_time TEXT,
+                    duration INTEGER NOT NULL,
+                    type TEXT NOT NULL,
+                    completed INTEGER NOT NULL DEFAULT 0,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    planned_duration INTEGER,
+                    session_type TEXT,
+                    FOREIGN KEY (task_id) REFERENCES tasks (id) ON DELETE CASCADE,
+                    FOREIGN KEY (skill_id) REFERENCES skills (id) ON DELETE CASCADE
+                );
+
+                INSERT INTO timer_sessions_old
+                    (id, task_id, skill_id, start_time, end_time, duration, type, completed,
+                     created_at, planned_duration, session_type)
+                SELECT id, task_id, skill_id, start_time, end_time, duration, type, completed,
+                       created_at, planned_duration, session_type
+                FROM timer_sessions
+                WHERE task_id IS NOT NULL;
+
+                DROP TABLE timer_sessions;
+                ALTER TABLE timer_sessions_old RENAME TO timer_sessions;
+
+                CREATE INDEX IF NOT EXISTS idx_timer_sessions_skill_id ON timer_sessions(skill_id);
+                CREATE INDEX IF NOT EXISTS idx_timer_sessions_task_id ON timer_sessions(task_id);
+                CREATE INDEX IF NOT EXISTS idx_timer_sessions_created_at ON timer_sessions(created_at);
+            ",
+            kind: MigrationKind::Down,
+        },
         Migration {
             version: 5,
             description: "add_task_priority_duedate_estimated",
@@ -215,6 +294,14 @@ pub fn get_migrations() -> Vec<Migration> {
             ",
             kind: MigrationKind::Up,
         },
+        Migration {
+            version: 5,
+            description: "add_task_priority_duedate_estimated",
+            sql: "
+                -- Only filled in defaults; the older schema accepts them as they are
+            ",
+            kind: MigrationKind::Down,
+        },
         Migration {
             version: 6,
             description: "add_user_settings_goal_columns",
@@ -226,5 +313,1060 @@ pub fn get_migrations() -> Vec<Migration> {
             ",
             kind: MigrationKind::Up,
         },
+        Migration {
+            version: 6,
+            description: "add_user_settings_goal_columns",
+            sql: "
+                ALTER TABLE user_settings DROP COLUMN email;
+                ALTER TABLE user_settings DROP COLUMN weekly_goal_minutes;
+                ALTER TABLE user_settings DROP COLUMN daily_goal_minutes;
+            ",
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 7,
+            description: "add_skill_daily_goal_minutes",
+            sql: "
+                -- Per-skill daily target, previously only known to the frontend
+                ALTER TABLE skills ADD COLUMN daily_goal_minutes INTEGER NOT NULL DEFAULT 60;
+
+                -- Never let a skill ask for more than the overall daily goal
+                UPDATE skills SET daily_goal_minutes = MIN(
+                    daily_goal_minutes,
+                    COALESCE((SELECT daily_goal_minutes FROM user_settings WHERE id = 1), daily_goal_minutes)
+                );
+            ",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 7,
+            description: "add_skill_daily_goal_minutes",
+            sql: "
+                ALTER TABLE skills DROP COLUMN daily_goal_minutes;
+            ",
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 8,
+            description: "repair_timer_session_column_order",
+            sql: "
+                -- Migration 4 copied rows with SELECT *, which shifted the columns
+                -- added in migration 3: created_at landed in planned_duration,
+                -- planned_duration in session_type and session_type in created_at
+                UPDATE timer_sessions SET
+                    planned_duration = session_type,
+                    session_type = created_at,
+                    created_at = planned_duration
+                WHERE typeof(planned_duration) = 'text';
+            ",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 8,
+            description: "repair_timer_session_column_order",
+            sql: "
+                -- Shifting the columns back would only bring the bug back
+            ",
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 9,
+            description: "maintain_aggregates_with_triggers",
+            sql: "
+                -- Completed pomodoros are the only sessions that count towards
+                -- skills.current_minutes and daily_activities
+                CREATE TRIGGER IF NOT EXISTS timer_sessions_aggregate_insert
+                AFTER INSERT ON timer_sessions
+                WHEN NEW.completed = 1 AND NEW.type = 'pomodoro'
+                BEGIN
+                    UPDATE skills SET current_minutes = current_minutes + NEW.duration
+                    WHERE id = NEW.skill_id;
+                    INSERT INTO daily_activities (date, total_minutes, total_sessions)
+                    VALUES (date(NEW.start_time), NEW.duration, 1)
+                    ON CONFLICT(date) DO UPDATE SET
+                        total_minutes = total_minutes + excluded.total_minutes,
+                        total_sessions = total_sessions + 1;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS timer_sessions_aggregate_delete
+                AFTER DELETE ON timer_sessions
+                WHEN OLD.completed = 1 AND OLD.type = 'pomodoro'
+                BEGIN
+                    UPDATE skills SET current_minutes = current_minutes - OLD.duration
+                    WHERE id = OLD.skill_id;
+                    UPDATE daily_activities SET
+                        total_minutes = total_minutes - OLD.duration,
+                        total_sessions = total_sessions - 1
+                    WHERE date = date(OLD.start_time);
+                    DELETE FROM daily_activities
+                    WHERE date = date(OLD.start_time) AND total_sessions <= 0;
+                END;
+
+                -- An update is the old row leaving the totals and the new one entering
+                CREATE TRIGGER IF NOT EXISTS timer_sessions_aggregate_update_old
+                AFTER UPDATE OF skill_id, start_time, duration, type, completed ON timer_sessions
+                WHEN OLD.completed = 1 AND OLD.type = 'pomodoro'
+                BEGIN
+                    UPDATE skills SET current_minutes = current_minutes - OLD.duration
+                    WHERE id = OLD.skill_id;
+                    UPDATE daily_activities SET
+                        total_minutes = total_minutes - OLD.duration,
+                        total_sessions = total_sessions - 1
+                    WHERE date = date(OLD.start_time);
+                    DELETE FROM daily_activities
+                    WHERE date = date(OLD.start_time) AND total_sessions <= 0;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS timer_sessions_aggregate_update_new
+                AFTER UPDATE OF skill_id, start_time, duration, type, completed ON timer_sessions
+                WHEN NEW.completed = 1 AND NEW.type = 'pomodoro'
+                BEGIN
+                    UPDATE skills SET current_minutes = current_minutes + NEW.duration
+                    WHERE id = NEW.skill_id;
+                    INSERT INTO daily_activities (date, total_minutes, total_sessions)
+                    VALUES (date(NEW.start_time), NEW.duration, 1)
+                    ON CONFLICT(date) DO UPDATE SET
+                        total_minutes = total_minutes + excluded.total_minutes,
+                        total_sessions = total_sessions + 1;
+                END;
+
+                -- The frontend never wrote daily_activities, so rebuild it once from
+                -- the sessions. skills.current_minutes is left alone because imported
+                -- skills carry minutes without the sessions behind them.
+                INSERT OR REPLACE INTO daily_activities (date, total_minutes, total_sessions)
+                SELECT date(start_time), SUM(duration), COUNT(*)
+                FROM timer_sessions
+                WHERE completed = 1 AND type = 'pomodoro'
+                GROUP BY date(start_time);
+            ",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 9,
+            description: "maintain_aggregates_with_triggers",
+            sql: "
+                -- daily_activities keeps the totals the triggers maintained
+                DROP TRIGGER IF EXISTS timer_sessions_aggregate_insert;
+                DROP TRIGGER IF EXISTS timer_sessions_aggregate_delete;
+                DROP TRIGGER IF EXISTS timer_sessions_aggregate_update_old;
+                DROP TRIGGER IF EXISTS timer_sessions_aggregate_update_new;
+            ",
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 10,
+            description: "add_achievement_rules",
+            sql: "
+                -- JSON rule for user-defined achievements; NULL for the built-in ones
+                ALTER TABLE achievements ADD COLUMN rule TEXT;
+            ",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 10,
+            description: "add_achievement_rules",
+            sql: "
+                -- User-defined achievements mean nothing without their rule
+                DELETE FROM achievements WHERE rule IS NOT NULL;
+                ALTER TABLE achievements DROP COLUMN rule;
+            ",
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 11,
+            description: "create_active_timer",
+            sql: "
+                -- At most one running session across the app and the tenk CLI
+                CREATE TABLE IF NOT EXISTS active_timer (
+                    id INTEGER PRIMARY KEY CHECK (id = 1),
+                    session_id TEXT NOT NULL REFERENCES timer_sessions(id) ON DELETE CASCADE,
+                    owner TEXT NOT NULL CHECK (owner IN ('app', 'cli'))
+                );
+            ",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 11,
+            description: "create_active_timer",
+            sql: "
+                DROP TABLE IF EXISTS active_timer;
+            ",
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 12,
+            description: "add_api_settings",
+            sql: "
+                -- Local HTTP API, off until the user turns it on
+                ALTER TABLE user_settings ADD COLUMN api_enabled INTEGER NOT NULL DEFAULT 0;
+                ALTER TABLE user_settings ADD COLUMN api_port INTEGER NOT NULL DEFAULT 47600;
+                ALTER TABLE user_settings ADD COLUMN api_token TEXT;
+            ",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 12,
+            description: "add_api_settings",
+            sql: "
+                ALTER TABLE user_settings DROP COLUMN api_token;
+                ALTER TABLE user_settings DROP COLUMN api_port;
+                ALTER TABLE user_settings DROP COLUMN api_enabled;
+            ",
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 13,
+            description: "add_local_day_columns",
+            sql: "
+                -- IANA zone the backend buckets days in; NULL until it adopts
+                -- the system zone on first start
+                ALTER TABLE user_settings ADD COLUMN timezone TEXT;
+
+                -- Day and hour of each session in that zone, written by the
+                -- backend. Until then they hold the UTC values.
+                ALTER TABLE timer_sessions ADD COLUMN local_date TEXT;
+                ALTER TABLE timer_sessions ADD COLUMN local_hour INTEGER;
+                UPDATE timer_sessions SET
+                    local_date = date(start_time),
+                    local_hour = CAST(strftime('%H', COALESCE(end_time, start_time)) AS INTEGER);
+                CREATE INDEX IF NOT EXISTS idx_timer_sessions_local_date ON timer_sessions(local_date);
+
+                -- Bucket by local_date, and move a session's minutes when it changes
+                DROP TRIGGER IF EXISTS timer_sessions_aggregate_insert;
+                DROP TRIGGER IF EXISTS timer_sessions_aggregate_delete;
+                DROP TRIGGER IF EXISTS timer_sessions_aggregate_update_old;
+                DROP TRIGGER IF EXISTS timer_sessions_aggregate_update_new;
+
+                CREATE TRIGGER IF NOT EXISTS timer_sessions_aggregate_insert
+                AFTER INSERT ON timer_sessions
+                WHEN NEW.completed = 1 AND NEW.type = 'pomodoro'
+                BEGIN
+                    UPDATE skills SET current_minutes = current_minutes + NEW.duration
+                    WHERE id = NEW.skill_id;
+                    INSERT INTO daily_activities (date, total_minutes, total_sessions)
+                    VALUES (COALESCE(NEW.local_date, date(NEW.start_time)), NEW.duration, 1)
+                    ON CONFLICT(date) DO UPDATE SET
+                        total_minutes = total_minutes + excluded.total_minutes,
+                        total_sessions = total_sessions + 1;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS timer_sessions_aggregate_delete
+                AFTER DELETE ON timer_sessions
+                WHEN OLD.completed = 1 AND OLD.type = 'pomodoro'
+                BEGIN
+                    UPDATE skills SET current_minutes = current_minutes - OLD.duration
+                    WHERE id = OLD.skill_id;
+                    UPDATE daily_activities SET
+                        total_minutes = total_minutes - OLD.duration,
+                        total_sessions = total_sessions - 1
+                    WHERE date = COALESCE(OLD.local_date, date(OLD.start_time));
+                    DELETE FROM daily_activities
+                    WHERE date = COALESCE(OLD.local_date, date(OLD.start_time)) AND total_sessions <= 0;
+                END;
+
+                -- An update is the old row leaving the totals and the new one entering
+                CREATE TRIGGER IF NOT EXISTS timer_sessions_aggregate_update_old
+                AFTER UPDATE OF skill_id, start_time, duration, type, completed, local_date ON timer_sessions
+                WHEN OLD.completed = 1 AND OLD.type = 'pomodoro'
+                BEGIN
+                    UPDATE skills SET current_minutes = current_minutes - OLD.duration
+                    WHERE id = OLD.skill_id;
+                    UPDATE daily_activities SET
+                        total_minutes = total_minutes - OLD.duration,
+                        total_sessions = total_sessions - 1
+                    WHERE date = COALESCE(OLD.local_date, date(OLD.start_time));
+                    DELETE FROM daily_activities
+                    WHERE date = COALESCE(OLD.local_date, date(OLD.start_time)) AND total_sessions <= 0;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS timer_sessions_aggregate_update_new
+                AFTER UPDATE OF skill_id, start_time, duration, type, completed, local_date ON timer_sessions
+                WHEN NEW.completed = 1 AND NEW.type = 'pomodoro'
+                BEGIN
+                    UPDATE skills SET current_minutes = current_minutes + NEW.duration
+                    WHERE id = NEW.skill_id;
+                    INSERT INTO daily_activities (date, total_minutes, total_sessions)
+                    VALUES (COALESCE(NEW.local_date, date(NEW.start_time)), NEW.duration, 1)
+                    ON CONFLICT(date) DO UPDATE SET
+                        total_minutes = total_minutes + excluded.total_minutes,
+                        total_sessions = total_sessions + 1;
+                END;
+            ",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 13,
+            description: "add_local_day_columns",
+            sql: "
+                DROP TRIGGER IF EXISTS timer_sessions_aggregate_insert;
+                DROP TRIGGER IF EXISTS timer_sessions_aggregate_delete;
+                DROP TRIGGER IF EXISTS timer_sessions_aggregate_update_old;
+                DROP TRIGGER IF EXISTS timer_sessions_aggregate_update_new;
+
+                CREATE TRIGGER IF NOT EXISTS timer_sessions_aggregate_insert
+                AFTER INSERT ON timer_sessions
+                WHEN NEW.completed = 1 AND NEW.type = 'pomodoro'
+                BEGIN
+                    UPDATE skills SET current_minutes = current_minutes + NEW.duration
+                    WHERE id = NEW.skill_id;
+                    INSERT INTO daily_activities (date, total_minutes, total_sessions)
+                    VALUES (date(NEW.start_time), NEW.duration, 1)
+                    ON CONFLICT(date) DO UPDATE SET
+                        total_minutes = total_minutes + excluded.total_minutes,
+                        total_sessions = total_sessions + 1;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS timer_sessions_aggregate_delete
+                AFTER DELETE ON timer_sessions
+                WHEN OLD.completed = 1 AND OLD.type = 'pomodoro'
+                BEGIN
+                    UPDATE skills SET current_minutes = current_minutes - OLD.duration
+                    WHERE id = OLD.skill_id;
+                    UPDATE daily_activities SET
+                        total_minutes = total_minutes - OLD.duration,
+                        total_sessions = total_sessions - 1
+                    WHERE date = date(OLD.start_time);
+                    DELETE FROM daily_activities
+                    WHERE date = date(OLD.start_time) AND total_sessions <= 0;
+                END;
+
+                -- An update is the old row leaving the totals and the new one entering
+                CREATE TRIGGER IF NOT EXISTS timer_sessions_aggregate_update_old
+                AFTER UPDATE OF skill_id, start_time, duration, type, completed ON timer_sessions
+                WHEN OLD.completed = 1 AND OLD.type = 'pomodoro'
+                BEGIN
+                    UPDATE skills SET current_minutes = current_minutes - OLD.duration
+                    WHERE id = OLD.skill_id;
+                    UPDATE daily_activities SET
+                        total_minutes = total_minutes - OLD.duration,
+                        total_sessions = total_sessions - 1
+                    WHERE date = date(OLD.start_time);
+                    DELETE FROM daily_activities
+                    WHERE date = date(OLD.start_time) AND total_sessions <= 0;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS timer_sessions_aggregate_update_new
+                AFTER UPDATE OF skill_id, start_time, duration, type, completed ON timer_sessions
+                WHEN NEW.completed = 1 AND NEW.type = 'pomodoro'
+                BEGIN
+                    UPDATE skills SET current_minutes = current_minutes + NEW.duration
+                    WHERE id = NEW.skill_id;
+                    INSERT INTO daily_activities (date, total_minutes, total_sessions)
+                    VALUES (date(NEW.start_time), NEW.duration, 1)
+                    ON CONFLICT(date) DO UPDATE SET
+                        total_minutes = total_minutes + excluded.total_minutes,
+                        total_sessions = total_sessions + 1;
+                END;
+
+                -- The restored triggers count by UTC day again
+                DELETE FROM daily_activities;
+                INSERT INTO daily_activities (date, total_minutes, total_sessions)
+                SELECT date(start_time), SUM(duration), COUNT(*)
+                FROM timer_sessions
+                WHERE completed = 1 AND type = 'pomodoro'
+                GROUP BY date(start_time);
+
+                DROP INDEX IF EXISTS idx_timer_sessions_local_date;
+                ALTER TABLE timer_sessions DROP COLUMN local_hour;
+                ALTER TABLE timer_sessions DROP COLUMN local_date;
+                ALTER TABLE user_settings DROP COLUMN timezone;
+            ",
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 14,
+            description: "add_day_start_hour",
+            sql: "
+                -- Local hour a day starts at, so a 1 AM session can count for
+                -- the day before. Midnight keeps every stored local_date valid.
+                ALTER TABLE user_settings ADD COLUMN day_start_hour INTEGER NOT NULL DEFAULT 0;
+            ",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 14,
+            description: "add_day_start_hour",
+            sql: "
+                -- The older backend re-buckets from midnight when it starts
+                ALTER TABLE user_settings DROP COLUMN day_start_hour;
+            ",
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 15,
+            description: "add_sleep_policy",
+            sql: "
+                -- What the timer does with a session the computer slept
+                -- through: 'pause', 'trim' or 'ask'
+                ALTER TABLE user_settings ADD COLUMN sleep_policy TEXT NOT NULL DEFAULT 'pause';
+            ",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 15,
+            description: "add_sleep_policy",
+            sql: "
+                ALTER TABLE user_settings DROP COLUMN sleep_policy;
+            ",
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 16,
+            description: "add_session_heartbeats",
+            sql: "
+                -- How far a running session got, written periodically by the
+                -- timer so a crash can be recovered from
+                ALTER TABLE timer_sessions ADD COLUMN heartbeat_at TEXT;
+                ALTER TABLE timer_sessions ADD COLUMN elapsed_seconds INTEGER;
+
+                -- What launch does with sessions a crash left unfinished:
+                -- 'close', 'ask' or 'resume'
+                ALTER TABLE user_settings ADD COLUMN recovery_policy TEXT NOT NULL DEFAULT 'close';
+            ",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 16,
+            description: "add_session_heartbeats",
+            sql: "
+                ALTER TABLE user_settings DROP COLUMN recovery_policy;
+                ALTER TABLE timer_sessions DROP COLUMN elapsed_seconds;
+                ALTER TABLE timer_sessions DROP COLUMN heartbeat_at;
+            ",
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 17,
+            description: "add_task_recurrence",
+            sql: "
+                -- An RRULE such as 'FREQ=WEEKLY;BYDAY=MO,TH', copied to every
+                -- instance of a recurring task
+                ALTER TABLE tasks ADD COLUMN recurrence TEXT;
+                -- The first task of the series, and the instance each one
+                -- was created after; deleting an instance relinks the next
+                ALTER TABLE tasks ADD COLUMN series_id TEXT;
+                ALTER TABLE tasks ADD COLUMN previous_id TEXT;
+
+                CREATE INDEX idx_tasks_series_id ON tasks(series_id);
+                -- At most one next instance, however often it is generated
+                CREATE UNIQUE INDEX idx_tasks_previous_id ON tasks(previous_id);
+            ",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 17,
+            description: "add_task_recurrence",
+            sql: "
+                DROP INDEX idx_tasks_previous_id;
+                DROP INDEX idx_tasks_series_id;
+                ALTER TABLE tasks DROP COLUMN previous_id;
+                ALTER TABLE tasks DROP COLUMN series_id;
+                ALTER TABLE tasks DROP COLUMN recurrence;
+            ",
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 18,
+            description: "create_subtasks_and_dependencies",
+            sql: "
+                -- A task has at most one parent; loops are refused by the
+                -- repository
+                CREATE TABLE subtasks (
+                    task_id TEXT PRIMARY KEY,
+                    parent_id TEXT NOT NULL,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    CHECK (task_id != parent_id),
+                    FOREIGN KEY (task_id) REFERENCES tasks (id) ON DELETE CASCADE,
+                    FOREIGN KEY (parent_id) REFERENCES tasks (id) ON DELETE CASCADE
+                );
+                CREATE INDEX idx_subtasks_parent_id ON subtasks(parent_id);
+
+                -- task_id cannot start until blocked_by_id is done
+                CREATE TABLE task_dependencies (
+                    task_id TEXT NOT NULL,
+                    blocked_by_id TEXT NOT NULL,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    PRIMARY KEY (task_id, blocked_by_id),
+                    CHECK (task_id != blocked_by_id),
+                    FOREIGN KEY (task_id) REFERENCES tasks (id) ON DELETE CASCADE,
+                    FOREIGN KEY (blocked_by_id) REFERENCES tasks (id) ON DELETE CASCADE
+                );
+                CREATE INDEX idx_task_dependencies_blocked_by_id ON task_dependencies(blocked_by_id);
+            ",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 18,
+            description: "create_subtasks_and_dependencies",
+            sql: "
+                DROP TABLE task_dependencies;
+                DROP TABLE subtasks;
+            ",
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 19,
+            description: "create_tags",
+            sql: "
+                -- Labels such as 'scales' or 'sight-reading' that cut across
+                -- skills
+                CREATE TABLE tags (
+                    id TEXT PRIMARY KEY,
+                    name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                    color TEXT,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+
+                CREATE TABLE task_tags (
+                    task_id TEXT NOT NULL,
+                    tag_id TEXT NOT NULL,
+                    PRIMARY KEY (task_id, tag_id),
+                    FOREIGN KEY (task_id) REFERENCES tasks (id) ON DELETE CASCADE,
+                    FOREIGN KEY (tag_id) REFERENCES tags (id) ON DELETE CASCADE
+                );
+                CREATE INDEX idx_task_tags_tag_id ON task_tags(tag_id);
+
+                -- A session also counts for the tags of its task
+                CREATE TABLE session_tags (
+                    session_id TEXT NOT NULL,
+                    tag_id TEXT NOT NULL,
+                    PRIMARY KEY (session_id, tag_id),
+                    FOREIGN KEY (session_id) REFERENCES timer_sessions (id) ON DELETE CASCADE,
+                    FOREIGN KEY (tag_id) REFERENCES tags (id) ON DELETE CASCADE
+                );
+                CREATE INDEX idx_session_tags_tag_id ON session_tags(tag_id);
+
+                CREATE TABLE reflection_tags (
+                    reflection_id TEXT NOT NULL,
+                    tag_id TEXT NOT NULL,
+                    PRIMARY KEY (reflection_id, tag_id),
+                    FOREIGN KEY (reflection_id) REFERENCES reflections (id) ON DELETE CASCADE,
+                    FOREIGN KEY (tag_id) REFERENCES tags (id) ON DELETE CASCADE
+                );
+                CREATE INDEX idx_reflection_tags_tag_id ON reflection_tags(tag_id);
+            ",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 19,
+            description: "create_tags",
+            sql: "
+                DROP TABLE reflection_tags;
+                DROP TABLE session_tags;
+                DROP TABLE task_tags;
+                DROP TABLE tags;
+            ",
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 20,
+            description: "create_subskills",
+            sql: "
+                -- Music > Piano > Jazz voicings: a skill has at most one
+                -- parent, and time on it counts toward every skill above it
+                CREATE TABLE subskills (
+                    skill_id TEXT PRIMARY KEY,
+                    parent_id TEXT NOT NULL,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    CHECK (skill_id != parent_id),
+                    FOREIGN KEY (skill_id) REFERENCES skills (id) ON DELETE CASCADE,
+                    FOREIGN KEY (parent_id) REFERENCES skills (id) ON DELETE CASCADE
+                );
+                CREATE INDEX idx_subskills_parent_id ON subskills(parent_id);
+            ",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 20,
+            description: "create_subskills",
+            sql: "
+                DROP TABLE subskills;
+            ",
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 21,
+            description: "add_adjustment_sessions",
+            sql: "
+                -- Time taken back off by hand is an 'adjustment' session,
+                -- which counts for minutes but not as a session
+                DROP TRIGGER IF EXISTS timer_sessions_aggregate_insert;
+                DROP TRIGGER IF EXISTS timer_sessions_aggregate_delete;
+                DROP TRIGGER IF EXISTS timer_sessions_aggregate_update_old;
+                DROP TRIGGER IF EXISTS timer_sessions_aggregate_update_new;
+
+                CREATE TRIGGER IF NOT EXISTS timer_sessions_aggregate_insert
+                AFTER INSERT ON timer_sessions
+                WHEN NEW.completed = 1 AND NEW.type IN ('pomodoro', 'adjustment')
+                BEGIN
+                    UPDATE skills SET current_minutes = current_minutes + NEW.duration
+                    WHERE id = NEW.skill_id;
+                    INSERT INTO daily_activities (date, total_minutes, total_sessions)
+                    VALUES (COALESCE(NEW.local_date, date(NEW.start_time)), NEW.duration, NEW.type = 'pomodoro')
+                    ON CONFLICT(date) DO UPDATE SET
+                        total_minutes = total_minutes + excluded.total_minutes,
+                        total_sessions = total_sessions + excluded.total_sessions;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS timer_sessions_aggregate_delete
+                AFTER DELETE ON timer_sessions
+                WHEN OLD.completed = 1 AND OLD.type IN ('pomodoro', 'adjustment')
+                BEGIN
+                    UPDATE skills SET current_minutes = current_minutes - OLD.duration
+                    WHERE id = OLD.skill_id;
+                    UPDATE daily_activities SET
+                        total_minutes = total_minutes - OLD.duration,
+                        total_sessions = total_sessions - (OLD.type = 'pomodoro')
+                    WHERE date = COALESCE(OLD.local_date, date(OLD.start_time));
+                    DELETE FROM daily_activities
+                    WHERE date = COALESCE(OLD.local_date, date(OLD.start_time))
+                      AND total_sessions <= 0 AND total_minutes = 0;
+                END;
+
+                -- An update is the old row leaving the totals and the new one entering
+                CREATE TRIGGER IF NOT EXISTS timer_sessions_aggregate_update_old
+                AFTER UPDATE OF skill_id, start_time, duration, type, completed, local_date ON timer_sessions
+                WHEN OLD.completed = 1 AND OLD.type IN ('pomodoro', 'adjustment')
+                BEGIN
+                    UPDATE skills SET current_minutes = current_minutes - OLD.duration
+                    WHERE id = OLD.skill_id;
+                    UPDATE daily_activities SET
+                        total_minutes = total_minutes - OLD.duration,
+                        total_sessions = total_sessions - (OLD.type = 'pomodoro')
+                    WHERE date = COALESCE(OLD.local_date, date(OLD.start_time));
+                    DELETE FROM daily_activities
+                    WHERE date = COALESCE(OLD.local_date, date(OLD.start_time))
+                      AND total_sessions <= 0 AND total_minutes = 0;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS timer_sessions_aggregate_update_new
+                AFTER UPDATE OF skill_id, start_time, duration, type, completed, local_date ON timer_sessions
+                WHEN NEW.completed = 1 AND NEW.type IN ('pomodoro', 'adjustment')
+                BEGIN
+                    UPDATE skills SET current_minutes = current_minutes + NEW.duration
+                    WHERE id = NEW.skill_id;
+                    INSERT INTO daily_activities (date, total_minutes, total_sessions)
+                    VALUES (COALESCE(NEW.local_date, date(NEW.start_time)), NEW.duration, NEW.type = 'pomodoro')
+                    ON CONFLICT(date) DO UPDATE SET
+                        total_minutes = total_minutes + excluded.total_minutes,
+                        total_sessions = total_sessions + excluded.total_sessions;
+                END;
+
+                -- Earlier corrections were stored as negative pomodoros; the
+                -- update triggers move them out of the session counts
+                UPDATE timer_sessions SET type = 'adjustment'
+                WHERE type = 'pomodoro' AND duration < 0;
+            ",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 21,
+            description: "add_adjustment_sessions",
+            sql: "
+                DROP TRIGGER IF EXISTS timer_sessions_aggregate_insert;
+                DROP TRIGGER IF EXISTS timer_sessions_aggregate_delete;
+                DROP TRIGGER IF EXISTS timer_sessions_aggregate_update_old;
+                DROP TRIGGER IF EXISTS timer_sessions_aggregate_update_new;
+
+                -- Adjustments go back to being negative pomodoros, with the
+                -- triggers out of the way so the minutes are not moved twice
+                UPDATE timer_sessions SET type = 'pomodoro' WHERE type = 'adjustment';
+
+                CREATE TRIGGER IF NOT EXISTS timer_sessions_aggregate_insert
+                AFTER INSERT ON timer_sessions
+                WHEN NEW.completed = 1 AND NEW.type = 'pomodoro'
+                BEGIN
+                    UPDATE skills SET current_minutes = current_minutes + NEW.duration
+                    WHERE id = NEW.skill_id;
+                    INSERT INTO daily_activities (date, total_minutes, total_sessions)
+                    VALUES (COALESCE(NEW.local_date, date(NEW.start_time)), NEW.duration, 1)
+                    ON CONFLICT(date) DO UPDATE SET
+                        total_minutes = total_minutes + excluded.total_minutes,
+                        total_sessions = total_sessions + 1;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS timer_sessions_aggregate_delete
+                AFTER DELETE ON timer_sessions
+                WHEN OLD.completed = 1 AND OLD.type = 'pomodoro'
+                BEGIN
+                    UPDATE skills SET current_minutes = current_minutes - OLD.duration
+                    WHERE id = OLD.skill_id;
+                    UPDATE daily_activities SET
+                        total_minutes = total_minutes - OLD.duration,
+                        total_sessions = total_sessions - 1
+                    WHERE date = COALESCE(OLD.local_date, date(OLD.start_time));
+                    DELETE FROM daily_activities
+                    WHERE date = COALESCE(OLD.local_date, date(OLD.start_time)) AND total_sessions <= 0;
+                END;
+
+                -- An update is the old row leaving the totals and the new one entering
+                CREATE TRIGGER IF NOT EXISTS timer_sessions_aggregate_update_old
+                AFTER UPDATE OF skill_id, start_time, duration, type, completed, local_date ON timer_sessions
+                WHEN OLD.completed = 1 AND OLD.type = 'pomodoro'
+                BEGIN
+                    UPDATE skills SET current_minutes = current_minutes - OLD.duration
+                    WHERE id = OLD.skill_id;
+                    UPDATE daily_activities SET
+                        total_minutes = total_minutes - OLD.duration,
+                        total_sessions = total_sessions - 1
+                    WHERE date = COALESCE(OLD.local_date, date(OLD.start_time));
+                    DELETE FROM daily_activities
+                    WHERE date = COALESCE(OLD.local_date, date(OLD.start_time)) AND total_sessions <= 0;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS timer_sessions_aggregate_update_new
+                AFTER UPDATE OF skill_id, start_time, duration, type, completed, local_date ON timer_sessions
+                WHEN NEW.completed = 1 AND NEW.type = 'pomodoro'
+                BEGIN
+                    UPDATE skills SET current_minutes = current_minutes + NEW.duration
+                    WHERE id = NEW.skill_id;
+                    INSERT INTO daily_activities (date, total_minutes, total_sessions)
+                    VALUES (COALESCE(NEW.local_date, date(NEW.start_time)), NEW.duration, 1)
+                    ON CONFLICT(date) DO UPDATE SET
+                        total_minutes = total_minutes + excluded.total_minutes,
+                        total_sessions = total_sessions + 1;
+                END;
+
+                -- Count the former adjustments as sessions again
+                DELETE FROM daily_activities;
+                INSERT INTO daily_activities (date, total_minutes, total_sessions)
+                SELECT COALESCE(local_date, date(start_time)), SUM(duration), COUNT(*)
+                FROM timer_sessions
+                WHERE completed = 1 AND type = 'pomodoro'
+                GROUP BY COALESCE(local_date, date(start_time));
+            ",
+            kind: MigrationKind::Down,
+        },
     ]
 }
+
+/// Feeds [`get_migrations`] to sqlx the same way the SQL plugin does, so both
+/// agree on versions and checksums in `_sqlx_migrations`. The plugin skips
+/// the `Down` migrations; sqlx only runs them from [`Migrator::undo`].
+#[derive(Clone, Debug)]
+struct MigrationList(Vec<SqlxMigration>);
+
+impl From<Vec<Migration>> for MigrationList {
+    fn from(migrations: Vec<Migration>) -> Self {
+        MigrationList(
+            migrations
+                .into_iter()
+                .map(|migration| {
+                    SqlxMigration::new(
+                        migration.version,
+                        Cow::Borrowed(migration.description),
+                        MigrationType::from(migration.kind),
+                        Cow::Borrowed(migration.sql),
+                        false,
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+impl MigrationList {
+    /// The versions up to and including `version`.
+    fn through(&self, version: i64) -> Self {
+        MigrationList(
+            self.0
+                .iter()
+                .filter(|migration| migration.version <= version)
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// The newest version of all.
+    fn latest(&self) -> i64 {
+        self.0
+            .iter()
+            .map(|migration| migration.version)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Versions after `applied` that move the schema forward, oldest first.
+    fn pending(&self, applied: i64) -> Vec<i64> {
+        let mut versions: Vec<i64> = self
+            .0
+            .iter()
+            .filter(|migration| !migration.migration_type.is_down_migration())
+            .map(|migration| migration.version)
+            .filter(|version| *version > applied)
+            .collect();
+        versions.sort_unstable();
+        versions.dedup();
+        versions
+    }
+}
+
+impl MigrationSource<'static> for MigrationList {
+    fn resolve(
+        self,
+    ) -> Pin<Box<dyn Future<Output = std::result::Result<Vec<SqlxMigration>, BoxDynError>> + Send>>
+    {
+        Box::pin(async move { Ok(self.0) })
+    }
+}
+
+/// Opens `app.db` in the app config directory and applies pending migrations.
+pub async fn connect(app: &AppHandle) -> Result<SqlitePool> {
+    open(&app.path().app_config_dir()?.join(DB_FILE)).await
+}
+
+/// Where the app keeps `app.db`, worked out the way Tauri resolves
+/// `app_config_dir` on desktop.
+pub fn default_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join(APP_IDENTIFIER).join(DB_FILE))
+}
+
+/// Opens the database at `path`, creating it if needed, and applies pending
+/// migrations with [`migrate_safely`].
+pub async fn open(path: &Path) -> Result<SqlitePool> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let options = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal)
+        // sqlx turns this on by default; the cascades depend on it, so say so.
+        .foreign_keys(true)
+        .busy_timeout(Duration::from_secs(5));
+    let pool = SqlitePoolOptions::new().connect_with(options).await?;
+    migrate_safely(&pool, path, get_migrations()).await?;
+
+    Ok(pool)
+}
+
+/// Why startup migrations failed, shown in the recovery dialog.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationFailure {
+    /// The migration that failed, when sqlx could tell.
+    pub version: Option<i64>,
+    pub description: Option<String>,
+    pub message: String,
+    /// Snapshot taken just before the failed migration.
+    pub snapshot: Option<PathBuf>,
+    /// Whether the database was put back to that snapshot.
+    pub restored: bool,
+    /// Whether the database is at a version newer than this build knows,
+    /// left by a newer version of the app. Nothing was tried on it.
+    pub from_newer_app: bool,
+}
+
+impl fmt::Display for MigrationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.from_newer_app {
+            return write!(f, "{}", self.message);
+        }
+        match (self.version, &self.description) {
+            (Some(version), Some(description)) => {
+                write!(f, "migration {version} ({description}) failed: ")?
+            }
+            (Some(version), None) => write!(f, "migration {version} failed: ")?,
+            _ => write!(f, "migrations failed: ")?,
+        }
+        write!(f, "{}", self.message)?;
+        if self.restored {
+            write!(
+                f,
+                "; the database was restored to its state before this migration"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Applies pending `migrations` to the database at `path`.
+///
+/// sqlx runs each version in its own transaction, so a failing version never
+/// half-applies. Pending versions are applied one at a time, each after a
+/// snapshot of its own; when one fails, only that snapshot is copied back,
+/// so the versions before it stay applied. The result is a
+/// [`MigrationFailure`] and a database at the last version that worked. A
+/// brand new database has nothing worth a snapshot and is migrated in one go.
+/// A database at a version newer than any in `migrations` is refused as it
+/// is, since this build does not know its schema.
+pub async fn migrate_safely(
+    pool: &SqlitePool,
+    path: &Path,
+    migrations: Vec<Migration>,
+) -> Result<()> {
+    let migrations = MigrationList::from(migrations);
+    let Some(applied) = applied_version(pool).await? else {
+        return apply(pool, migrations).await;
+    };
+    let latest = migrations.latest();
+    if applied > latest {
+        return Err(Error::Migration(Box::new(MigrationFailure {
+            version: Some(applied),
+            description: None,
+            message: format!(
+                "the database is at version {applied}, newer than the version {latest} this \
+                 version of the app knows; open it with the newer app"
+            ),
+            snapshot: None,
+            restored: false,
+            from_newer_app: true,
+        })));
+    }
+    let dir = snapshot::dir(path);
+    for version in migrations.pending(applied) {
+        let taken = snapshot::take(pool, &dir, &format!("before-v{version}")).await?;
+        let snapshot = dir.join(taken.name);
+        if let Err(err) = apply(pool, migrations.through(version)).await {
+            return Err(roll_back_failed(pool, &migrations, err, snapshot).await);
+        }
+    }
+    Ok(())
+}
+
+/// Copies `snapshot` back after `err` and describes what happened.
+async fn roll_back_failed(
+    pool: &SqlitePool,
+    migrations: &MigrationList,
+    err: Error,
+    snapshot: PathBuf,
+) -> Error {
+    let version = match &err {
+        Error::Migrate(MigrateError::ExecuteMigration(_, version)) => Some(*version),
+        _ => None,
+    };
+    let mut failure = MigrationFailure {
+        version,
+        description: migrations
+            .0
+            .iter()
+            .find(|migration| Some(migration.version) == version)
+            .map(|migration| migration.description.to_string()),
+        message: match &err {
+            Error::Migrate(MigrateError::ExecuteMigration(source, _)) => source.to_string(),
+            other => other.to_string(),
+        },
+        snapshot: Some(snapshot.clone()),
+        restored: false,
+        from_newer_app: false,
+    };
+    match snapshot::roll_back(pool, &snapshot).await {
+        Ok(()) => failure.restored = true,
+        Err(restore_err) => log::error!("could not restore {}: {restore_err}", snapshot.display()),
+    }
+    log::error!(
+        "migration failed: version={:?} description={:?} restored={} snapshot={:?} error={}",
+        failure.version,
+        failure.description,
+        failure.restored,
+        failure.snapshot,
+        failure.message
+    );
+    Error::Migration(Box::new(failure))
+}
+
+/// First of `migrations` that `pool` has not applied, or `None` when it is
+/// up to date or has never been migrated.
+pub async fn pending_migration(pool: &SqlitePool, migrations: &[Migration]) -> Result<Option<i64>> {
+    let Some(applied) = applied_version(pool).await? else {
+        return Ok(None);
+    };
+    Ok(migrations
+        .iter()
+        .map(|migration| migration.version)
+        .filter(|version| *version > applied)
+        .min())
+}
+
+/// Newest version applied to `pool`, or `None` when it has never been
+/// migrated.
+pub async fn applied_version(pool: &SqlitePool) -> Result<Option<i64>> {
+    let (exists,): (bool,) = sqlx::query_as(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')",
+    )
+    .fetch_one(pool)
+    .await?;
+    if !exists {
+        return Ok(None);
+    }
+    let (applied,): (Option<i64>,) =
+        sqlx::query_as("SELECT MAX(version) FROM _sqlx_migrations WHERE success = 1")
+            .fetch_one(pool)
+            .await?;
+    Ok(Some(applied.unwrap_or(0)))
+}
+
+/// Applies every pending migration from [`get_migrations`] to `pool`.
+pub async fn migrate(pool: &SqlitePool) -> Result<()> {
+    apply(pool, get_migrations().into()).await
+}
+
+async fn apply(pool: &SqlitePool, migrations: MigrationList) -> Result<()> {
+    Migrator::new(migrations).await?.run(pool).await?;
+    Ok(())
+}
+
+/// Oldest version [`revert`] can reach. Versions 1 and 2 create the tables
+/// and the built-in achievements, and have no `Down` migration.
+pub const MIN_REVERT_VERSION: i64 = 2;
+
+async fn check_revert_target(pool: &SqlitePool, version: i64) -> Result<()> {
+    let applied = applied_version(pool).await?.unwrap_or(0);
+    if version < MIN_REVERT_VERSION || version > applied {
+        return Err(Error::Invalid(format!(
+            "cannot revert to version {version}; the database is at version {applied} \
+             and can go back as far as version {MIN_REVERT_VERSION}"
+        )));
+    }
+    Ok(())
+}
+
+/// Runs the `Down` migrations of every version after `version`, newest
+/// first, each in its own transaction. Data the older schema has no place
+/// for, such as sessions without a task below version 4, is dropped.
+pub async fn revert(pool: &SqlitePool, version: i64) -> Result<()> {
+    check_revert_target(pool, version).await?;
+    Migrator::new(MigrationList::from(get_migrations()))
+        .await?
+        .undo(pool, version)
+        .await?;
+    Ok(())
+}
+
+/// [`revert`]s the database at `path` to `version` so an older app can open
+/// it, after taking a snapshot that is copied back if any step fails. The
+/// next [`open`] migrates it forward again.
+pub async fn downgrade(pool: &SqlitePool, path: &Path, version: i64) -> Result<PathBuf> {
+    check_revert_target(pool, version).await?;
+    let dir = snapshot::dir(path);
+    let taken = snapshot::take(pool, &dir, &format!("before-downgrade-v{version}")).await?;
+    let snapshot = dir.join(taken.name);
+
+    if let Err(err) = revert(pool, version).await {
+        log::error!("downgrade to version {version} failed: {err}");
+        snapshot::roll_back(pool, &snapshot).await?;
+        return Err(err);
+    }
+    Ok(snapshot)
+}
+
+/// Generates an id in the `<prefix>_<millis>_<random>` shape the frontend uses.
+pub fn generate_id(prefix: &str) -> String {
+    let random = uuid::Uuid::new_v4().simple().to_string();
+    format!(
+        "{prefix}_{}_{}",
+        chrono::Utc::now().timestamp_millis(),
+        &random[..9]
+    )
+}
+
+/// Current UTC time formatted like JavaScript's `Date.toISOString()`.
+pub fn now_iso() -> String {
+    iso(SystemTime::now())
+}
+
+/// `time` formatted like [`now_iso`].
+pub fn iso(time: SystemTime) -> String {
+    chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
@@ -0,0 +1,40 @@
+use serde::{Serialize, Serializer};
+
+use crate::database::MigrationFailure;
+
+/// Errors returned by backend modules and Tauri commands.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+
+    #[error(transparent)]
+    Migrate(#[from] sqlx::migrate::MigrateError),
+
+    /// Startup migrations failed; the database was rolled back if possible.
+    #[error("{0}")]
+    Migration(Box<MigrationFailure>),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Tauri(#[from] tauri::Error),
+
+    /// A timer command was issued in a state that does not allow it.
+    #[error("timer is {0}")]
+    Timer(&'static str),
+
+    /// A request was rejected because of the data it refers to.
+    #[error("{0}")]
+    Invalid(String),
+}
+
+// Commands hand errors to the webview, which only needs the message.
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
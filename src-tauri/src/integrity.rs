@@ -0,0 +1,203 @@
+//! Startup repair of rows whose parent row is gone.
+//!
+//! Older databases were written while nothing guaranteed
+//! `PRAGMA foreign_keys` was on, so deleting a skill could leave sessions
+//! behind that still counted towards `daily_activities`. Connections from
+//! [`crate::database::open`] enforce foreign keys, so once this pass has run
+//! no new orphans appear and later starts find nothing to do.
+
+use std::fmt;
+
+use serde::Serialize;
+use sqlx::{SqliteConnection, SqlitePool};
+use tauri::State;
+
+use crate::error::Result;
+
+/// What happened to an orphaned row.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    /// Moved to the skill of the task it was logged against.
+    Reattached,
+    /// Kept on its skill without the missing task.
+    Detached,
+    /// Deleted, because nothing it belongs to is left.
+    Purged,
+}
+
+/// A row that referred to a missing parent, and what was done about it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Orphan {
+    pub table: String,
+    pub id: String,
+    pub parent_table: String,
+    pub parent_id: String,
+    pub action: Action,
+}
+
+impl fmt::Display for Orphan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self.action {
+            Action::Reattached => "reattached",
+            Action::Detached => "detached",
+            Action::Purged => "purged",
+        };
+        write!(
+            f,
+            "{action} {} `{}`: {} `{}` does not exist",
+            self.table, self.id, self.parent_table, self.parent_id
+        )
+    }
+}
+
+/// Orphans repaired when the app started, kept for the webview to ask about.
+#[derive(Debug, Default)]
+pub struct Report(pub Vec<Orphan>);
+
+/// One kind of orphan. `condition` picks the rows, `id` and `parent_id`
+/// describe them, and `set` is the fix, or empty to delete them.
+struct Step {
+    table: &'static str,
+    id: &'static str,
+    parent_table: &'static str,
+    parent_id: &'static str,
+    condition: &'static str,
+    set: &'static str,
+    action: Action,
+}
+
+const SKILL_MISSING: &str = "skill_id NOT IN (SELECT id FROM skills)";
+
+/// Applied in order: sessions are saved onto a skill that still exists
+/// before the tasks they point at are purged.
+const STEPS: &[Step] = &[
+    Step {
+        table: "timer_sessions",
+        id: "id",
+        parent_table: "skills",
+        parent_id: "skill_id",
+        condition: "skill_id NOT IN (SELECT id FROM skills)
+            AND task_id IN (SELECT id FROM tasks WHERE skill_id IN (SELECT id FROM skills))",
+        set: "skill_id = (SELECT skill_id FROM tasks WHERE tasks.id = timer_sessions.task_id)",
+        action: Action::Reattached,
+    },
+    Step {
+        table: "timer_sessions",
+        id: "id",
+        parent_table: "tasks",
+        parent_id: "task_id",
+        condition: "skill_id IN (SELECT id FROM skills) AND task_id IS NOT NULL
+            AND task_id NOT IN (SELECT id FROM tasks WHERE skill_id IN (SELECT id FROM skills))",
+        set: "task_id = NULL",
+        action: Action::Detached,
+    },
+    Step {
+        table: "timer_sessions",
+        id: "id",
+        parent_table: "skills",
+        parent_id: "skill_id",
+        condition: SKILL_MISSING,
+        set: "",
+        action: Action::Purged,
+    },
+    Step {
+        table: "tasks",
+        id: "id",
+        parent_table: "skills",
+        parent_id: "skill_id",
+        condition: SKILL_MISSING,
+        set: "",
+        action: Action::Purged,
+    },
+    Step {
+        table: "reflection_skills",
+        id: "reflection_id || '/' || skill_id",
+        parent_table: "reflections",
+        parent_id: "reflection_id",
+        condition: "reflection_id NOT IN (SELECT id FROM reflections)",
+        set: "",
+        action: Action::Purged,
+    },
+    Step {
+        table: "reflection_skills",
+        id: "reflection_id || '/' || skill_id",
+        parent_table: "skills",
+        parent_id: "skill_id",
+        condition: SKILL_MISSING,
+        set: "",
+        action: Action::Purged,
+    },
+    Step {
+        table: "active_timer",
+        id: "session_id",
+        parent_table: "timer_sessions",
+        parent_id: "session_id",
+        condition: "session_id NOT IN (SELECT id FROM timer_sessions)",
+        set: "",
+        action: Action::Purged,
+    },
+];
+
+/// Rows that break a foreign key, as `(table, parent table)` pairs.
+async fn violations(conn: &mut SqliteConnection) -> Result<Vec<(String, String)>> {
+    let rows: Vec<(String, String)> =
+        sqlx::query_as(r#"SELECT "table", parent FROM pragma_foreign_key_check"#)
+            .fetch_all(&mut *conn)
+            .await?;
+    Ok(rows)
+}
+
+/// Finds rows whose parent is missing and reattaches or purges them in one
+/// transaction. Purged sessions go through the aggregate triggers, so they
+/// stop counting towards `daily_activities`. Returns nothing, without
+/// writing, when every foreign key holds.
+pub async fn repair(pool: &SqlitePool) -> Result<Vec<Orphan>> {
+    let mut tx = pool.begin().await?;
+    if violations(&mut tx).await?.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut orphans = Vec::new();
+    for step in STEPS {
+        let found: Vec<(String, String)> = sqlx::query_as(&format!(
+            "SELECT {}, {} FROM {} WHERE {}",
+            step.id, step.parent_id, step.table, step.condition
+        ))
+        .fetch_all(&mut *tx)
+        .await?;
+        if found.is_empty() {
+            continue;
+        }
+
+        let fix = if step.set.is_empty() {
+            format!("DELETE FROM {} WHERE {}", step.table, step.condition)
+        } else {
+            format!(
+                "UPDATE {} SET {} WHERE {}",
+                step.table, step.set, step.condition
+            )
+        };
+        sqlx::query(&fix).execute(&mut *tx).await?;
+        orphans.extend(found.into_iter().map(|(id, parent_id)| Orphan {
+            table: step.table.to_owned(),
+            id,
+            parent_table: step.parent_table.to_owned(),
+            parent_id,
+            action: step.action,
+        }));
+    }
+
+    // A foreign key added later without a step here should not go unnoticed.
+    for (table, parent) in violations(&mut tx).await? {
+        log::error!("unrepaired orphan in {table}: missing {parent} row");
+    }
+    tx.commit().await?;
+    Ok(orphans)
+}
+
+#[tauri::command]
+pub fn repaired_orphans(report: State<'_, Report>) -> Vec<Orphan> {
+    report.0.clone()
+}
@@ -0,0 +1,168 @@
+pub mod achievements;
+pub mod analytics;
+pub mod api;
+pub mod backup;
+mod commands;
+pub mod database;
+pub mod error;
+pub mod integrity;
+pub mod projection;
+pub mod recovery;
+pub mod recurrence;
+pub mod repository;
+pub mod schema;
+pub mod snapshot;
+pub mod timer;
+pub mod timezone;
+pub mod unfinished;
+
+use tauri::Manager;
+
+use crate::error::Error;
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    let migrations = database::get_migrations();
+
+    let result = tauri::Builder::default()
+        .plugin(
+            tauri_plugin_sql::Builder::new()
+                .add_migrations("sqlite:app.db", migrations)
+                .build(),
+        )
+        .plugin(tauri_plugin_shell::init())
+        .setup(|app| {
+            let pool = match tauri::async_runtime::block_on(database::connect(app.handle())) {
+                Ok(pool) => pool,
+                // Keep the window up so the recovery dialog can explain what
+                // happened; nothing else is managed.
+                Err(Error::Migration(failure)) => {
+                    app.manage(recovery::Recovery(Some(*failure)));
+                    return Ok(());
+                }
+                Err(err) => return Err(err.into()),
+            };
+            app.manage(recovery::Recovery(None));
+            // A session the app was running when it last exited cannot be
+            // resumed, so let the CLI start one.
+            tauri::async_runtime::block_on(repository::sessions::release(
+                &pool,
+                repository::sessions::Owner::App,
+            ))?;
+            let drift = tauri::async_runtime::block_on(schema::check(&pool))?;
+            for problem in &drift {
+                log::error!("schema drift: {problem}");
+            }
+            app.manage(schema::Report(drift));
+            let orphans = tauri::async_runtime::block_on(integrity::repair(&pool))?;
+            for orphan in &orphans {
+                log::warn!("integrity: {orphan}");
+            }
+            app.manage(integrity::Report(orphans));
+
+            let moved = tauri::async_runtime::block_on(timezone::init(&pool))?;
+            if moved > 0 {
+                log::info!("re-bucketed {moved} sessions into the current time zone");
+            }
+            // Sessions a crash left running are closed, resumed or kept for
+            // the window to ask about before the timer is handed out.
+            let timer = timer::Timer::default();
+            let unfinished = tauri::async_runtime::block_on(unfinished::recover(&pool, &timer))?;
+            if !unfinished.is_empty() {
+                log::warn!("{} unfinished sessions to ask about", unfinished.len());
+            }
+            app.manage(database::Db(pool));
+            app.manage(timer);
+            app.manage(api::Server::default());
+            snapshot::spawn_scheduler(app.handle().clone());
+            recurrence::spawn_scheduler(app.handle().clone());
+            // A port that is taken should not keep the app from starting.
+            if let Err(err) = tauri::async_runtime::block_on(api::apply(app.handle())) {
+                log::error!("api server not started: {err}");
+            }
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            timer::timer_state,
+            timer::timer_start,
+            timer::timer_pause,
+            timer::timer_resume,
+            timer::timer_stop,
+            unfinished::unfinished_sessions,
+            unfinished::resolve_unfinished_session,
+            schema::schema_drift,
+            integrity::repaired_orphans,
+            recovery::startup_failure,
+            recovery::quit_app,
+            analytics::get_activity_days,
+            analytics::get_streaks,
+            analytics::get_weekly_stats,
+            analytics::get_skill_progress,
+            analytics::get_tag_breakdown,
+            projection::get_mastery_projections,
+            achievements::recompute_achievements,
+            api::get_api_settings,
+            api::update_api_settings,
+            api::regenerate_api_token,
+            backup::create_backup,
+            backup::restore_backup,
+            snapshot::list_snapshots,
+            snapshot::create_snapshot,
+            snapshot::restore_snapshot,
+            commands::list_skills,
+            commands::create_skill,
+            commands::update_skill,
+            commands::delete_skill,
+            commands::set_active_skill,
+            commands::list_subskills,
+            commands::set_skill_parent,
+            commands::skill_rollups,
+            commands::list_tasks,
+            commands::create_task,
+            commands::update_task,
+            commands::set_task_status,
+            commands::delete_task,
+            commands::reorder_tasks,
+            commands::list_task_series,
+            commands::list_subtasks,
+            commands::set_task_parent,
+            commands::task_rollups,
+            commands::list_task_dependencies,
+            commands::add_task_dependency,
+            commands::remove_task_dependency,
+            commands::unblocked_tasks,
+            commands::list_tags,
+            commands::create_tag,
+            commands::update_tag,
+            commands::delete_tag,
+            commands::list_tag_links,
+            commands::set_tags,
+            commands::list_sessions,
+            commands::record_manual_session,
+            commands::update_session,
+            commands::minutes_by_skill_on,
+            commands::minutes_by_day,
+            commands::skill_minutes_on,
+            commands::list_daily_activities,
+            commands::recompute_aggregates,
+            commands::get_profile_stats,
+            commands::list_reflection_dates,
+            commands::get_reflection,
+            commands::save_reflection,
+            commands::list_achievements,
+            commands::create_achievement,
+            commands::delete_achievement,
+            commands::get_settings,
+            commands::update_settings,
+            commands::export_data,
+            commands::import_data,
+            commands::clear_data,
+        ])
+        .run(tauri::generate_context!());
+
+    if let Err(err) = result {
+        log::error!("app stopped: {err}");
+        eprintln!("10,000 Hours could not start: {err}");
+        std::process::exit(1);
+    }
+}
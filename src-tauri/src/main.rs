@@ -1,24 +1,6 @@
 // Hide console window on Windows in release builds
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-mod database;
-
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    let migrations = database::get_migrations();
-
-    tauri::Builder::default()
-        .plugin(
-            tauri_plugin_sql::Builder::new()
-                .add_migrations("sqlite:app.db", migrations)
-                .build(),
-        )
-        .plugin(tauri_plugin_shell::init())
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
-}
-
 fn main() {
-    run();
+    ten_k_hours_app_lib::run();
 }
-
@@ -0,0 +1,278 @@
+//! Projects when each skill will reach its `goal_hours` from the pace of
+//! recent practice.
+//!
+//! Pace is measured over trailing 7, 30 and 90 day windows. The 30 day pace
+//! gives the projected date, and the fastest and slowest of the three bound
+//! it, so a skill practised steadily gets a narrow band and a bursty one a
+//! wide band.
+//!
+//! A skill's time includes that of its sub-skills, measured against its own
+//! goal.
+
+use chrono::{Days, Duration, NaiveDate};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::State;
+
+use crate::database::Db;
+use crate::error::Result;
+use crate::repository::subskills::SKILL_TREE;
+use crate::timezone;
+
+/// Trailing windows, in days, that pace is measured over.
+pub const WINDOWS: [i64; 3] = [7, 30, 90];
+
+/// Window whose pace gives the projected date.
+const CENTRAL_WINDOW: usize = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pace {
+    pub window_days: i64,
+    pub minutes_per_day: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MasteryProjection {
+    pub skill_id: String,
+    pub skill_name: String,
+    pub goal_minutes: i64,
+    pub current_minutes: i64,
+    pub remaining_minutes: i64,
+    pub paces: Vec<Pace>,
+    /// `None` when there has been no practice in the central window.
+    pub projected_date: Option<String>,
+    /// Band edges from the fastest and slowest pace. `latest` is `None` when
+    /// any window has no practice, since the goal may then never be reached.
+    pub earliest_date: Option<String>,
+    pub latest_date: Option<String>,
+    pub target_date: Option<String>,
+    /// Minutes a day from today through `target_date` to reach the goal.
+    pub required_daily_minutes: Option<f64>,
+}
+
+/// Totals for one skill as read from the database.
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct SkillPace {
+    pub id: String,
+    pub name: String,
+    pub goal_hours: i64,
+    pub current_minutes: i64,
+    /// Date of the first counted session, if any.
+    pub first_day: Option<String>,
+    pub last_7: i64,
+    pub last_30: i64,
+    pub last_90: i64,
+}
+
+fn round(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+/// Date on which `remaining` minutes run out at `per_day`, counting today
+/// as the first day. `None` when that never happens or is past the end of
+/// the calendar.
+fn finish_date(today: NaiveDate, remaining: i64, per_day: f64) -> Option<NaiveDate> {
+    if remaining <= 0 {
+        return Some(today);
+    }
+    if per_day <= 0.0 {
+        return None;
+    }
+    let days = (remaining as f64 / per_day).ceil() as u64;
+    today.checked_add_days(Days::new(days - 1))
+}
+
+pub fn project(
+    skill: &SkillPace,
+    today: NaiveDate,
+    target: Option<NaiveDate>,
+) -> MasteryProjection {
+    let goal_minutes = skill.goal_hours.saturating_mul(60);
+    let remaining = (goal_minutes - skill.current_minutes).max(0);
+
+    // A skill started ten days ago has only ten days of history, even in
+    // the 90 day window.
+    let history = skill
+        .first_day
+        .as_deref()
+        .and_then(|day| day.parse::<NaiveDate>().ok())
+        .map(|first| (today - first).num_days() + 1)
+        .unwrap_or(0)
+        .max(1);
+    let paces: Vec<Pace> = WINDOWS
+        .iter()
+        .zip([skill.last_7, skill.last_30, skill.last_90])
+        .map(|(&window_days, minutes)| Pace {
+            window_days,
+            minutes_per_day: round(minutes as f64 / window_days.min(history) as f64),
+        })
+        .collect();
+
+    let rates = paces.iter().map(|pace| pace.minutes_per_day);
+    let fastest = rates.clone().fold(0.0, f64::max);
+    let slowest = rates.fold(f64::INFINITY, f64::min);
+    let date = |per_day| finish_date(today, remaining, per_day).map(|d| d.to_string());
+
+    let required_daily_minutes = target.and_then(|target| {
+        let days = (target - today).num_days() + 1;
+        (days > 0).then(|| round(remaining as f64 / days as f64))
+    });
+
+    MasteryProjection {
+        skill_id: skill.id.clone(),
+        skill_name: skill.name.clone(),
+        goal_minutes,
+        current_minutes: skill.current_minutes,
+        remaining_minutes: remaining,
+        projected_date: date(paces[CENTRAL_WINDOW].minutes_per_day),
+        earliest_date: date(fastest),
+        latest_date: date(slowest),
+        paces,
+        target_date: target.map(|d| d.to_string()),
+        required_daily_minutes,
+    }
+}
+
+async fn skill_paces(pool: &SqlitePool, today: NaiveDate) -> Result<Vec<SkillPace>> {
+    let since = |days: i64| (today - Duration::days(days - 1)).to_string();
+    Ok(sqlx::query_as(&format!(
+        "WITH RECURSIVE {SKILL_TREE}
+         SELECT k.id, k.name, k.goal_hours,
+                (SELECT SUM(below.current_minutes) FROM skill_tree t
+                 JOIN skills below ON below.id = t.skill_id
+                 WHERE t.ancestor_id = k.id) AS current_minutes,
+                MIN(CASE WHEN s.type = 'pomodoro' THEN s.local_date END) AS first_day,
+                COALESCE(SUM(CASE WHEN s.local_date >= ? THEN s.duration END), 0) AS last_7,
+                COALESCE(SUM(CASE WHEN s.local_date >= ? THEN s.duration END), 0) AS last_30,
+                COALESCE(SUM(CASE WHEN s.local_date >= ? THEN s.duration END), 0) AS last_90
+         FROM skills k
+         JOIN skill_tree t ON t.ancestor_id = k.id
+         LEFT JOIN timer_sessions s
+           ON s.skill_id = t.skill_id AND s.completed = 1
+          AND s.type IN ('pomodoro', 'adjustment')
+         GROUP BY k.id
+         ORDER BY k.created_at DESC"
+    ))
+    .bind(since(WINDOWS[0]))
+    .bind(since(WINDOWS[1]))
+    .bind(since(WINDOWS[2]))
+    .fetch_all(pool)
+    .await?)
+}
+
+pub async fn projections(
+    pool: &SqlitePool,
+    target: Option<NaiveDate>,
+) -> Result<Vec<MasteryProjection>> {
+    let today = timezone::load(pool).await?.today();
+    Ok(skill_paces(pool, today)
+        .await?
+        .iter()
+        .map(|skill| project(skill, today, target))
+        .collect())
+}
+
+#[tauri::command]
+pub async fn get_mastery_projections(
+    db: State<'_, Db>,
+    target_date: Option<NaiveDate>,
+) -> Result<Vec<MasteryProjection>> {
+    projections(&db.0, target_date).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn skill(current_minutes: i64, first_day: Option<&str>, windows: [i64; 3]) -> SkillPace {
+        SkillPace {
+            id: "piano".into(),
+            name: "Piano".into(),
+            goal_hours: 100,
+            current_minutes,
+            first_day: first_day.map(Into::into),
+            last_7: windows[0],
+            last_30: windows[1],
+            last_90: windows[2],
+        }
+    }
+
+    #[test]
+    fn steady_pace_gives_a_single_date() {
+        // 60 minutes a day for 90 days, 5400 minutes left.
+        let skill = skill(600, Some("2023-12-01"), [420, 1800, 5400]);
+        let projection = project(&skill, date("2024-03-01"), None);
+
+        assert_eq!(projection.remaining_minutes, 5400);
+        assert!(projection.paces.iter().all(|p| p.minutes_per_day == 60.0));
+        assert_eq!(projection.projected_date.as_deref(), Some("2024-05-29"));
+        assert_eq!(projection.earliest_date, projection.projected_date);
+        assert_eq!(projection.latest_date, projection.projected_date);
+    }
+
+    #[test]
+    fn band_spans_fastest_and_slowest_windows() {
+        let skill = skill(0, Some("2023-01-01"), [840, 1800, 2700]);
+        let projection = project(&skill, date("2024-03-01"), None);
+
+        let paces: Vec<f64> = projection.paces.iter().map(|p| p.minutes_per_day).collect();
+        assert_eq!(paces, [120.0, 60.0, 30.0]);
+        assert_eq!(projection.earliest_date.as_deref(), Some("2024-04-19"));
+        assert_eq!(projection.projected_date.as_deref(), Some("2024-06-08"));
+        assert_eq!(projection.latest_date.as_deref(), Some("2024-09-16"));
+    }
+
+    #[test]
+    fn young_skill_is_not_diluted_by_long_windows() {
+        let skill = skill(300, Some("2024-02-26"), [300, 300, 300]);
+        let projection = project(&skill, date("2024-03-01"), None);
+        assert!(projection.paces.iter().all(|p| p.minutes_per_day == 60.0));
+    }
+
+    #[test]
+    fn no_recent_practice_has_no_projection() {
+        let skill = skill(600, Some("2023-01-01"), [0, 0, 600]);
+        let projection = project(&skill, date("2024-03-01"), None);
+
+        assert_eq!(projection.projected_date, None);
+        assert!(projection.earliest_date.is_some());
+        assert_eq!(projection.latest_date, None);
+    }
+
+    #[test]
+    fn goals_past_the_calendar_have_no_date() {
+        let mut skill = skill(0, Some("2023-01-01"), [0, 0, 9]);
+        skill.goal_hours = 1_000_000_000;
+        let projection = project(&skill, date("2024-03-01"), None);
+        assert_eq!(projection.projected_date, None);
+        assert_eq!(projection.latest_date, None);
+    }
+
+    #[test]
+    fn reached_goal_projects_today() {
+        let skill = skill(6000, Some("2023-01-01"), [0, 0, 0]);
+        let projection = project(&skill, date("2024-03-01"), Some(date("2024-03-31")));
+
+        assert_eq!(projection.remaining_minutes, 0);
+        assert_eq!(projection.projected_date.as_deref(), Some("2024-03-01"));
+        assert_eq!(projection.required_daily_minutes, Some(0.0));
+    }
+
+    #[test]
+    fn required_minutes_count_today_and_target() {
+        let skill = skill(3000, None, [0, 0, 0]);
+        let today = date("2024-03-01");
+
+        let projection = project(&skill, today, Some(date("2024-03-30")));
+        assert_eq!(projection.required_daily_minutes, Some(100.0));
+
+        let projection = project(&skill, today, Some(date("2024-02-28")));
+        assert_eq!(projection.required_daily_minutes, None);
+    }
+}
@@ -0,0 +1,23 @@
+//! Startup failures the window reports instead of the app panicking.
+//!
+//! When migrations fail, setup stops before managing the database, so the
+//! regular commands are unavailable. The webview asks [`startup_failure`]
+//! first and shows a recovery dialog when there is one.
+
+use tauri::{AppHandle, State};
+
+use crate::database::MigrationFailure;
+
+/// Managed state; `None` when startup went normally.
+#[derive(Debug, Default)]
+pub struct Recovery(pub Option<MigrationFailure>);
+
+#[tauri::command]
+pub fn startup_failure(recovery: State<'_, Recovery>) -> Option<MigrationFailure> {
+    recovery.0.clone()
+}
+
+#[tauri::command]
+pub fn quit_app(app: AppHandle) {
+    app.exit(1);
+}
@@ -0,0 +1,477 @@
+//! Recurring tasks.
+//!
+//! A task's `recurrence` holds an RFC 5545 `RRULE` such as
+//! `FREQ=WEEKLY;BYDAY=MO,TH`. Every instance of a series keeps the rule and
+//! the tags, the id of the first task in `series_id` and the instance before
+//! it in `previous_id`, so the series reads as a chain. The next instance is
+//! created when one is marked done, and by [`catch_up`] when the latest one
+//! is overdue, so skipping a day of practice still puts today's on the board.
+//!
+//! Only the parts of `RRULE` a practice schedule needs are supported:
+//! `FREQ` (daily to yearly), `INTERVAL`, `COUNT`, `UNTIL`, and `BYDAY`
+//! without ordinals for weekly rules. Occurrences are dates; the time of day
+//! is not tracked. `COUNT` limits how many instances the series gets.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{Datelike, Days, Months, NaiveDate, TimeDelta, Weekday};
+use sqlx::SqlitePool;
+use tauri::{AppHandle, Manager};
+
+use crate::database::{self, Db};
+use crate::error::{Error, Result};
+use crate::repository::tasks::TaskRecord;
+use crate::timezone;
+
+/// How often the scheduler looks for overdue series.
+const CHECK_EVERY: Duration = Duration::from_secs(60 * 60);
+
+/// `INTERVAL`s a rule may have. A thousand years apart is already further
+/// than anyone schedules practice.
+pub const INTERVALS: std::ops::RangeInclusive<u32> = 1..=1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// A parsed `RRULE`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    pub frequency: Frequency,
+    pub interval: u32,
+    /// Weekdays of a weekly rule, Monday first. Empty means the weekday of
+    /// the instance it is counted from.
+    pub by_day: Vec<Weekday>,
+    pub count: Option<u32>,
+    pub until: Option<NaiveDate>,
+}
+
+const WEEKDAYS: [(&str, Weekday); 7] = [
+    ("MO", Weekday::Mon),
+    ("TU", Weekday::Tue),
+    ("WE", Weekday::Wed),
+    ("TH", Weekday::Thu),
+    ("FR", Weekday::Fri),
+    ("SA", Weekday::Sat),
+    ("SU", Weekday::Sun),
+];
+
+fn invalid(message: String) -> Error {
+    Error::Invalid(format!("recurrence: {message}"))
+}
+
+impl FromStr for Rule {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let text = text.trim();
+        let text = match text.get(..6) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("RRULE:") => &text[6..],
+            _ => text,
+        };
+        let mut frequency = None;
+        let mut rule = Rule {
+            frequency: Frequency::Daily,
+            interval: 1,
+            by_day: Vec::new(),
+            count: None,
+            until: None,
+        };
+
+        for part in text.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("`{part}` is not KEY=VALUE")))?;
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        other => return Err(invalid(format!("unsupported FREQ `{other}`"))),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval = value
+                        .parse()
+                        .ok()
+                        .filter(|interval| INTERVALS.contains(interval))
+                        .ok_or_else(|| invalid(format!("bad INTERVAL `{value}`")))?
+                }
+                "COUNT" => {
+                    rule.count = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|count| *count > 0)
+                            .ok_or_else(|| invalid(format!("bad COUNT `{value}`")))?,
+                    )
+                }
+                "UNTIL" => {
+                    // Only the date of a DATE-TIME matters here.
+                    let date = value.get(..8).unwrap_or(value);
+                    rule.until = Some(
+                        NaiveDate::parse_from_str(date, "%Y%m%d")
+                            .map_err(|_| invalid(format!("bad UNTIL `{value}`")))?,
+                    )
+                }
+                "BYDAY" => {
+                    for day in value.split(',') {
+                        let weekday = WEEKDAYS
+                            .iter()
+                            .find(|(name, _)| name.eq_ignore_ascii_case(day))
+                            .map(|(_, weekday)| *weekday)
+                            .ok_or_else(|| invalid(format!("unsupported BYDAY `{day}`")))?;
+                        if !rule.by_day.contains(&weekday) {
+                            rule.by_day.push(weekday);
+                        }
+                    }
+                    rule.by_day.sort_by_key(|day| day.num_days_from_monday());
+                }
+                other => return Err(invalid(format!("unsupported part `{other}`"))),
+            }
+        }
+
+        rule.frequency = frequency.ok_or_else(|| invalid("FREQ is required".into()))?;
+        if rule.count.is_some() && rule.until.is_some() {
+            return Err(invalid("COUNT and UNTIL cannot both be set".into()));
+        }
+        if !rule.by_day.is_empty() && rule.frequency != Frequency::Weekly {
+            return Err(invalid("BYDAY is only supported with FREQ=WEEKLY".into()));
+        }
+        Ok(rule)
+    }
+}
+
+impl fmt::Display for Rule {
+    /// The normalized form stored in `tasks.recurrence`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        };
+        write!(f, "FREQ={frequency}")?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<&str> = self
+                .by_day
+                .iter()
+                .filter_map(|day| WEEKDAYS.iter().find(|(_, w)| w == day))
+                .map(|(name, _)| *name)
+                .collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={count}")?;
+        }
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", until.format("%Y%m%d"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Rule {
+    /// Occurrences counted from `start`, which is itself the first when it
+    /// matches the rule. Ignores `COUNT` and `UNTIL`, and ends where the
+    /// dates run past what `NaiveDate` can hold.
+    fn occurrences(&self, start: NaiveDate) -> impl Iterator<Item = NaiveDate> + '_ {
+        let interval = self.interval;
+        let monday = start.week(Weekday::Mon).first_day();
+        (0u32..)
+            .map_while(move |step| {
+                let n = step.checked_mul(interval)?;
+                let dates: Vec<NaiveDate> = match self.frequency {
+                    Frequency::Daily => vec![start.checked_add_days(Days::new(n.into()))?],
+                    Frequency::Weekly if self.by_day.is_empty() => {
+                        vec![start.checked_add_days(Days::new(u64::from(n) * 7))?]
+                    }
+                    Frequency::Weekly => {
+                        let week = monday.checked_add_signed(TimeDelta::weeks(n.into()))?;
+                        self.by_day
+                            .iter()
+                            .map(|day| {
+                                week.checked_add_signed(TimeDelta::days(
+                                    day.num_days_from_monday().into(),
+                                ))
+                            })
+                            .collect::<Option<_>>()?
+                    }
+                    // Months without the start's day, like 31 April, are skipped.
+                    Frequency::Monthly => start
+                        .with_day(1)?
+                        .checked_add_months(Months::new(n))?
+                        .with_day(start.day())
+                        .into_iter()
+                        .collect(),
+                    Frequency::Yearly => start
+                        .with_day(1)?
+                        .checked_add_months(Months::new(n.checked_mul(12)?))?
+                        .with_day(start.day())
+                        .into_iter()
+                        .collect(),
+                };
+                Some(dates)
+            })
+            .flatten()
+            .filter(move |date| *date >= start)
+    }
+
+    /// The first occurrence after `after`, counting from `start`, if the rule
+    /// has one left. `instances` is how many the series already has.
+    pub fn next_after(
+        &self,
+        start: NaiveDate,
+        after: NaiveDate,
+        instances: u32,
+    ) -> Option<NaiveDate> {
+        if self.count.is_some_and(|count| instances >= count) {
+            return None;
+        }
+        let next = self.occurrences(start).find(|date| *date > after)?;
+        match self.until {
+            Some(until) if next > until => None,
+            _ => Some(next),
+        }
+    }
+}
+
+/// Checks a rule from the webview and returns it in its stored form.
+pub fn normalize(text: &str) -> Result<String> {
+    Ok(text.parse::<Rule>()?.to_string())
+}
+
+fn date_of(text: &str) -> Option<NaiveDate> {
+    text.get(..10)?.parse().ok()
+}
+
+/// Creates the instance after `task`, due on the first occurrence after
+/// `after`, unless it has one already or the rule has run out. Returns the new
+/// instance's id.
+async fn spawn_next(
+    pool: &SqlitePool,
+    task: &TaskRecord,
+    after: NaiveDate,
+) -> Result<Option<String>> {
+    let Some(recurrence) = &task.recurrence else {
+        return Ok(None);
+    };
+    let rule: Rule = recurrence.parse()?;
+    let series_id = task.series_id.as_deref().unwrap_or(&task.id);
+    let (instances, has_next): (i64, bool) = sqlx::query_as(
+        "SELECT (SELECT COUNT(*) FROM tasks WHERE series_id = ?1),
+                EXISTS (SELECT 1 FROM tasks WHERE previous_id = ?2)",
+    )
+    .bind(series_id)
+    .bind(&task.id)
+    .fetch_one(pool)
+    .await?;
+    if has_next {
+        return Ok(None);
+    }
+    let start = task
+        .due_date
+        .as_deref()
+        .and_then(date_of)
+        .or_else(|| date_of(&task.created_at))
+        .unwrap_or(after);
+    let Some(due) = rule.next_after(start, after, instances as u32) else {
+        return Ok(None);
+    };
+
+    let id = database::generate_id("task");
+    let mut tx = pool.begin().await?;
+    // The unique index on previous_id keeps two callers from both adding one.
+    let added = sqlx::query(
+        "INSERT INTO tasks
+            (id, skill_id, title, description, status, priority, due_date,
+             estimated_pomodoros, order_index, recurrence, series_id, previous_id)
+         SELECT ?, skill_id, title, description, 'todo', priority, ?,
+                estimated_pomodoros, order_index, recurrence, ?, id
+         FROM tasks WHERE id = ?
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(&id)
+    .bind(due.to_string())
+    .bind(series_id)
+    .bind(&task.id)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    if added == 0 {
+        return Ok(None);
+    }
+    sqlx::query(
+        "INSERT INTO task_tags (task_id, tag_id) SELECT ?, tag_id FROM task_tags WHERE task_id = ?",
+    )
+    .bind(&id)
+    .bind(&task.id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(Some(id))
+}
+
+/// Called when `task` is marked done: adds the next instance, due after both
+/// this one's due date and today, so finishing late does not leave a trail of
+/// instances that are already overdue.
+pub async fn advance(pool: &SqlitePool, task: &TaskRecord) -> Result<Option<String>> {
+    if task.recurrence.is_none() {
+        return Ok(None);
+    }
+    let today = timezone::load(pool).await?.today();
+    let due = task.due_date.as_deref().and_then(date_of).unwrap_or(today);
+    spawn_next(pool, task, due.max(today)).await
+}
+
+/// Adds today's instance, or the next one, to every series whose latest
+/// instance is overdue and not done. The overdue one stays where it is.
+pub async fn catch_up(pool: &SqlitePool) -> Result<Vec<String>> {
+    let today = timezone::load(pool).await?.today();
+    let overdue: Vec<TaskRecord> = sqlx::query_as(
+        "SELECT * FROM tasks t
+         WHERE recurrence IS NOT NULL AND status != 'done' AND due_date < ?
+           AND NOT EXISTS (SELECT 1 FROM tasks n WHERE n.previous_id = t.id)",
+    )
+    .bind(today.to_string())
+    .fetch_all(pool)
+    .await?;
+
+    let yesterday = today.pred_opt().unwrap_or(today);
+    let mut added = Vec::new();
+    for task in &overdue {
+        if let Some(id) = spawn_next(pool, task, yesterday).await? {
+            added.push(id);
+        }
+    }
+    Ok(added)
+}
+
+/// Runs [`catch_up`] at startup and every [`CHECK_EVERY`] after.
+pub fn spawn_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match catch_up(&app.state::<Db>().0).await {
+                Ok(added) if !added.is_empty() => {
+                    log::info!("added {} recurring task instances", added.len())
+                }
+                Ok(_) => {}
+                Err(err) => log::error!("recurring task schedule failed: {err}"),
+            }
+            tokio::time::sleep(CHECK_EVERY).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn rule(s: &str) -> Rule {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn rules_round_trip_in_normal_form() {
+        assert_eq!(
+            normalize("RRULE:freq=weekly;byday=th,MO;interval=2").unwrap(),
+            "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH"
+        );
+        assert_eq!(
+            normalize("FREQ=DAILY;UNTIL=20240630T235959Z").unwrap(),
+            "FREQ=DAILY;UNTIL=20240630"
+        );
+        for bad in [
+            "",
+            "INTERVAL=2",
+            "FREQ=HOURLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;INTERVAL=1001",
+            "FREQ=DAILY;INTERVAL=4294967295",
+            "FREQ=WEEKLY;BYDAY=MO;INTERVAL=100000000",
+            "FREQ=MONTHLY;BYDAY=1MO",
+            "FREQ=DAILY;BYSETPOS=1",
+            "FREQ=DAILY;COUNT=3;UNTIL=20240101",
+        ] {
+            assert!(normalize(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn weekly_rules_follow_their_weekdays() {
+        let katas = rule("FREQ=WEEKLY;BYDAY=MO,TH");
+        // 2024-03-04 is a Monday.
+        let start = date("2024-03-04");
+        assert_eq!(katas.next_after(start, start, 1), Some(date("2024-03-07")));
+        assert_eq!(
+            katas.next_after(start, date("2024-03-07"), 2),
+            Some(date("2024-03-11"))
+        );
+        let fortnightly = rule("FREQ=WEEKLY;INTERVAL=2");
+        assert_eq!(
+            fortnightly.next_after(start, date("2024-03-05"), 1),
+            Some(date("2024-03-18"))
+        );
+    }
+
+    #[test]
+    fn monthly_rules_skip_short_months() {
+        let monthly = rule("FREQ=MONTHLY");
+        let start = date("2024-01-31");
+        assert_eq!(
+            monthly.next_after(start, start, 1),
+            Some(date("2024-03-31"))
+        );
+        let leap = rule("FREQ=YEARLY");
+        assert_eq!(
+            leap.next_after(date("2024-02-29"), date("2024-02-29"), 1),
+            Some(date("2028-02-29"))
+        );
+    }
+
+    #[test]
+    fn count_and_until_end_the_series() {
+        let start = date("2024-03-01");
+        let three = rule("FREQ=DAILY;COUNT=3");
+        assert_eq!(three.next_after(start, start, 2), Some(date("2024-03-02")));
+        assert_eq!(three.next_after(start, start, 3), None);
+        let until = rule("FREQ=DAILY;UNTIL=20240302");
+        assert_eq!(
+            until.next_after(start, date("2024-03-01"), 1),
+            Some(date("2024-03-02"))
+        );
+        assert_eq!(until.next_after(start, date("2024-03-02"), 2), None);
+    }
+
+    #[test]
+    fn series_end_with_the_calendar() {
+        let start = date("2024-03-04");
+        for text in [
+            "FREQ=DAILY;INTERVAL=1000",
+            "FREQ=WEEKLY;BYDAY=MO,SU;INTERVAL=1000",
+            "FREQ=MONTHLY;INTERVAL=1000",
+            "FREQ=YEARLY;INTERVAL=1000",
+        ] {
+            assert_eq!(
+                rule(text).next_after(start, NaiveDate::MAX, 1),
+                None,
+                "{text}"
+            );
+        }
+    }
+}
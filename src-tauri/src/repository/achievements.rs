@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::SqlitePool;
+
+use crate::achievements::rules::Rule;
+use crate::database;
+use crate::error::{Error, Result};
+
+/// Prefix of the `type` of achievements the user defined with a [`Rule`].
+/// `type` is unique, so each one is followed by its id.
+pub const CUSTOM_PREFIX: &str = "custom:";
+
+#[derive(Clone, Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AchievementRecord {
+    pub id: String,
+    #[serde(rename = "type")]
+    #[sqlx(rename = "type")]
+    pub kind: String,
+    pub name: String,
+    pub description: String,
+    pub icon: String,
+    pub unlocked_at: Option<String>,
+    pub progress: i64,
+    pub target: i64,
+    pub created_at: String,
+    #[serde(default)]
+    pub rule: Option<Json<Rule>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateAchievementInput {
+    pub name: String,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub rule: Rule,
+}
+
+/// All achievements, most recently unlocked first.
+pub async fn list(pool: &SqlitePool) -> Result<Vec<AchievementRecord>> {
+    Ok(
+        sqlx::query_as("SELECT * FROM achievements ORDER BY unlocked_at DESC")
+            .fetch_all(pool)
+            .await?,
+    )
+}
+
+/// Adds a user-defined achievement. Its target is the rule's threshold.
+pub async fn create(pool: &SqlitePool, input: CreateAchievementInput) -> Result<AchievementRecord> {
+    let name = input.name.trim();
+    if name.is_empty() {
+        return Err(Error::Invalid("achievement name is required".into()));
+    }
+    input.rule.validate()?;
+    if let Some(skill_id) = &input.rule.skill_id {
+        let (exists,): (bool,) = sqlx::query_as("SELECT EXISTS(SELECT 1 FROM skills WHERE id = ?)")
+            .bind(skill_id)
+            .fetch_one(pool)
+            .await?;
+        if !exists {
+            return Err(Error::Invalid(format!("skill {skill_id} does not exist")));
+        }
+    }
+
+    let id = database::generate_id("achievement");
+    sqlx::query(
+        "INSERT INTO achievements (id, type, name, description, icon, target, rule)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&id)
+    .bind(format!("{CUSTOM_PREFIX}{id}"))
+    .bind(name)
+    .bind(input.description.as_deref().unwrap_or(""))
+    .bind(input.icon.as_deref().unwrap_or("Target"))
+    .bind(input.rule.threshold)
+    .bind(Json(&input.rule))
+    .execute(pool)
+    .await?;
+
+    sqlx::query_as("SELECT * FROM achievements WHERE id = ?")
+        .bind(&id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| Error::Invalid(format!("achievement {id} does not exist")))
+}
+
+/// Deletes a user-defined achievement. The built-in ones cannot be removed.
+pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
+    let deleted = sqlx::query("DELETE FROM achievements WHERE id = ? AND rule IS NOT NULL")
+        .bind(id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    if deleted == 0 {
+        return Err(Error::Invalid(format!(
+            "achievement {id} is not a custom achievement"
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::analytics;
+use crate::error::Result;
+use crate::timezone;
+
+#[derive(Clone, Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DailyActivityRecord {
+    pub date: String,
+    pub total_minutes: i64,
+    pub total_sessions: i64,
+}
+
+/// Lifetime totals shown on the profile.
+#[derive(Debug, Serialize)]
+pub struct ProfileStats {
+    pub total_minutes: i64,
+    pub current_streak: i64,
+    pub longest_streak: i64,
+}
+
+/// Daily rows from the last `days` local days, oldest first.
+pub async fn list(pool: &SqlitePool, days: i64) -> Result<Vec<DailyActivityRecord>> {
+    let since = timezone::days_before(timezone::load(pool).await?.today(), days)?;
+    Ok(sqlx::query_as(
+        "SELECT * FROM daily_activities
+         WHERE date >= ?
+         ORDER BY date ASC",
+    )
+    .bind(since.to_string())
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Rebuilds the `daily_activities` rows of days with sessions from those
+/// sessions, the same rows the `timer_sessions_aggregate_*` triggers count.
+/// Days without sessions and `skills.current_minutes` are left alone, since
+/// imported history carries minutes without the sessions behind them.
+pub async fn recompute(pool: &SqlitePool) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    recompute_in(&mut tx).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// [`recompute`] inside the caller's transaction, for restores that merge
+/// sessions into a live database.
+pub async fn recompute_in(conn: &mut SqliteConnection) -> Result<()> {
+    sqlx::query(
+        "DELETE FROM daily_activities WHERE date IN (
+            SELECT COALESCE(local_date, date(start_time)) FROM timer_sessions
+            WHERE completed = 1 AND type IN ('pomodoro', 'adjustment')
+         )",
+    )
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query(
+        "INSERT INTO daily_activities (date, total_minutes, total_sessions)
+         SELECT COALESCE(local_date, date(start_time)), SUM(duration), SUM(type = 'pomodoro')
+         FROM timer_sessions
+         WHERE completed = 1 AND type IN ('pomodoro', 'adjustment')
+         GROUP BY COALESCE(local_date, date(start_time))",
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn profile_stats(pool: &SqlitePool) -> Result<ProfileStats> {
+    let (total_minutes,): (Option<i64>,) =
+        sqlx::query_as("SELECT SUM(current_minutes) FROM skills")
+            .fetch_one(pool)
+            .await?;
+
+    let dates: Vec<(String,)> = sqlx::query_as(
+        "SELECT date FROM daily_activities WHERE total_minutes > 0 ORDER BY date ASC",
+    )
+    .fetch_all(pool)
+    .await?;
+    let dates: Vec<NaiveDate> = dates
+        .iter()
+        .filter_map(|(date,)| date.parse().ok())
+        .collect();
+
+    let streaks = analytics::streaks(&dates, timezone::load(pool).await?.today());
+    Ok(ProfileStats {
+        total_minutes: total_minutes.unwrap_or(0),
+        current_streak: streaks.current,
+        longest_streak: streaks.longest,
+    })
+}
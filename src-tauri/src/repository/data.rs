@@ -0,0 +1,231 @@
+//! Whole-database export, import and reset used by the settings page.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use super::achievements::{self, AchievementRecord};
+use super::activities::DailyActivityRecord;
+use super::reflections::{self, ReflectionRecord};
+use super::sessions::{self, TimerSessionRecord};
+use super::settings::{self, UserSettingsRecord};
+use super::skills::{self, SkillRecord};
+use super::tasks::{self, TaskRecord};
+use crate::error::Result;
+use crate::timezone;
+
+/// Every table as records. Keys match the JSON export written by earlier
+/// versions of the app.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DataExport {
+    #[serde(default)]
+    pub skills: Vec<SkillRecord>,
+    #[serde(default)]
+    pub tasks: Vec<TaskRecord>,
+    #[serde(default)]
+    pub sessions: Vec<TimerSessionRecord>,
+    #[serde(default)]
+    pub activities: Vec<DailyActivityRecord>,
+    #[serde(default)]
+    pub reflections: Vec<ReflectionRecord>,
+    #[serde(default)]
+    pub achievements: Vec<AchievementRecord>,
+    #[serde(skip_deserializing)]
+    pub settings: Option<UserSettingsRecord>,
+}
+
+pub async fn export(pool: &SqlitePool) -> Result<DataExport> {
+    Ok(DataExport {
+        skills: skills::list(pool).await?,
+        tasks: tasks::list(pool, None).await?,
+        sessions: sessions::list(pool, None).await?,
+        activities: sqlx::query_as("SELECT * FROM daily_activities ORDER BY date")
+            .fetch_all(pool)
+            .await?,
+        reflections: reflections::list(pool).await?,
+        achievements: achievements::list(pool).await?,
+        // Like backup archives, exports leave the Spotify session behind.
+        settings: Some(UserSettingsRecord {
+            spotify_access_token: None,
+            spotify_refresh_token: None,
+            spotify_token_expiry: None,
+            ..settings::get(pool).await?
+        }),
+    })
+}
+
+/// Merges an export into the database. Rows already here are updated in
+/// place rather than replaced, so their sessions, subtasks, tags and links
+/// stay. Sessions are added when new and left alone when already here;
+/// they bring their minutes into the skill totals and the day they fall
+/// on. A skill new to this database gets the total from the export, which
+/// also covers minutes imported without sessions, and so does a day with
+/// no sessions behind it. A reflection for a date that already has one
+/// keeps the one here.
+pub async fn import(pool: &SqlitePool, data: DataExport) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    let mut new_skills = Vec::new();
+    for skill in &data.skills {
+        let (exists,): (bool,) =
+            sqlx::query_as("SELECT EXISTS (SELECT 1 FROM skills WHERE id = ?)")
+                .bind(&skill.id)
+                .fetch_one(&mut *tx)
+                .await?;
+        if !exists {
+            new_skills.push(skill);
+        }
+        sqlx::query(
+            "INSERT INTO skills
+                (id, name, description, goal_hours, daily_goal_minutes, current_minutes, color, is_active, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                description = excluded.description,
+                goal_hours = excluded.goal_hours,
+                daily_goal_minutes = excluded.daily_goal_minutes,
+                color = excluded.color,
+                is_active = excluded.is_active,
+                updated_at = excluded.updated_at",
+        )
+        .bind(&skill.id)
+        .bind(&skill.name)
+        .bind(&skill.description)
+        .bind(skill.goal_hours)
+        .bind(skill.daily_goal_minutes)
+        .bind(skill.current_minutes)
+        .bind(&skill.color)
+        .bind(skill.is_active)
+        .bind(&skill.created_at)
+        .bind(&skill.updated_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for task in &data.tasks {
+        sqlx::query(
+            "INSERT INTO tasks
+                (id, skill_id, title, description, status, pomodoro_sessions, total_minutes, order_index, created_at, completed_at,
+                 recurrence, series_id, previous_id)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(id) DO UPDATE SET
+                skill_id = excluded.skill_id,
+                title = excluded.title,
+                description = excluded.description,
+                status = excluded.status,
+                order_index = excluded.order_index,
+                completed_at = excluded.completed_at,
+                recurrence = excluded.recurrence,
+                series_id = excluded.series_id,
+                previous_id = excluded.previous_id",
+        )
+        .bind(&task.id)
+        .bind(&task.skill_id)
+        .bind(&task.title)
+        .bind(&task.description)
+        .bind(&task.status)
+        .bind(task.pomodoro_sessions)
+        .bind(task.total_minutes)
+        .bind(task.order_index)
+        .bind(&task.created_at)
+        .bind(&task.completed_at)
+        .bind(&task.recurrence)
+        .bind(&task.series_id)
+        .bind(&task.previous_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for session in &data.sessions {
+        sqlx::query(
+            "INSERT INTO timer_sessions
+                (id, task_id, skill_id, start_time, end_time, duration, type, completed, planned_duration, session_type,
+                 created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(id) DO NOTHING",
+        )
+        .bind(&session.id)
+        .bind(&session.task_id)
+        .bind(&session.skill_id)
+        .bind(&session.start_time)
+        .bind(&session.end_time)
+        .bind(session.duration)
+        .bind(&session.kind)
+        .bind(session.completed)
+        .bind(session.planned_duration)
+        .bind(&session.session_type)
+        .bind(&session.created_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+    // Moves the new sessions onto their local day.
+    timezone::rebucket_in(&mut tx).await?;
+
+    for skill in new_skills {
+        sqlx::query("UPDATE skills SET current_minutes = ? WHERE id = ?")
+            .bind(skill.current_minutes)
+            .bind(&skill.id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    for activity in &data.activities {
+        sqlx::query(
+            "INSERT INTO daily_activities (date, total_minutes, total_sessions)
+             VALUES (?, ?, ?)
+             ON CONFLICT(date) DO NOTHING",
+        )
+        .bind(&activity.date)
+        .bind(activity.total_minutes)
+        .bind(activity.total_sessions)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for reflection in &data.reflections {
+        sqlx::query(
+            "INSERT INTO reflections (id, date, content, mood, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?)
+             ON CONFLICT(id) DO UPDATE SET
+                date = excluded.date,
+                content = excluded.content,
+                mood = excluded.mood,
+                updated_at = excluded.updated_at
+             ON CONFLICT DO NOTHING",
+        )
+        .bind(&reflection.id)
+        .bind(&reflection.date)
+        .bind(&reflection.content)
+        .bind(&reflection.mood)
+        .bind(&reflection.created_at)
+        .bind(&reflection.updated_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Deletes all practice data and locks every achievement again. Settings
+/// and the achievement definitions are kept.
+pub async fn clear(pool: &SqlitePool) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    for table in [
+        "active_timer",
+        "timer_sessions",
+        "reflection_skills",
+        "tasks",
+        "reflections",
+        "daily_activities",
+        "skills",
+    ] {
+        sqlx::query(&format!("DELETE FROM {table}"))
+            .execute(&mut *tx)
+            .await?;
+    }
+    sqlx::query("UPDATE achievements SET progress = 0, unlocked_at = NULL")
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,122 @@
+//! "Blocked by" links between tasks.
+//!
+//! A task is blocked while any task it, or a task above it, is blocked by is
+//! not done. Links that would make a task wait on itself, however
+//! indirectly, are refused, including through its parents: a parent cannot
+//! be blocked by its own subtask, since the subtask inherits the block.
+
+use serde::Serialize;
+use sqlx::{SqliteConnection, SqlitePool};
+
+use super::tasks::{self, TaskRecord};
+use crate::error::{Error, Result};
+
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct DependencyRecord {
+    pub task_id: String,
+    pub blocked_by_id: String,
+    pub created_at: String,
+}
+
+pub async fn list(pool: &SqlitePool) -> Result<Vec<DependencyRecord>> {
+    Ok(
+        sqlx::query_as("SELECT * FROM task_dependencies ORDER BY created_at")
+            .fetch_all(pool)
+            .await?,
+    )
+}
+
+/// Marks `task_id` as blocked by `blocked_by_id`.
+pub async fn add(pool: &SqlitePool, task_id: &str, blocked_by_id: &str) -> Result<()> {
+    if task_id == blocked_by_id {
+        return Err(Error::Invalid(format!(
+            "task {task_id} cannot block itself"
+        )));
+    }
+    for id in [task_id, blocked_by_id] {
+        if tasks::get(pool, id).await?.is_none() {
+            return Err(Error::Invalid(format!("task {id} does not exist")));
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO task_dependencies (task_id, blocked_by_id) VALUES (?, ?)
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(task_id)
+    .bind(blocked_by_id)
+    .execute(&mut *tx)
+    .await?;
+    if waits_on_itself(&mut tx, task_id).await? {
+        return Err(Error::Invalid(format!(
+            "task {blocked_by_id} already waits on {task_id}"
+        )));
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Whether `task_id` or a task below it now waits on itself. Run on the
+/// transaction that made a change, to roll it back when it did.
+pub(super) async fn waits_on_itself(conn: &mut SqliteConnection, task_id: &str) -> Result<bool> {
+    let (found,): (bool,) = sqlx::query_as(
+        "WITH RECURSIVE
+            lineage(id, ancestor) AS (
+                SELECT id, id FROM tasks
+                UNION SELECT lineage.id, s.parent_id FROM lineage
+                JOIN subtasks s ON s.task_id = lineage.ancestor
+            ),
+            subtree(id) AS (
+                SELECT ?1
+                UNION SELECT s.task_id FROM subtasks s JOIN subtree ON s.parent_id = subtree.id
+            ),
+            waits(origin, id) AS (
+                SELECT id, id FROM subtree
+                UNION SELECT waits.origin, d.blocked_by_id FROM waits
+                JOIN lineage ON lineage.id = waits.id
+                JOIN task_dependencies d ON d.task_id = lineage.ancestor
+            )
+         SELECT EXISTS (
+             SELECT 1 FROM waits
+             JOIN lineage ON lineage.id = waits.id
+             JOIN task_dependencies d ON d.task_id = lineage.ancestor
+             WHERE d.blocked_by_id = waits.origin
+         )",
+    )
+    .bind(task_id)
+    .fetch_one(conn)
+    .await?;
+    Ok(found)
+}
+
+pub async fn remove(pool: &SqlitePool, task_id: &str, blocked_by_id: &str) -> Result<()> {
+    sqlx::query("DELETE FROM task_dependencies WHERE task_id = ? AND blocked_by_id = ?")
+        .bind(task_id)
+        .bind(blocked_by_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Tasks that are not done and not blocked, in board order.
+pub async fn unblocked(pool: &SqlitePool, skill_id: Option<&str>) -> Result<Vec<TaskRecord>> {
+    Ok(sqlx::query_as(
+        "WITH RECURSIVE lineage(task_id, id) AS (
+            SELECT id, id FROM tasks WHERE status != 'done' AND (?1 IS NULL OR skill_id = ?1)
+            UNION SELECT lineage.task_id, s.parent_id FROM subtasks s JOIN lineage ON s.task_id = lineage.id
+         )
+         SELECT * FROM tasks
+         WHERE status != 'done' AND (?1 IS NULL OR skill_id = ?1)
+           AND id NOT IN (
+               SELECT lineage.task_id FROM lineage
+               JOIN task_dependencies d ON d.task_id = lineage.id
+               JOIN tasks blocker ON blocker.id = d.blocked_by_id
+               WHERE blocker.status != 'done'
+           )
+         ORDER BY order_index, created_at DESC",
+    )
+    .bind(skill_id)
+    .fetch_all(pool)
+    .await?)
+}
@@ -0,0 +1,36 @@
+//! Typed access to the tables created by [`crate::database::get_migrations`].
+//!
+//! Records serialize with the column names as keys, so the frontend maps them
+//! the same way it mapped raw `SELECT *` rows. Inputs deserialize from the
+//! camelCase shapes in `src/types`.
+
+pub mod achievements;
+pub mod activities;
+pub mod data;
+pub mod dependencies;
+pub mod reflections;
+pub mod sessions;
+pub mod settings;
+pub mod skills;
+pub mod subskills;
+pub mod subtasks;
+pub mod tags;
+pub mod tasks;
+
+use serde::{Deserialize, Deserializer};
+
+/// Reads a flag stored as `true`/`false` or as the `0`/`1` that raw SQLite
+/// rows carry in exports written by the webview.
+fn bool_from_int<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Flag {
+        Bool(bool),
+        Int(i64),
+    }
+
+    Ok(match Flag::deserialize(deserializer)? {
+        Flag::Bool(flag) => flag,
+        Flag::Int(value) => value != 0,
+    })
+}
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::database;
+use crate::error::Result;
+
+#[derive(Clone, Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ReflectionRecord {
+    pub id: String,
+    pub date: String,
+    pub content: String,
+    pub mood: Option<String>,
+    pub total_minutes: Option<i64>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// A reflection together with the skills linked to it in `reflection_skills`.
+#[derive(Debug, Serialize)]
+pub struct ReflectionWithSkills {
+    #[serde(flatten)]
+    pub reflection: ReflectionRecord,
+    pub skill_ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveReflectionInput {
+    pub date: String,
+    pub content: String,
+    pub mood: Option<String>,
+    pub skill_ids: Option<Vec<String>>,
+}
+
+pub async fn list(pool: &SqlitePool) -> Result<Vec<ReflectionRecord>> {
+    Ok(
+        sqlx::query_as("SELECT * FROM reflections ORDER BY date DESC")
+            .fetch_all(pool)
+            .await?,
+    )
+}
+
+pub async fn dates(pool: &SqlitePool) -> Result<Vec<String>> {
+    let rows: Vec<(String,)> = sqlx::query_as("SELECT date FROM reflections")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.into_iter().map(|(date,)| date).collect())
+}
+
+pub async fn get_by_date(pool: &SqlitePool, date: &str) -> Result<Option<ReflectionWithSkills>> {
+    let Some(reflection) =
+        sqlx::query_as::<_, ReflectionRecord>("SELECT * FROM reflections WHERE date = ?")
+            .bind(date)
+            .fetch_optional(pool)
+            .await?
+    else {
+        return Ok(None);
+    };
+
+    let skill_ids: Vec<(String,)> =
+        sqlx::query_as("SELECT skill_id FROM reflection_skills WHERE reflection_id = ?")
+            .bind(&reflection.id)
+            .fetch_all(pool)
+            .await?;
+
+    Ok(Some(ReflectionWithSkills {
+        reflection,
+        skill_ids: skill_ids.into_iter().map(|(id,)| id).collect(),
+    }))
+}
+
+/// Creates or updates the reflection for `input.date`. Linked skills are only
+/// replaced when `skill_ids` is given.
+pub async fn save(pool: &SqlitePool, input: SaveReflectionInput) -> Result<ReflectionWithSkills> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "INSERT INTO reflections (id, date, content, mood) VALUES (?, ?, ?, ?)
+         ON CONFLICT(date) DO UPDATE SET
+            content = excluded.content,
+            mood = excluded.mood,
+            updated_at = datetime('now')",
+    )
+    .bind(database::generate_id("reflection"))
+    .bind(&input.date)
+    .bind(&input.content)
+    .bind(&input.mood)
+    .execute(&mut *tx)
+    .await?;
+
+    if let Some(skill_ids) = &input.skill_ids {
+        let (id,): (String,) = sqlx::query_as("SELECT id FROM reflections WHERE date = ?")
+            .bind(&input.date)
+            .fetch_one(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM reflection_skills WHERE reflection_id = ?")
+            .bind(&id)
+            .execute(&mut *tx)
+            .await?;
+        for skill_id in skill_ids {
+            sqlx::query("INSERT INTO reflection_skills (reflection_id, skill_id) VALUES (?, ?)")
+                .bind(&id)
+                .bind(skill_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+
+    tx.commit().await?;
+    Ok(get_by_date(pool, &input.date)
+        .await?
+        .expect("reflection was just saved"))
+}
@@ -0,0 +1,374 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::database;
+use crate::error::{Error, Result};
+use crate::timezone;
+
+#[derive(Clone, Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TimerSessionRecord {
+    pub id: String,
+    pub task_id: Option<String>,
+    pub skill_id: String,
+    pub start_time: String,
+    pub end_time: Option<String>,
+    pub duration: i64,
+    #[serde(rename = "type")]
+    #[sqlx(rename = "type")]
+    pub kind: String,
+    #[serde(deserialize_with = "super::bool_from_int")]
+    pub completed: bool,
+    pub planned_duration: Option<i64>,
+    pub session_type: Option<String>,
+    pub created_at: String,
+}
+
+/// Which process is running a session. The app and the `tenk` CLI share the
+/// database, and only one of them may run a session at a time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum Owner {
+    App,
+    Cli,
+}
+
+/// The session currently claimed in `active_timer`.
+#[derive(Clone, Debug)]
+pub struct Running {
+    pub owner: Owner,
+    pub session: TimerSessionRecord,
+}
+
+/// A session row written when a timer starts, before any time is credited.
+#[derive(Debug)]
+pub struct NewSession<'a> {
+    pub owner: Owner,
+    pub id: &'a str,
+    pub task_id: Option<&'a str>,
+    pub skill_id: &'a str,
+    pub start_time: &'a str,
+    pub planned_minutes: i64,
+    pub kind: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSessionInput {
+    pub id: String,
+    pub end_time: Option<String>,
+    pub completed: Option<bool>,
+    pub duration: Option<i64>,
+}
+
+/// Minutes per skill for one day, as shown on the dashboard.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct SkillMinutes {
+    pub skill_id: String,
+    pub total_minutes: i64,
+}
+
+/// Minutes per calendar day, as used by the activity heatmap.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct DayMinutes {
+    pub activity_date: String,
+    pub total_minutes: i64,
+}
+
+pub async fn list(pool: &SqlitePool, skill_id: Option<&str>) -> Result<Vec<TimerSessionRecord>> {
+    Ok(sqlx::query_as(
+        "SELECT * FROM timer_sessions WHERE ?1 IS NULL OR skill_id = ?1
+         ORDER BY start_time DESC",
+    )
+    .bind(skill_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+pub async fn get(pool: &SqlitePool, id: &str) -> Result<Option<TimerSessionRecord>> {
+    Ok(sqlx::query_as("SELECT * FROM timer_sessions WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?)
+}
+
+/// Writes the session row and claims `active_timer` for it in one
+/// transaction. Fails without writing anything while another session, from
+/// either process, is running.
+pub async fn start(pool: &SqlitePool, session: &NewSession<'_>) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO timer_sessions
+            (id, task_id, skill_id, start_time, duration, type, completed, planned_duration, session_type)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, 0, ?5, ?6)",
+    )
+    .bind(session.id)
+    .bind(session.task_id)
+    .bind(session.skill_id)
+    .bind(session.start_time)
+    .bind(session.planned_minutes)
+    .bind(session.kind)
+    .execute(&mut *tx)
+    .await?;
+    timezone::stamp(&mut tx, session.id).await?;
+    claim(&mut tx, session.id, session.owner).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Claims `active_timer` for an existing session, as when the app picks up
+/// one a crash left unfinished.
+pub async fn reclaim(pool: &SqlitePool, id: &str, owner: Owner) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    claim(&mut tx, id, owner).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn claim(conn: &mut SqliteConnection, id: &str, owner: Owner) -> Result<()> {
+    // Deleting a skill takes its sessions with it, claim or not.
+    sqlx::query("DELETE FROM active_timer WHERE session_id NOT IN (SELECT id FROM timer_sessions)")
+        .execute(&mut *conn)
+        .await?;
+    let claimed = sqlx::query(
+        "INSERT INTO active_timer (id, session_id, owner) VALUES (1, ?, ?)
+         ON CONFLICT(id) DO NOTHING",
+    )
+    .bind(id)
+    .bind(owner)
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+    if claimed == 0 {
+        let (owner,): (Owner,) = sqlx::query_as("SELECT owner FROM active_timer WHERE id = 1")
+            .fetch_one(&mut *conn)
+            .await?;
+        return Err(Error::Timer(match owner {
+            Owner::App => "already running in the app",
+            Owner::Cli => "already running in the tenk CLI",
+        }));
+    }
+    Ok(())
+}
+
+/// Records that a running session had counted `elapsed_seconds` at `at`, so
+/// it can be closed there if the app dies. See [`crate::unfinished`].
+pub async fn heartbeat(pool: &SqlitePool, id: &str, at: &str, elapsed_seconds: i64) -> Result<()> {
+    sqlx::query(
+        "UPDATE timer_sessions SET heartbeat_at = ?, elapsed_seconds = ?
+         WHERE id = ? AND completed = 0",
+    )
+    .bind(at)
+    .bind(elapsed_seconds)
+    .bind(id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// The running session and who started it, if any.
+pub async fn running(pool: &SqlitePool) -> Result<Option<Running>> {
+    let claim: Option<(String, Owner)> =
+        sqlx::query_as("SELECT session_id, owner FROM active_timer WHERE id = 1")
+            .fetch_optional(pool)
+            .await?;
+    let Some((id, owner)) = claim else {
+        return Ok(None);
+    };
+    Ok(get(pool, &id)
+        .await?
+        .map(|session| Running { owner, session }))
+}
+
+/// Drops a claim `owner` left behind, as when the app exits mid-session. The
+/// session row itself is kept.
+pub async fn release(pool: &SqlitePool, owner: Owner) -> Result<()> {
+    sqlx::query("DELETE FROM active_timer WHERE owner = ?")
+        .bind(owner)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Marks a session completed with `minutes` of work. Pomodoros also credit
+/// their task in the same transaction, and `full_pomodoro` counts one more
+/// pomodoro on it. Skill and daily totals follow from the triggers added in
+/// migration 9.
+pub async fn complete(
+    pool: &SqlitePool,
+    id: &str,
+    minutes: i64,
+    full_pomodoro: bool,
+) -> Result<()> {
+    complete_at(pool, id, &database::now_iso(), minutes, full_pomodoro).await
+}
+
+/// [`complete`] for a session that ended at `end_time` rather than now, as
+/// when the computer went to sleep during it.
+pub async fn complete_at(
+    pool: &SqlitePool,
+    id: &str,
+    end_time: &str,
+    minutes: i64,
+    full_pomodoro: bool,
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    let session: TimerSessionRecord = sqlx::query_as("SELECT * FROM timer_sessions WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| Error::Invalid(format!("session {id} does not exist")))?;
+
+    sqlx::query("UPDATE timer_sessions SET end_time = ?, duration = ?, completed = 1 WHERE id = ?")
+        .bind(end_time)
+        .bind(minutes)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    timezone::stamp(&mut tx, id).await?;
+
+    sqlx::query("DELETE FROM active_timer WHERE session_id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+    if session.kind == "pomodoro" {
+        if let Some(task_id) = &session.task_id {
+            sqlx::query(
+                "UPDATE tasks SET pomodoro_sessions = pomodoro_sessions + ?,
+                                  total_minutes = total_minutes + ?
+                 WHERE id = ?",
+            )
+            .bind(i64::from(full_pomodoro))
+            .bind(minutes)
+            .bind(task_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Stores time entered by hand as an already completed pomodoro session, which
+/// the aggregate triggers add to the skill and the day. Negative `minutes`
+/// take time back off as an `adjustment`, which counts for minutes but not as
+/// a session.
+pub async fn record_manual(
+    pool: &SqlitePool,
+    skill_id: &str,
+    task_id: Option<&str>,
+    minutes: i64,
+) -> Result<TimerSessionRecord> {
+    if minutes == 0 {
+        return Err(Error::Invalid("no minutes to record".into()));
+    }
+    let kind = if minutes > 0 {
+        "pomodoro"
+    } else {
+        "adjustment"
+    };
+    let id = database::generate_id("session");
+    let now = database::now_iso();
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO timer_sessions
+            (id, task_id, skill_id, start_time, end_time, duration, type, completed, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?4, ?5, ?6, 1, ?4)",
+    )
+    .bind(&id)
+    .bind(task_id)
+    .bind(skill_id)
+    .bind(&now)
+    .bind(minutes)
+    .bind(kind)
+    .execute(&mut *tx)
+    .await?;
+    timezone::stamp(&mut tx, &id).await?;
+    tx.commit().await?;
+    get(pool, &id)
+        .await?
+        .ok_or_else(|| Error::Invalid(format!("session {id} does not exist")))
+}
+
+pub async fn update(pool: &SqlitePool, input: UpdateSessionInput) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "UPDATE timer_sessions SET
+            end_time = COALESCE(?, end_time),
+            completed = COALESCE(?, completed),
+            duration = COALESCE(?, duration)
+         WHERE id = ?",
+    )
+    .bind(&input.end_time)
+    .bind(input.completed)
+    .bind(input.duration)
+    .bind(&input.id)
+    .execute(&mut *tx)
+    .await?;
+    if input.end_time.is_some() {
+        timezone::stamp(&mut tx, &input.id).await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM active_timer WHERE session_id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM timer_sessions WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Completed minutes per skill on the local day `date` (`YYYY-MM-DD`),
+/// corrections included.
+pub async fn minutes_by_skill_on(pool: &SqlitePool, date: &str) -> Result<Vec<SkillMinutes>> {
+    Ok(sqlx::query_as(
+        "SELECT skill_id, SUM(duration) AS total_minutes
+         FROM timer_sessions
+         WHERE completed = 1 AND type IN ('pomodoro', 'adjustment') AND local_date = date(?)
+         GROUP BY skill_id",
+    )
+    .bind(date)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Completed minutes per local day over the last `days` days, corrections
+/// included.
+pub async fn minutes_by_day(pool: &SqlitePool, days: i64) -> Result<Vec<DayMinutes>> {
+    let since = timezone::days_before(timezone::load(pool).await?.today(), days)?;
+    Ok(sqlx::query_as(
+        "SELECT local_date AS activity_date, SUM(duration) AS total_minutes
+         FROM timer_sessions
+         WHERE completed = 1 AND type IN ('pomodoro', 'adjustment') AND local_date >= ?
+         GROUP BY local_date",
+    )
+    .bind(since.to_string())
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Completed minutes logged to one skill on the local day `date`
+/// (`YYYY-MM-DD`).
+pub async fn skill_minutes_on(pool: &SqlitePool, skill_id: &str, date: &str) -> Result<i64> {
+    let (minutes,): (Option<i64>,) = sqlx::query_as(
+        "SELECT SUM(duration) FROM timer_sessions
+         WHERE skill_id = ? AND local_date = ? AND completed = 1
+           AND type IN ('pomodoro', 'adjustment')",
+    )
+    .bind(skill_id)
+    .bind(date)
+    .fetch_one(pool)
+    .await?;
+    Ok(minutes.unwrap_or(0))
+}
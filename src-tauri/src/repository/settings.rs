@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::error::{Error, Result};
+use crate::timer::SleepPolicy;
+use crate::timezone;
+use crate::unfinished::RecoveryPolicy;
+
+/// The single `user_settings` row (`id = 1`).
+#[derive(Clone, Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserSettingsRecord {
+    pub id: i64,
+    pub name: String,
+    pub email: Option<String>,
+    pub avatar: Option<String>,
+    pub theme: String,
+    #[serde(deserialize_with = "super::bool_from_int")]
+    pub sound_enabled: bool,
+    #[serde(deserialize_with = "super::bool_from_int")]
+    pub notifications_enabled: bool,
+    pub pomodoro_duration: i64,
+    pub short_break_duration: i64,
+    pub long_break_duration: i64,
+    #[serde(deserialize_with = "super::bool_from_int")]
+    pub auto_start_breaks: bool,
+    #[serde(deserialize_with = "super::bool_from_int")]
+    pub auto_start_pomodoros: bool,
+    pub long_break_interval: i64,
+    pub daily_goal_minutes: i64,
+    pub weekly_goal_minutes: i64,
+    #[serde(deserialize_with = "super::bool_from_int")]
+    pub spotify_enabled: bool,
+    pub spotify_access_token: Option<String>,
+    pub spotify_refresh_token: Option<String>,
+    pub spotify_token_expiry: Option<String>,
+    /// IANA zone days are counted in; see [`crate::timezone`].
+    pub timezone: Option<String>,
+    /// Local hour days start at, 0 for midnight.
+    pub day_start_hour: i64,
+    pub sleep_policy: SleepPolicy,
+    pub recovery_policy: RecoveryPolicy,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Columns to change; unset fields keep their value.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSettingsInput {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub avatar: Option<String>,
+    pub theme: Option<String>,
+    pub sound_enabled: Option<bool>,
+    pub notifications_enabled: Option<bool>,
+    pub pomodoro_duration: Option<i64>,
+    pub short_break_duration: Option<i64>,
+    pub long_break_duration: Option<i64>,
+    pub auto_start_breaks: Option<bool>,
+    pub auto_start_pomodoros: Option<bool>,
+    pub long_break_interval: Option<i64>,
+    pub daily_goal_minutes: Option<i64>,
+    pub weekly_goal_minutes: Option<i64>,
+    /// Re-buckets past sessions when it changes.
+    pub timezone: Option<String>,
+    /// Re-buckets past sessions when it changes.
+    pub day_start_hour: Option<i64>,
+    pub sleep_policy: Option<SleepPolicy>,
+    pub recovery_policy: Option<RecoveryPolicy>,
+}
+
+/// Local HTTP API settings. Kept out of [`UserSettingsRecord`] so the token
+/// never ends up in an export.
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiSettings {
+    pub api_enabled: bool,
+    pub api_port: i64,
+    pub api_token: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateApiSettingsInput {
+    pub enabled: Option<bool>,
+    pub port: Option<i64>,
+}
+
+/// Returns the settings row, recreating it with defaults if it went missing.
+pub async fn get(pool: &SqlitePool) -> Result<UserSettingsRecord> {
+    sqlx::query("INSERT OR IGNORE INTO user_settings (id, name) VALUES (1, 'User')")
+        .execute(pool)
+        .await?;
+    Ok(sqlx::query_as("SELECT * FROM user_settings WHERE id = 1")
+        .fetch_one(pool)
+        .await?)
+}
+
+pub async fn update(pool: &SqlitePool, input: UpdateSettingsInput) -> Result<UserSettingsRecord> {
+    if let Some(name) = &input.timezone {
+        timezone::Zone::new(name)?;
+    }
+    if let Some(hour) = input.day_start_hour {
+        timezone::Zone::utc().starting_at(hour)?;
+    }
+    // The settings and the sessions re-bucketed for a new zone or day start
+    // change together or not at all.
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "UPDATE user_settings SET
+            name = COALESCE(?, name),
+            email = COALESCE(?, email),
+            avatar = COALESCE(?, avatar),
+            theme = COALESCE(?, theme),
+            sound_enabled = COALESCE(?, sound_enabled),
+            notifications_enabled = COALESCE(?, notifications_enabled),
+            pomodoro_duration = COALESCE(?, pomodoro_duration),
+            short_break_duration = COALESCE(?, short_break_duration),
+            long_break_duration = COALESCE(?, long_break_duration),
+            auto_start_breaks = COALESCE(?, auto_start_breaks),
+            auto_start_pomodoros = COALESCE(?, auto_start_pomodoros),
+            long_break_interval = COALESCE(?, long_break_interval),
+            daily_goal_minutes = COALESCE(?, daily_goal_minutes),
+            weekly_goal_minutes = COALESCE(?, weekly_goal_minutes),
+            sleep_policy = COALESCE(?, sleep_policy),
+            recovery_policy = COALESCE(?, recovery_policy),
+            timezone = COALESCE(?, timezone),
+            day_start_hour = COALESCE(?, day_start_hour),
+            updated_at = datetime('now')
+         WHERE id = 1",
+    )
+    .bind(&input.name)
+    .bind(&input.email)
+    .bind(&input.avatar)
+    .bind(&input.theme)
+    .bind(input.sound_enabled)
+    .bind(input.notifications_enabled)
+    .bind(input.pomodoro_duration)
+    .bind(input.short_break_duration)
+    .bind(input.long_break_duration)
+    .bind(input.auto_start_breaks)
+    .bind(input.auto_start_pomodoros)
+    .bind(input.long_break_interval)
+    .bind(input.daily_goal_minutes)
+    .bind(input.weekly_goal_minutes)
+    .bind(input.sleep_policy)
+    .bind(input.recovery_policy)
+    .bind(&input.timezone)
+    .bind(input.day_start_hour)
+    .execute(&mut *tx)
+    .await?;
+    if input.timezone.is_some() || input.day_start_hour.is_some() {
+        timezone::rebucket_in(&mut tx).await?;
+    }
+    tx.commit().await?;
+    get(pool).await
+}
+
+pub async fn api(pool: &SqlitePool) -> Result<ApiSettings> {
+    get(pool).await?;
+    Ok(
+        sqlx::query_as("SELECT api_enabled, api_port, api_token FROM user_settings WHERE id = 1")
+            .fetch_one(pool)
+            .await?,
+    )
+}
+
+/// Updates the API settings. Enabling the API for the first time creates its
+/// token.
+pub async fn update_api(pool: &SqlitePool, input: UpdateApiSettingsInput) -> Result<ApiSettings> {
+    if let Some(port) = input.port {
+        if !(1024..=65535).contains(&port) {
+            return Err(Error::Invalid(format!("port {port} is outside 1024-65535")));
+        }
+    }
+    sqlx::query(
+        "UPDATE user_settings SET
+            api_enabled = COALESCE(?, api_enabled),
+            api_port = COALESCE(?, api_port),
+            api_token = COALESCE(api_token, ?),
+            updated_at = datetime('now')
+         WHERE id = 1",
+    )
+    .bind(input.enabled)
+    .bind(input.port)
+    .bind(new_token())
+    .execute(pool)
+    .await?;
+    api(pool).await
+}
+
+/// Replaces the API token, locking out every client using the old one.
+pub async fn regenerate_api_token(pool: &SqlitePool) -> Result<ApiSettings> {
+    get(pool).await?;
+    sqlx::query(
+        "UPDATE user_settings SET api_token = ?, updated_at = datetime('now') WHERE id = 1",
+    )
+    .bind(new_token())
+    .execute(pool)
+    .await?;
+    api(pool).await
+}
+
+fn new_token() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::database;
+use crate::error::{Error, Result};
+
+#[derive(Clone, Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SkillRecord {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub goal_hours: i64,
+    #[serde(default = "default_daily_goal")]
+    pub daily_goal_minutes: i64,
+    pub current_minutes: i64,
+    pub color: String,
+    #[serde(deserialize_with = "super::bool_from_int")]
+    pub is_active: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSkillInput {
+    pub name: String,
+    pub description: Option<String>,
+    pub goal_hours: i64,
+    pub daily_goal_minutes: Option<i64>,
+    pub color: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSkillInput {
+    pub id: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub goal_hours: Option<i64>,
+    pub daily_goal_minutes: Option<i64>,
+    pub color: Option<String>,
+}
+
+/// Daily target for skills created or imported without one.
+const DEFAULT_DAILY_GOAL: i64 = 60;
+
+fn default_daily_goal() -> i64 {
+    DEFAULT_DAILY_GOAL
+}
+
+pub async fn list(pool: &SqlitePool) -> Result<Vec<SkillRecord>> {
+    Ok(
+        sqlx::query_as("SELECT * FROM skills ORDER BY created_at DESC")
+            .fetch_all(pool)
+            .await?,
+    )
+}
+
+pub async fn get(pool: &SqlitePool, id: &str) -> Result<Option<SkillRecord>> {
+    Ok(sqlx::query_as("SELECT * FROM skills WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?)
+}
+
+async fn fetch(pool: &SqlitePool, id: &str) -> Result<SkillRecord> {
+    get(pool, id)
+        .await?
+        .ok_or_else(|| Error::Invalid(format!("skill {id} does not exist")))
+}
+
+pub async fn create(pool: &SqlitePool, input: CreateSkillInput) -> Result<SkillRecord> {
+    let id = database::generate_id("skill");
+    sqlx::query(
+        "INSERT INTO skills
+            (id, name, description, goal_hours, daily_goal_minutes, current_minutes, color, is_active)
+         VALUES (?, ?, ?, ?, ?, 0, ?, 0)",
+    )
+    .bind(&id)
+    .bind(&input.name)
+    .bind(&input.description)
+    .bind(input.goal_hours)
+    .bind(input.daily_goal_minutes.unwrap_or(DEFAULT_DAILY_GOAL))
+    .bind(input.color.as_deref().unwrap_or("#1A73E8"))
+    .execute(pool)
+    .await?;
+    fetch(pool, &id).await
+}
+
+pub async fn update(pool: &SqlitePool, input: UpdateSkillInput) -> Result<SkillRecord> {
+    sqlx::query(
+        "UPDATE skills SET
+            name = COALESCE(?, name),
+            description = COALESCE(?, description),
+            goal_hours = COALESCE(?, goal_hours),
+            daily_goal_minutes = COALESCE(?, daily_goal_minutes),
+            color = COALESCE(?, color),
+            updated_at = datetime('now')
+         WHERE id = ?",
+    )
+    .bind(&input.name)
+    .bind(&input.description)
+    .bind(input.goal_hours)
+    .bind(input.daily_goal_minutes)
+    .bind(&input.color)
+    .bind(&input.id)
+    .execute(pool)
+    .await?;
+    fetch(pool, &input.id).await
+}
+
+/// Deletes a skill and its sessions. Skills that still have tasks are kept so
+/// tasks are never removed as a side effect.
+pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    let (tasks,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM tasks WHERE skill_id = ?")
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if tasks > 0 {
+        return Err(Error::Invalid(
+            "Cannot delete skill with existing tasks. Please delete or reassign tasks first."
+                .into(),
+        ));
+    }
+
+    sqlx::query("DELETE FROM timer_sessions WHERE skill_id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM skills WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Makes `id` the only active skill, or clears the active skill for `None`.
+pub async fn set_active(pool: &SqlitePool, id: Option<&str>) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE skills SET is_active = 0 WHERE is_active != 0")
+        .execute(&mut *tx)
+        .await?;
+    if let Some(id) = id {
+        sqlx::query("UPDATE skills SET is_active = 1 WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
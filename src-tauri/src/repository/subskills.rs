@@ -0,0 +1,149 @@
+//! Parent/child links between skills, such as Music > Piano > Jazz voicings.
+//!
+//! A skill has at most one parent and the links never loop. Each skill keeps
+//! its own `current_minutes` and `goal_hours`; time logged to a skill also
+//! counts toward every skill above it, which [`SKILL_TREE`] and [`rollups`]
+//! work out.
+
+use serde::Serialize;
+use sqlx::{SqliteExecutor, SqlitePool};
+
+use super::skills::{self, SkillRecord};
+use crate::error::{Error, Result};
+
+/// `(ancestor_id, skill_id)` for every skill and each skill at or below it,
+/// to use in a `WITH RECURSIVE` clause. Joining sessions on `skill_id` and
+/// grouping by `ancestor_id` gives each skill its rolled-up time.
+pub const SKILL_TREE: &str = "skill_tree(ancestor_id, skill_id) AS (
+    SELECT id, id FROM skills
+    UNION SELECT skill_tree.ancestor_id, s.skill_id FROM subskills s
+    JOIN skill_tree ON s.parent_id = skill_tree.skill_id
+)";
+
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct SubskillRecord {
+    pub skill_id: String,
+    pub parent_id: String,
+    pub created_at: String,
+}
+
+/// A skill's time including every skill below it, against its own goal.
+#[derive(Clone, Debug, PartialEq, Serialize, sqlx::FromRow)]
+pub struct SkillRollup {
+    pub skill_id: String,
+    pub parent_id: Option<String>,
+    pub goal_hours: i64,
+    /// Time logged to this skill itself.
+    pub own_minutes: i64,
+    pub total_minutes: i64,
+    /// Skills below this one, at any depth.
+    pub subskills: i64,
+    pub progress_percentage: f64,
+}
+
+pub async fn list(pool: &SqlitePool) -> Result<Vec<SubskillRecord>> {
+    Ok(
+        sqlx::query_as("SELECT * FROM subskills ORDER BY created_at")
+            .fetch_all(pool)
+            .await?,
+    )
+}
+
+/// The direct sub-skills of `parent_id`.
+pub async fn children(pool: &SqlitePool, parent_id: &str) -> Result<Vec<SkillRecord>> {
+    Ok(sqlx::query_as(
+        "SELECT k.* FROM skills k JOIN subskills s ON s.skill_id = k.id
+         WHERE s.parent_id = ?
+         ORDER BY k.name",
+    )
+    .bind(parent_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Whether `ancestor` is `skill_id` or above it.
+async fn is_at_or_above<'e>(
+    executor: impl SqliteExecutor<'e>,
+    ancestor: &str,
+    skill_id: &str,
+) -> Result<bool> {
+    let (found,): (bool,) = sqlx::query_as(
+        "WITH RECURSIVE ancestors(id) AS (
+            SELECT ?1
+            UNION SELECT s.parent_id FROM subskills s JOIN ancestors a ON s.skill_id = a.id
+         )
+         SELECT EXISTS (SELECT 1 FROM ancestors WHERE id = ?2)",
+    )
+    .bind(skill_id)
+    .bind(ancestor)
+    .fetch_one(executor)
+    .await?;
+    Ok(found)
+}
+
+/// Moves `skill_id` under `parent_id`, or to the top level when it is `None`.
+/// Refuses to put a skill under itself or one of its own sub-skills.
+pub async fn set_parent(pool: &SqlitePool, skill_id: &str, parent_id: Option<&str>) -> Result<()> {
+    let Some(parent_id) = parent_id else {
+        sqlx::query("DELETE FROM subskills WHERE skill_id = ?")
+            .bind(skill_id)
+            .execute(pool)
+            .await?;
+        return Ok(());
+    };
+    for id in [skill_id, parent_id] {
+        if skills::get(pool, id).await?.is_none() {
+            return Err(Error::Invalid(format!("skill {id} does not exist")));
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+    if is_at_or_above(&mut *tx, skill_id, parent_id).await? {
+        return Err(Error::Invalid(format!(
+            "skill {parent_id} is a sub-skill of {skill_id}, so it cannot be its parent"
+        )));
+    }
+    sqlx::query(
+        "INSERT INTO subskills (skill_id, parent_id) VALUES (?, ?)
+         ON CONFLICT (skill_id) DO UPDATE SET parent_id = excluded.parent_id",
+    )
+    .bind(skill_id)
+    .bind(parent_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Rolled-up time for every skill, in name order.
+pub async fn rollups(pool: &SqlitePool) -> Result<Vec<SkillRollup>> {
+    Ok(sqlx::query_as(&format!(
+        "WITH RECURSIVE {SKILL_TREE}
+         SELECT k.id AS skill_id,
+                p.parent_id,
+                k.goal_hours,
+                k.current_minutes AS own_minutes,
+                SUM(below.current_minutes) AS total_minutes,
+                COUNT(*) - 1 AS subskills,
+                CASE WHEN k.goal_hours > 0
+                     THEN ROUND(SUM(below.current_minutes) / 60.0 / k.goal_hours * 100, 1)
+                     ELSE 0.0 END AS progress_percentage
+         FROM skills k
+         JOIN skill_tree t ON t.ancestor_id = k.id
+         JOIN skills below ON below.id = t.skill_id
+         LEFT JOIN subskills p ON p.skill_id = k.id
+         GROUP BY k.id
+         ORDER BY k.name"
+    ))
+    .fetch_all(pool)
+    .await?)
+}
+
+/// [`rollups`] for a single skill.
+pub async fn rollup(pool: &SqlitePool, skill_id: &str) -> Result<SkillRollup> {
+    rollups(pool)
+        .await?
+        .into_iter()
+        .find(|rollup| rollup.skill_id == skill_id)
+        .ok_or_else(|| Error::Invalid(format!("skill {skill_id} does not exist")))
+}
@@ -0,0 +1,152 @@
+//! Parent/child links between tasks.
+//!
+//! A task has at most one parent, and the links never loop, so the tasks of
+//! a skill form a forest. Each task keeps its own `estimated_pomodoros`,
+//! `pomodoro_sessions` and `total_minutes`; [`rollups`] adds up the ones
+//! below it.
+
+use serde::Serialize;
+use sqlx::{SqliteExecutor, SqlitePool};
+
+use super::dependencies;
+use super::tasks::{self, TaskRecord};
+use crate::error::{Error, Result};
+
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct SubtaskRecord {
+    pub task_id: String,
+    pub parent_id: String,
+    pub created_at: String,
+}
+
+/// A task's totals including every task below it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, sqlx::FromRow)]
+pub struct Rollup {
+    pub task_id: String,
+    pub estimated_pomodoros: i64,
+    pub pomodoro_sessions: i64,
+    pub total_minutes: i64,
+    /// Tasks below this one, at any depth.
+    pub subtasks: i64,
+    pub done_subtasks: i64,
+}
+
+pub async fn list(pool: &SqlitePool) -> Result<Vec<SubtaskRecord>> {
+    Ok(sqlx::query_as("SELECT * FROM subtasks ORDER BY created_at")
+        .fetch_all(pool)
+        .await?)
+}
+
+/// The direct subtasks of `parent_id`.
+pub async fn children(pool: &SqlitePool, parent_id: &str) -> Result<Vec<TaskRecord>> {
+    Ok(sqlx::query_as(
+        "SELECT t.* FROM tasks t JOIN subtasks s ON s.task_id = t.id
+         WHERE s.parent_id = ?
+         ORDER BY t.order_index, t.created_at DESC",
+    )
+    .bind(parent_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Whether `ancestor` is `task_id` or above it.
+async fn is_at_or_above<'e>(
+    executor: impl SqliteExecutor<'e>,
+    ancestor: &str,
+    task_id: &str,
+) -> Result<bool> {
+    let (found,): (bool,) = sqlx::query_as(
+        "WITH RECURSIVE ancestors(id) AS (
+            SELECT ?1
+            UNION SELECT s.parent_id FROM subtasks s JOIN ancestors a ON s.task_id = a.id
+         )
+         SELECT EXISTS (SELECT 1 FROM ancestors WHERE id = ?2)",
+    )
+    .bind(task_id)
+    .bind(ancestor)
+    .fetch_one(executor)
+    .await?;
+    Ok(found)
+}
+
+/// Moves `task_id` under `parent_id`, or to the top level when it is `None`.
+/// Refuses to put a task under itself or one of its own subtasks, under a
+/// task whose blockers wait on it, or under a task of another skill, whose
+/// rollups would then count time from both.
+pub async fn set_parent(pool: &SqlitePool, task_id: &str, parent_id: Option<&str>) -> Result<()> {
+    let Some(parent_id) = parent_id else {
+        sqlx::query("DELETE FROM subtasks WHERE task_id = ?")
+            .bind(task_id)
+            .execute(pool)
+            .await?;
+        return Ok(());
+    };
+    let mut skills = Vec::new();
+    for id in [task_id, parent_id] {
+        match tasks::get(pool, id).await? {
+            Some(task) => skills.push(task.skill_id),
+            None => return Err(Error::Invalid(format!("task {id} does not exist"))),
+        }
+    }
+    if skills[0] != skills[1] {
+        return Err(Error::Invalid(format!(
+            "task {parent_id} belongs to another skill, so it cannot be the parent of {task_id}"
+        )));
+    }
+
+    let mut tx = pool.begin().await?;
+    if is_at_or_above(&mut *tx, task_id, parent_id).await? {
+        return Err(Error::Invalid(format!(
+            "task {parent_id} is a subtask of {task_id}, so it cannot be its parent"
+        )));
+    }
+    sqlx::query(
+        "INSERT INTO subtasks (task_id, parent_id) VALUES (?, ?)
+         ON CONFLICT (task_id) DO UPDATE SET parent_id = excluded.parent_id",
+    )
+    .bind(task_id)
+    .bind(parent_id)
+    .execute(&mut *tx)
+    .await?;
+    if dependencies::waits_on_itself(&mut tx, task_id).await? {
+        return Err(Error::Invalid(format!(
+            "task {task_id} would wait on itself under {parent_id}"
+        )));
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Rolled-up totals for every task, or those of one skill.
+pub async fn rollups(pool: &SqlitePool, skill_id: Option<&str>) -> Result<Vec<Rollup>> {
+    Ok(sqlx::query_as(
+        "WITH RECURSIVE tree(root, id) AS (
+            SELECT id, id FROM tasks WHERE ?1 IS NULL OR skill_id = ?1
+            UNION SELECT tree.root, s.task_id FROM subtasks s JOIN tree ON s.parent_id = tree.id
+         )
+         SELECT tree.root AS task_id,
+                SUM(t.estimated_pomodoros) AS estimated_pomodoros,
+                SUM(t.pomodoro_sessions) AS pomodoro_sessions,
+                SUM(t.total_minutes) AS total_minutes,
+                COUNT(*) - 1 AS subtasks,
+                COALESCE(SUM(t.id != tree.root AND t.status = 'done'), 0) AS done_subtasks
+         FROM tree JOIN tasks t ON t.id = tree.id
+         GROUP BY tree.root
+         ORDER BY tree.root",
+    )
+    .bind(skill_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// [`rollups`] for a single task.
+pub async fn rollup(pool: &SqlitePool, task_id: &str) -> Result<Rollup> {
+    let task = tasks::get(pool, task_id)
+        .await?
+        .ok_or_else(|| Error::Invalid(format!("task {task_id} does not exist")))?;
+    rollups(pool, Some(&task.skill_id))
+        .await?
+        .into_iter()
+        .find(|rollup| rollup.task_id == task_id)
+        .ok_or_else(|| Error::Invalid(format!("task {task_id} does not exist")))
+}
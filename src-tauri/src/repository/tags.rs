@@ -0,0 +1,172 @@
+//! Tags and their links to tasks, timer sessions and reflections.
+//!
+//! Tag names are unique regardless of case. A session counts for its own
+//! tags and for those of its task, so tagging a task also tags the time
+//! already spent on it; see [`TAGGED_SESSIONS`].
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::database;
+use crate::error::{Error, Result};
+
+/// `(session_id, tag_id)` for every tag a session counts for, to use in a
+/// `WITH` clause.
+pub const TAGGED_SESSIONS: &str = "tagged_sessions(session_id, tag_id) AS (
+    SELECT session_id, tag_id FROM session_tags
+    UNION SELECT s.id, t.tag_id FROM timer_sessions s JOIN task_tags t ON t.task_id = s.task_id
+)";
+
+#[derive(Clone, Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TagRecord {
+    pub id: String,
+    pub name: String,
+    pub color: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTagInput {
+    pub name: String,
+    pub color: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateTagInput {
+    pub id: String,
+    pub name: Option<String>,
+    pub color: Option<String>,
+}
+
+/// What a tag is attached to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tagged {
+    Task,
+    Session,
+    Reflection,
+}
+
+impl Tagged {
+    /// The junction table and its column for the tagged row.
+    fn junction(self) -> (&'static str, &'static str) {
+        match self {
+            Tagged::Task => ("task_tags", "task_id"),
+            Tagged::Session => ("session_tags", "session_id"),
+            Tagged::Reflection => ("reflection_tags", "reflection_id"),
+        }
+    }
+}
+
+/// A tag on a task, session or reflection.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, sqlx::FromRow)]
+pub struct TagLink {
+    pub item_id: String,
+    pub tag_id: String,
+}
+
+fn name(name: &str) -> Result<&str> {
+    match name.trim() {
+        "" => Err(Error::Invalid("a tag needs a name".into())),
+        name => Ok(name),
+    }
+}
+
+pub async fn list(pool: &SqlitePool) -> Result<Vec<TagRecord>> {
+    Ok(
+        sqlx::query_as("SELECT * FROM tags ORDER BY name COLLATE NOCASE")
+            .fetch_all(pool)
+            .await?,
+    )
+}
+
+async fn fetch(pool: &SqlitePool, id: &str) -> Result<TagRecord> {
+    sqlx::query_as("SELECT * FROM tags WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| Error::Invalid(format!("tag {id} does not exist")))
+}
+
+/// Creates a tag, or returns the one that already has this name.
+pub async fn create(pool: &SqlitePool, input: CreateTagInput) -> Result<TagRecord> {
+    let name = name(&input.name)?;
+    sqlx::query(
+        "INSERT INTO tags (id, name, color) VALUES (?, ?, ?) ON CONFLICT (name) DO NOTHING",
+    )
+    .bind(database::generate_id("tag"))
+    .bind(name)
+    .bind(&input.color)
+    .execute(pool)
+    .await?;
+    Ok(sqlx::query_as("SELECT * FROM tags WHERE name = ?")
+        .bind(name)
+        .fetch_one(pool)
+        .await?)
+}
+
+pub async fn update(pool: &SqlitePool, input: UpdateTagInput) -> Result<TagRecord> {
+    let name = input.name.as_deref().map(name).transpose()?;
+    let taken: Option<(String,)> = sqlx::query_as("SELECT id FROM tags WHERE name = ? AND id != ?")
+        .bind(name)
+        .bind(&input.id)
+        .fetch_optional(pool)
+        .await?;
+    if taken.is_some() {
+        return Err(Error::Invalid(format!(
+            "a tag named {} already exists",
+            name.unwrap_or_default()
+        )));
+    }
+    sqlx::query(
+        "UPDATE tags SET name = COALESCE(?, name), color = COALESCE(?, color) WHERE id = ?",
+    )
+    .bind(name)
+    .bind(&input.color)
+    .bind(&input.id)
+    .execute(pool)
+    .await?;
+    fetch(pool, &input.id).await
+}
+
+/// Deletes a tag and removes it from everything it was on.
+pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
+    sqlx::query("DELETE FROM tags WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Every tag on a kind of row.
+pub async fn links(pool: &SqlitePool, kind: Tagged) -> Result<Vec<TagLink>> {
+    let (table, column) = kind.junction();
+    Ok(sqlx::query_as(&format!(
+        "SELECT {column} AS item_id, tag_id FROM {table} ORDER BY {column}, tag_id"
+    ))
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Replaces the tags on one task, session or reflection.
+pub async fn set(pool: &SqlitePool, kind: Tagged, item_id: &str, tag_ids: &[String]) -> Result<()> {
+    let (table, column) = kind.junction();
+    let mut tx = pool.begin().await?;
+    sqlx::query(&format!("DELETE FROM {table} WHERE {column} = ?"))
+        .bind(item_id)
+        .execute(&mut *tx)
+        .await?;
+    for tag_id in tag_ids {
+        sqlx::query(&format!(
+            "INSERT INTO {table} ({column}, tag_id) VALUES (?, ?) ON CONFLICT DO NOTHING"
+        ))
+        .bind(item_id)
+        .bind(tag_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::database;
+use crate::error::{Error, Result};
+use crate::recurrence;
+
+#[derive(Clone, Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TaskRecord {
+    pub id: String,
+    pub skill_id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub status: String,
+    pub priority: String,
+    pub due_date: Option<String>,
+    pub estimated_pomodoros: i64,
+    pub pomodoro_sessions: i64,
+    pub total_minutes: i64,
+    pub order_index: i64,
+    pub created_at: String,
+    pub completed_at: Option<String>,
+    /// The `RRULE` of a recurring task; see [`crate::recurrence`].
+    #[serde(default)]
+    pub recurrence: Option<String>,
+    /// The first task of the series this one belongs to.
+    #[serde(default)]
+    pub series_id: Option<String>,
+    /// The instance this one was created after.
+    #[serde(default)]
+    pub previous_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTaskInput {
+    pub skill_id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub status: Option<String>,
+    pub priority: Option<String>,
+    pub due_date: Option<String>,
+    pub estimated_pomodoros: Option<i64>,
+    pub recurrence: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateTaskInput {
+    pub id: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub status: Option<String>,
+    pub priority: Option<String>,
+    pub due_date: Option<String>,
+    pub estimated_pomodoros: Option<i64>,
+    pub order: Option<i64>,
+    pub pomodoro_sessions: Option<i64>,
+    pub total_minutes: Option<i64>,
+    /// An empty rule stops the task recurring.
+    pub recurrence: Option<String>,
+}
+
+pub async fn list(pool: &SqlitePool, skill_id: Option<&str>) -> Result<Vec<TaskRecord>> {
+    Ok(sqlx::query_as(
+        "SELECT * FROM tasks WHERE ?1 IS NULL OR skill_id = ?1
+         ORDER BY order_index, created_at DESC",
+    )
+    .bind(skill_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+pub async fn get(pool: &SqlitePool, id: &str) -> Result<Option<TaskRecord>> {
+    Ok(sqlx::query_as("SELECT * FROM tasks WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?)
+}
+
+/// Every instance of a recurring series, oldest first.
+pub async fn series(pool: &SqlitePool, series_id: &str) -> Result<Vec<TaskRecord>> {
+    Ok(sqlx::query_as(
+        "SELECT * FROM tasks WHERE series_id = ?
+         ORDER BY COALESCE(due_date, created_at), created_at",
+    )
+    .bind(series_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+async fn fetch(pool: &SqlitePool, id: &str) -> Result<TaskRecord> {
+    get(pool, id)
+        .await?
+        .ok_or_else(|| Error::Invalid(format!("task {id} does not exist")))
+}
+
+/// The stored form of a rule from the webview, or `None` to clear it.
+fn parse_recurrence(rule: Option<&str>) -> Result<Option<String>> {
+    match rule.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(rule) => recurrence::normalize(rule).map(Some),
+    }
+}
+
+/// Creates a task. One with a `recurrence` starts a series of its own.
+pub async fn create(pool: &SqlitePool, input: CreateTaskInput) -> Result<TaskRecord> {
+    let id = database::generate_id("task");
+    let recurrence = parse_recurrence(input.recurrence.as_deref())?;
+    sqlx::query(
+        "INSERT INTO tasks
+            (id, skill_id, title, description, status, priority, due_date, estimated_pomodoros,
+             recurrence, series_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, CASE WHEN ?9 IS NOT NULL THEN ?1 END)",
+    )
+    .bind(&id)
+    .bind(&input.skill_id)
+    .bind(&input.title)
+    .bind(&input.description)
+    .bind(input.status.as_deref().unwrap_or("todo"))
+    .bind(input.priority.as_deref().unwrap_or("medium"))
+    .bind(&input.due_date)
+    .bind(input.estimated_pomodoros.unwrap_or(1))
+    .bind(&recurrence)
+    .execute(pool)
+    .await?;
+    let task = fetch(pool, &id).await?;
+    if task.status == "done" {
+        recurrence::advance(pool, &task).await?;
+    }
+    Ok(task)
+}
+
+/// Applies the fields set on `input`. Moving a task to `done` stamps
+/// `completed_at` and, for a recurring task, creates the next instance.
+/// Changing the rule changes it for this instance and the ones after it.
+pub async fn update(pool: &SqlitePool, input: UpdateTaskInput) -> Result<TaskRecord> {
+    let recurrence = match &input.recurrence {
+        // Bound as '' to clear, NULL to leave alone.
+        Some(rule) => Some(parse_recurrence(Some(rule))?.unwrap_or_default()),
+        None => None,
+    };
+    sqlx::query(
+        "UPDATE tasks SET
+            title = COALESCE(?1, title),
+            description = COALESCE(?2, description),
+            status = COALESCE(?3, status),
+            completed_at = CASE WHEN ?3 = 'done' THEN datetime('now') ELSE completed_at END,
+            priority = COALESCE(?4, priority),
+            due_date = COALESCE(?5, due_date),
+            estimated_pomodoros = COALESCE(?6, estimated_pomodoros),
+            order_index = COALESCE(?7, order_index),
+            pomodoro_sessions = COALESCE(?8, pomodoro_sessions),
+            total_minutes = COALESCE(?9, total_minutes),
+            recurrence = CASE WHEN ?11 IS NULL THEN recurrence ELSE NULLIF(?11, '') END,
+            series_id = CASE WHEN ?11 != '' THEN COALESCE(series_id, id) ELSE series_id END
+         WHERE id = ?10",
+    )
+    .bind(&input.title)
+    .bind(&input.description)
+    .bind(&input.status)
+    .bind(&input.priority)
+    .bind(&input.due_date)
+    .bind(input.estimated_pomodoros)
+    .bind(input.order)
+    .bind(input.pomodoro_sessions)
+    .bind(input.total_minutes)
+    .bind(&input.id)
+    .bind(&recurrence)
+    .execute(pool)
+    .await?;
+    let task = fetch(pool, &input.id).await?;
+    if input.status.as_deref() == Some("done") {
+        recurrence::advance(pool, &task).await?;
+    }
+    Ok(task)
+}
+
+pub async fn set_status(pool: &SqlitePool, id: &str, status: &str) -> Result<TaskRecord> {
+    update(
+        pool,
+        UpdateTaskInput {
+            id: id.to_owned(),
+            status: Some(status.to_owned()),
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+/// Deletes a task. The instance after it in a series is linked to the one
+/// before it instead, so the history stays a chain.
+pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    let previous: Option<(Option<String>,)> =
+        sqlx::query_as("SELECT previous_id FROM tasks WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?;
+    // Deleted first, as previous_id is unique.
+    sqlx::query("DELETE FROM tasks WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE tasks SET previous_id = ? WHERE previous_id = ?")
+        .bind(previous.and_then(|(previous,)| previous))
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Rewrites `order_index` so tasks sort in the order of `ids`.
+pub async fn reorder(pool: &SqlitePool, ids: &[String]) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    for (index, id) in ids.iter().enumerate() {
+        sqlx::query("UPDATE tasks SET order_index = ? WHERE id = ?")
+            .bind(index as i64)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,209 @@
+//! Startup check that the live SQLite schema has every table and column the
+//! repository queries rely on.
+//!
+//! A column missing from the database otherwise only shows up as a decode
+//! error the first time a query touches it.
+
+use std::fmt;
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::State;
+
+use crate::error::Result;
+
+/// Tables and columns read by [`crate::repository`], as of the latest
+/// migration in [`crate::database::get_migrations`].
+pub const EXPECTED: &[(&str, &[&str])] = &[
+    (
+        "user_settings",
+        &[
+            "id",
+            "name",
+            "email",
+            "avatar",
+            "theme",
+            "sound_enabled",
+            "notifications_enabled",
+            "pomodoro_duration",
+            "short_break_duration",
+            "long_break_duration",
+            "auto_start_breaks",
+            "auto_start_pomodoros",
+            "long_break_interval",
+            "daily_goal_minutes",
+            "weekly_goal_minutes",
+            "spotify_enabled",
+            "spotify_access_token",
+            "spotify_refresh_token",
+            "spotify_token_expiry",
+            "created_at",
+            "updated_at",
+            "api_enabled",
+            "api_port",
+            "api_token",
+            "timezone",
+            "day_start_hour",
+            "sleep_policy",
+            "recovery_policy",
+        ],
+    ),
+    (
+        "skills",
+        &[
+            "id",
+            "name",
+            "description",
+            "goal_hours",
+            "daily_goal_minutes",
+            "current_minutes",
+            "color",
+            "is_active",
+            "created_at",
+            "updated_at",
+        ],
+    ),
+    (
+        "tasks",
+        &[
+            "id",
+            "skill_id",
+            "title",
+            "description",
+            "status",
+            "priority",
+            "due_date",
+            "estimated_pomodoros",
+            "pomodoro_sessions",
+            "total_minutes",
+            "order_index",
+            "created_at",
+            "completed_at",
+            "recurrence",
+            "series_id",
+            "previous_id",
+        ],
+    ),
+    (
+        "timer_sessions",
+        &[
+            "id",
+            "task_id",
+            "skill_id",
+            "start_time",
+            "end_time",
+            "duration",
+            "type",
+            "completed",
+            "planned_duration",
+            "session_type",
+            "created_at",
+            "local_date",
+            "local_hour",
+            "heartbeat_at",
+            "elapsed_seconds",
+        ],
+    ),
+    (
+        "achievements",
+        &[
+            "id",
+            "type",
+            "name",
+            "description",
+            "icon",
+            "unlocked_at",
+            "progress",
+            "target",
+            "created_at",
+            "rule",
+        ],
+    ),
+    (
+        "reflections",
+        &[
+            "id",
+            "date",
+            "content",
+            "mood",
+            "total_minutes",
+            "created_at",
+            "updated_at",
+        ],
+    ),
+    ("reflection_skills", &["reflection_id", "skill_id"]),
+    ("tags", &["id", "name", "color", "created_at"]),
+    ("task_tags", &["task_id", "tag_id"]),
+    ("session_tags", &["session_id", "tag_id"]),
+    ("reflection_tags", &["reflection_id", "tag_id"]),
+    ("subskills", &["skill_id", "parent_id", "created_at"]),
+    ("subtasks", &["task_id", "parent_id", "created_at"]),
+    (
+        "task_dependencies",
+        &["task_id", "blocked_by_id", "created_at"],
+    ),
+    (
+        "daily_activities",
+        &["date", "total_minutes", "total_sessions"],
+    ),
+    ("active_timer", &["id", "session_id", "owner"]),
+];
+
+/// One difference between [`EXPECTED`] and the database.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Drift {
+    MissingTable { table: String },
+    MissingColumn { table: String, column: String },
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Drift::MissingTable { table } => write!(f, "table `{table}` is missing"),
+            Drift::MissingColumn { table, column } => {
+                write!(f, "column `{table}.{column}` is missing")
+            }
+        }
+    }
+}
+
+/// Drift found when the app started, kept for the webview to ask about.
+#[derive(Debug, Default)]
+pub struct Report(pub Vec<Drift>);
+
+/// Compares the tables in `pool` against [`EXPECTED`]. Extra tables and
+/// columns are fine; only missing ones are reported.
+pub async fn check(pool: &SqlitePool) -> Result<Vec<Drift>> {
+    let mut drift = Vec::new();
+
+    for (table, columns) in EXPECTED {
+        let live: Vec<(String,)> = sqlx::query_as("SELECT name FROM pragma_table_info(?)")
+            .bind(table)
+            .fetch_all(pool)
+            .await?;
+
+        if live.is_empty() {
+            drift.push(Drift::MissingTable {
+                table: table.to_string(),
+            });
+            continue;
+        }
+
+        for column in *columns {
+            if !live.iter().any(|(name,)| name == column) {
+                drift.push(Drift::MissingColumn {
+                    table: table.to_string(),
+                    column: column.to_string(),
+                });
+            }
+        }
+    }
+
+    Ok(drift)
+}
+
+#[tauri::command]
+pub fn schema_drift(report: State<'_, Report>) -> Vec<Drift> {
+    report.0.clone()
+}
@@ -0,0 +1,386 @@
+//! Whole-file snapshots of `app.db`.
+//!
+//! Snapshots are copied page by page with SQLite's online backup API, so they
+//! are consistent even while the app and `tenk` are writing. They live in a
+//! `snapshots` directory next to the database and are taken once a day, before
+//! pending migrations are applied, and before a snapshot is restored. Old
+//! ones are pruned by [`Retention`]: the newest snapshot of each recent day,
+//! week and month is kept.
+
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_int;
+use std::path::{Path, PathBuf};
+use std::ptr::{self, NonNull};
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, Timelike, Utc};
+use libsqlite3_sys as ffi;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::{AppHandle, Manager, State};
+
+use crate::analytics;
+use crate::database::{self, Db};
+use crate::error::{Error, Result};
+
+/// Directory next to `app.db` that holds the snapshots.
+pub const SNAPSHOT_DIR: &str = "snapshots";
+
+/// How long a scheduled snapshot stays fresh.
+pub const INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How often the scheduler checks whether a snapshot is due.
+const CHECK_EVERY: Duration = Duration::from_secs(60 * 60);
+
+const STAMP: &str = "%Y%m%dT%H%M%SZ";
+
+/// How many days, ISO weeks and months keep their newest snapshot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Retention {
+    pub daily: i64,
+    pub weekly: i64,
+    pub monthly: i64,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Retention {
+            daily: 7,
+            weekly: 4,
+            monthly: 12,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    pub name: String,
+    pub taken_at: DateTime<Utc>,
+    /// `scheduled`, `manual`, `before-restore` or `before-v<N>`.
+    pub reason: String,
+    pub size: u64,
+}
+
+/// Where snapshots of the database at `db_path` are kept.
+pub fn dir(db_path: &Path) -> PathBuf {
+    db_path.with_file_name(SNAPSHOT_DIR)
+}
+
+fn parse_name(name: &str) -> Option<(DateTime<Utc>, String)> {
+    let stem = name.strip_prefix("app-")?.strip_suffix(".db")?;
+    let (stamp, reason) = stem.split_once('-')?;
+    let taken_at = NaiveDateTime::parse_from_str(stamp, STAMP).ok()?.and_utc();
+    Some((taken_at, reason.to_owned()))
+}
+
+/// Snapshots in `dir`, newest first. Files that do not look like
+/// snapshots are ignored.
+pub fn list(dir: &Path) -> Result<Vec<Snapshot>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let mut snapshots = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if let Some((taken_at, reason)) = parse_name(&name) {
+            snapshots.push(Snapshot {
+                name,
+                taken_at,
+                reason,
+                size: entry.metadata()?.len(),
+            });
+        }
+    }
+    snapshots.sort_by_key(|snapshot| Reverse(snapshot.taken_at));
+    Ok(snapshots)
+}
+
+fn sqlite_error(db: *mut ffi::sqlite3, action: &str) -> Error {
+    // SAFETY: `db` is an open handle; sqlite3_errmsg returns a string owned
+    // by it that stays valid until the next call on the handle.
+    let message = unsafe { CStr::from_ptr(ffi::sqlite3_errmsg(db)) };
+    Error::Io(std::io::Error::other(format!(
+        "{action}: {}",
+        message.to_string_lossy()
+    )))
+}
+
+/// Copies the `main` database of `source` into `dest` with the backup API,
+/// waiting out writers that hold a lock. Fails if the copy is still not
+/// complete once the wait is over.
+///
+/// # Safety
+///
+/// Both handles must be open and not used elsewhere for the duration of the
+/// call.
+unsafe fn copy(source: *mut ffi::sqlite3, dest: *mut ffi::sqlite3) -> Result<()> {
+    let main = c"main".as_ptr();
+    let backup = ffi::sqlite3_backup_init(dest, main, source, main);
+    if backup.is_null() {
+        return Err(sqlite_error(dest, "could not start backup"));
+    }
+    // About ten seconds, twice the pool's busy timeout.
+    let mut step = ffi::SQLITE_BUSY;
+    for _ in 0..200 {
+        step = ffi::sqlite3_backup_step(backup, -1);
+        match step {
+            ffi::SQLITE_OK | ffi::SQLITE_BUSY | ffi::SQLITE_LOCKED => {
+                std::thread::sleep(Duration::from_millis(50))
+            }
+            _ => break,
+        }
+    }
+    // Finishing a copy cut short by a lock still reports success, so only
+    // the last step tells whether every page made it.
+    if ffi::sqlite3_backup_finish(backup) != ffi::SQLITE_OK {
+        return Err(sqlite_error(dest, "backup failed"));
+    }
+    if step != ffi::SQLITE_DONE {
+        return Err(Error::Io(std::io::Error::other(
+            "backup failed: the database stayed locked",
+        )));
+    }
+    Ok(())
+}
+
+/// Opens a database file outside the pool for the backup API.
+fn open_file(path: &Path, flags: c_int) -> Result<*mut ffi::sqlite3> {
+    let path = CString::new(path.to_string_lossy().as_bytes())
+        .map_err(|_| Error::Invalid("snapshot path contains a NUL byte".into()))?;
+    let mut db = ptr::null_mut();
+    // SAFETY: `path` is NUL-terminated and `db` receives the new handle,
+    // which is closed by the caller even when opening fails.
+    let rc = unsafe { ffi::sqlite3_open_v2(path.as_ptr(), &mut db, flags, ptr::null()) };
+    if rc != ffi::SQLITE_OK {
+        let err = sqlite_error(db, "could not open snapshot");
+        // SAFETY: closing a handle from a failed open is allowed.
+        unsafe { ffi::sqlite3_close(db) };
+        return Err(err);
+    }
+    Ok(db)
+}
+
+/// Copies between the pool's database and the file at `path`, in the
+/// direction given by `restore`.
+async fn transfer(pool: &SqlitePool, path: &Path, restore: bool) -> Result<()> {
+    let flags = if restore {
+        ffi::SQLITE_OPEN_READONLY
+    } else {
+        ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE
+    };
+    let mut conn = pool.acquire().await?;
+    let mut handle = conn.lock_handle().await?;
+    // Raw handles are not `Send`, so nothing below may await.
+    let file = open_file(path, flags)?;
+    let live: NonNull<ffi::sqlite3> = handle.as_raw_handle();
+    // SAFETY: the pool connection is locked for the duration of the copy and
+    // `file` is only used here.
+    let copied = unsafe {
+        if restore {
+            copy(file, live.as_ptr())
+        } else {
+            copy(live.as_ptr(), file)
+        }
+    };
+    // SAFETY: `file` was opened above and the backup has finished.
+    unsafe { ffi::sqlite3_close(file) };
+    copied
+}
+
+/// Takes a snapshot of `pool` into `dir`.
+pub async fn take(pool: &SqlitePool, dir: &Path, reason: &str) -> Result<Snapshot> {
+    std::fs::create_dir_all(dir)?;
+    let taken_at = Utc::now();
+    let name = format!("app-{}-{reason}.db", taken_at.format(STAMP));
+    let partial = dir.join(format!("{name}.partial"));
+    if let Err(err) = transfer(pool, &partial, false).await {
+        let _ = std::fs::remove_file(&partial);
+        return Err(err);
+    }
+    let path = dir.join(&name);
+    std::fs::rename(&partial, &path)?;
+    log::info!("took {reason} snapshot {name}");
+
+    Ok(Snapshot {
+        size: std::fs::metadata(&path)?.len(),
+        name,
+        taken_at: taken_at.with_nanosecond(0).unwrap_or(taken_at),
+        reason: reason.to_owned(),
+    })
+}
+
+/// Numbers a day, week or month so that consecutive periods differ by one.
+type Bucket = fn(NaiveDate) -> i64;
+
+/// Names of the snapshots [`Retention`] keeps. `snapshots` is newest first.
+pub fn keep(snapshots: &[Snapshot], now: DateTime<Utc>, retention: Retention) -> HashSet<String> {
+    let mut kept = HashSet::new();
+    let today = now.date_naive();
+    let buckets: [(i64, Bucket); 3] = [
+        (retention.daily, |date| date.num_days_from_ce().into()),
+        (retention.weekly, |date| {
+            i64::from(analytics::week_start(date).num_days_from_ce()) / 7
+        }),
+        (retention.monthly, |date| {
+            i64::from(date.year()) * 12 + i64::from(date.month0())
+        }),
+    ];
+    for (count, bucket) in buckets {
+        let current = bucket(today);
+        let mut seen = HashSet::new();
+        for snapshot in snapshots {
+            let slot = bucket(snapshot.taken_at.date_naive());
+            if current - slot < count && seen.insert(slot) {
+                kept.insert(snapshot.name.clone());
+            }
+        }
+    }
+    kept
+}
+
+/// Deletes the snapshots in `dir` that [`Retention`] does not keep.
+pub fn prune(dir: &Path, retention: Retention) -> Result<Vec<String>> {
+    let snapshots = list(dir)?;
+    let kept = keep(&snapshots, Utc::now(), retention);
+    let mut removed = Vec::new();
+    for snapshot in snapshots {
+        if !kept.contains(&snapshot.name) {
+            std::fs::remove_file(dir.join(&snapshot.name))?;
+            removed.push(snapshot.name);
+        }
+    }
+    Ok(removed)
+}
+
+/// Takes a scheduled snapshot if the newest one is older than [`INTERVAL`],
+/// then prunes.
+pub async fn run_schedule(pool: &SqlitePool, dir: &Path) -> Result<()> {
+    let due = match list(dir)?.first() {
+        Some(latest) => {
+            Utc::now() - latest.taken_at
+                >= chrono::Duration::from_std(INTERVAL).unwrap_or(chrono::Duration::MAX)
+        }
+        None => true,
+    };
+    if due {
+        take(pool, dir, "scheduled").await?;
+    }
+    prune(dir, Retention::default())?;
+    Ok(())
+}
+
+/// Copies the snapshot file at `path` over the database behind `pool`.
+pub async fn roll_back(pool: &SqlitePool, path: &Path) -> Result<()> {
+    transfer(pool, path, true).await
+}
+
+/// Replaces the contents of `pool` with the snapshot `name` from `dir`. The
+/// current contents are snapshotted first, and migrations are re-applied in
+/// case the snapshot predates some of them.
+pub async fn restore(pool: &SqlitePool, dir: &Path, name: &str) -> Result<Snapshot> {
+    let Some(snapshot) = list(dir)?.into_iter().find(|s| s.name == name) else {
+        return Err(Error::Invalid(format!("snapshot {name} does not exist")));
+    };
+    take(pool, dir, "before-restore").await?;
+    roll_back(pool, &dir.join(&snapshot.name)).await?;
+    database::migrate(pool).await?;
+    Ok(snapshot)
+}
+
+fn app_dir(app: &AppHandle) -> Result<PathBuf> {
+    Ok(dir(&app.path().app_config_dir()?.join(database::DB_FILE)))
+}
+
+/// Checks every [`CHECK_EVERY`] whether a scheduled snapshot is due.
+pub fn spawn_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let result = match app_dir(&app) {
+                Ok(dir) => run_schedule(&app.state::<Db>().0, &dir).await,
+                Err(err) => Err(err),
+            };
+            if let Err(err) = result {
+                log::error!("scheduled snapshot failed: {err}");
+            }
+            tokio::time::sleep(CHECK_EVERY).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn list_snapshots(app: AppHandle) -> Result<Vec<Snapshot>> {
+    list(&app_dir(&app)?)
+}
+
+#[tauri::command]
+pub async fn create_snapshot(app: AppHandle, db: State<'_, Db>) -> Result<Snapshot> {
+    let dir = app_dir(&app)?;
+    let snapshot = take(&db.0, &dir, "manual").await?;
+    prune(&dir, Retention::default())?;
+    Ok(snapshot)
+}
+
+#[tauri::command]
+pub async fn restore_snapshot(app: AppHandle, db: State<'_, Db>, name: String) -> Result<Snapshot> {
+    restore(&db.0, &app_dir(&app)?, &name).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(taken_at: &str) -> Snapshot {
+        let taken_at: DateTime<Utc> = taken_at.parse().unwrap();
+        Snapshot {
+            name: format!("app-{}-scheduled.db", taken_at.format(STAMP)),
+            taken_at,
+            reason: "scheduled".into(),
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn names_round_trip() {
+        let (taken_at, reason) = parse_name("app-20240301T081500Z-before-v13.db").unwrap();
+        assert_eq!(
+            taken_at,
+            "2024-03-01T08:15:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(reason, "before-v13");
+        assert!(parse_name("app-20240301T081500Z-scheduled.db.partial").is_none());
+        assert!(parse_name("notes.txt").is_none());
+    }
+
+    #[test]
+    fn retention_keeps_newest_per_day_week_and_month() {
+        // Newest first, as `list` returns them.
+        let snapshots = [
+            snapshot("2024-03-20T18:00:00Z"),
+            snapshot("2024-03-20T08:00:00Z"),
+            snapshot("2024-03-19T08:00:00Z"),
+            snapshot("2024-03-11T08:00:00Z"),
+            snapshot("2024-03-01T08:00:00Z"),
+            snapshot("2024-02-10T08:00:00Z"),
+            snapshot("2023-01-10T08:00:00Z"),
+        ];
+        let now = "2024-03-20T20:00:00Z".parse().unwrap();
+        let retention = Retention {
+            daily: 2,
+            weekly: 2,
+            monthly: 2,
+        };
+        let kept = keep(&snapshots, now, retention);
+        let names = |dates: &[usize]| -> HashSet<String> {
+            dates.iter().map(|&i| snapshots[i].name.clone()).collect()
+        };
+        // Today and yesterday; this week is already kept, last week's
+        // newest is the 11th; this month is kept, February's newest.
+        assert_eq!(kept, names(&[0, 2, 3, 5]));
+    }
+}
@@ -0,0 +1,759 @@
+//! Pomodoro timer owned by the backend.
+//!
+//! The countdown runs against a monotonic clock so webview reloads or a
+//! throttled tab cannot skew it. The active session row in `timer_sessions` is
+//! written when a session starts and committed when it completes or is
+//! stopped; progress is pushed to the frontend through [`TICK_EVENT`] and
+//! [`COMPLETE_EVENT`].
+//!
+//! Whether the monotonic clock keeps running while the computer sleeps
+//! depends on the platform, so each tick also reads the wall clock. When
+//! either one jumps by more than [`GAP_THRESHOLD`], the session is paused or
+//! trimmed at the last tick before the jump according to
+//! `user_settings.sleep_policy`, and [`INTERRUPTED_EVENT`] says so. A wall
+//! clock set forward looks the same as a sleep and is handled the same way.
+//!
+//! A running session also writes a heartbeat to its row every
+//! [`HEARTBEAT_INTERVAL`] and when it pauses, which
+//! [`unfinished`](crate::unfinished) uses after a crash.
+
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::async_runtime::{self, Mutex};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::achievements;
+use crate::database::{self, Db};
+use crate::error::{Error, Result};
+use crate::repository::sessions::{self, NewSession, Owner};
+use crate::unfinished::Unfinished;
+
+/// Emitted about once a second while a session is running.
+pub const TICK_EVENT: &str = "timer-tick";
+/// Emitted when a session runs to zero.
+pub const COMPLETE_EVENT: &str = "timer-complete";
+/// Emitted when the computer slept, or its clock jumped, during a session.
+pub const INTERRUPTED_EVENT: &str = "timer-interrupted";
+
+/// Time between two ticks beyond which the computer is taken to have slept.
+pub const GAP_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// How often a running session records how far it got.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimerType {
+    Pomodoro,
+    ShortBreak,
+    LongBreak,
+}
+
+impl TimerType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TimerType::Pomodoro => "pomodoro",
+            TimerType::ShortBreak => "short-break",
+            TimerType::LongBreak => "long-break",
+        }
+    }
+
+    pub fn is_break(self) -> bool {
+        self != TimerType::Pomodoro
+    }
+
+    /// The inverse of [`TimerType::as_str`].
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            TimerType::Pomodoro,
+            TimerType::ShortBreak,
+            TimerType::LongBreak,
+        ]
+        .into_iter()
+        .find(|kind| kind.as_str() == name)
+    }
+}
+
+/// What happens to a running session the computer slept through. The time
+/// asleep is never credited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum SleepPolicy {
+    /// Pause where the computer went to sleep.
+    #[default]
+    Pause,
+    /// End the session there, keeping the minutes worked before it.
+    Trim,
+    /// Pause, and have the window ask whether to resume or end it.
+    Ask,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimerStatus {
+    Idle,
+    Running,
+    Paused,
+}
+
+/// Durations and auto-start flags from `user_settings`.
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct TimerSettings {
+    pub pomodoro_duration: i64,
+    pub short_break_duration: i64,
+    pub long_break_duration: i64,
+    pub auto_start_breaks: bool,
+    pub auto_start_pomodoros: bool,
+    pub long_break_interval: i64,
+    pub sleep_policy: SleepPolicy,
+}
+
+impl Default for TimerSettings {
+    fn default() -> Self {
+        Self {
+            pomodoro_duration: 25,
+            short_break_duration: 5,
+            long_break_duration: 15,
+            auto_start_breaks: false,
+            auto_start_pomodoros: false,
+            long_break_interval: 4,
+            sleep_policy: SleepPolicy::Pause,
+        }
+    }
+}
+
+impl TimerSettings {
+    pub async fn load(pool: &SqlitePool) -> Result<Self> {
+        let settings = sqlx::query_as::<_, TimerSettings>(
+            "SELECT pomodoro_duration, short_break_duration, long_break_duration,
+                    auto_start_breaks, auto_start_pomodoros, long_break_interval,
+                    sleep_policy
+             FROM user_settings WHERE id = 1",
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(settings.unwrap_or_default())
+    }
+
+    pub fn minutes(&self, kind: TimerType) -> i64 {
+        match kind {
+            TimerType::Pomodoro => self.pomodoro_duration,
+            TimerType::ShortBreak => self.short_break_duration,
+            TimerType::LongBreak => self.long_break_duration,
+        }
+    }
+
+    fn auto_starts(&self, kind: TimerType) -> bool {
+        if kind.is_break() {
+            self.auto_start_breaks
+        } else {
+            self.auto_start_pomodoros
+        }
+    }
+}
+
+/// The session row the timer is currently counting down.
+#[derive(Clone, Debug)]
+struct ActiveSession {
+    id: String,
+    kind: TimerType,
+    task_id: Option<String>,
+    skill_id: String,
+    start_time: String,
+    total: Duration,
+}
+
+#[derive(Debug)]
+enum Phase {
+    Idle {
+        next: TimerType,
+        total: Duration,
+    },
+    Running {
+        session: ActiveSession,
+        resumed_at: Instant,
+        remaining_at_resume: Duration,
+    },
+    Paused {
+        session: ActiveSession,
+        remaining: Duration,
+        /// When the countdown stopped, and when the session ends if it is
+        /// stopped from here.
+        since: String,
+    },
+}
+
+/// Both clocks read at the same moment.
+#[derive(Clone, Copy, Debug)]
+struct Reading {
+    instant: Instant,
+    wall: SystemTime,
+}
+
+impl Reading {
+    fn now() -> Self {
+        Reading {
+            instant: Instant::now(),
+            wall: SystemTime::now(),
+        }
+    }
+}
+
+/// How much longer than the `expected` wait passed between two readings on
+/// whichever clock ran further, when that is at least [`GAP_THRESHOLD`]. A
+/// wall clock set back counts as no time at all.
+fn gap(before: Reading, after: Reading, expected: Duration) -> Option<Duration> {
+    let monotonic = after.instant.saturating_duration_since(before.instant);
+    let wall = after.wall.duration_since(before.wall).unwrap_or_default();
+    let gap = monotonic.max(wall).saturating_sub(expected);
+    (gap >= GAP_THRESHOLD).then_some(gap)
+}
+
+/// What the frontend renders; mirrors `TimerState` in `types/timer.ts`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimerSnapshot {
+    pub status: TimerStatus,
+    #[serde(rename = "type")]
+    pub kind: TimerType,
+    pub remaining_seconds: u64,
+    pub total_seconds: u64,
+    pub current_task_id: Option<String>,
+    pub current_skill_id: Option<String>,
+    pub session_id: Option<String>,
+    pub completed_pomodoros: u32,
+}
+
+/// Payload of [`INTERRUPTED_EVENT`].
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Interruption {
+    pub session_id: String,
+    /// The last tick before the gap, where the session was paused or ended.
+    pub since: String,
+    pub gap_seconds: u64,
+    pub policy: SleepPolicy,
+    pub next: TimerSnapshot,
+}
+
+/// Payload of [`COMPLETE_EVENT`].
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletedSession {
+    pub session_id: String,
+    #[serde(rename = "type")]
+    pub kind: TimerType,
+    pub task_id: Option<String>,
+    pub skill_id: String,
+    pub minutes: i64,
+    pub next: TimerSnapshot,
+}
+
+/// Timer state machine. Transitions are only made while holding the lock in
+/// [`Timer`], so the database and the in-memory phase never disagree.
+#[derive(Debug)]
+pub struct TimerMachine {
+    phase: Phase,
+    completed_pomodoros: u32,
+    /// Bumped on every transition so a stale ticker task knows to exit.
+    generation: u64,
+}
+
+impl Default for TimerMachine {
+    fn default() -> Self {
+        let settings = TimerSettings::default();
+        Self {
+            phase: Phase::Idle {
+                next: TimerType::Pomodoro,
+                total: minutes(settings.pomodoro_duration),
+            },
+            completed_pomodoros: 0,
+            generation: 0,
+        }
+    }
+}
+
+impl TimerMachine {
+    pub fn status(&self) -> TimerStatus {
+        match self.phase {
+            Phase::Idle { .. } => TimerStatus::Idle,
+            Phase::Running { .. } => TimerStatus::Running,
+            Phase::Paused { .. } => TimerStatus::Paused,
+        }
+    }
+
+    fn remaining(&self) -> Duration {
+        self.remaining_at(Instant::now())
+    }
+
+    fn remaining_at(&self, at: Instant) -> Duration {
+        match &self.phase {
+            Phase::Idle { total, .. } => *total,
+            Phase::Running {
+                resumed_at,
+                remaining_at_resume,
+                ..
+            } => remaining_at_resume.saturating_sub(at.saturating_duration_since(*resumed_at)),
+            Phase::Paused { remaining, .. } => *remaining,
+        }
+    }
+
+    fn session(&self) -> Option<&ActiveSession> {
+        match &self.phase {
+            Phase::Idle { .. } => None,
+            Phase::Running { session, .. } | Phase::Paused { session, .. } => Some(session),
+        }
+    }
+
+    pub fn snapshot(&self) -> TimerSnapshot {
+        let (kind, total) = match &self.phase {
+            Phase::Idle { next, total } => (*next, *total),
+            Phase::Running { session, .. } | Phase::Paused { session, .. } => {
+                (session.kind, session.total)
+            }
+        };
+        let session = self.session();
+
+        TimerSnapshot {
+            status: self.status(),
+            kind,
+            remaining_seconds: self.remaining().as_millis().div_ceil(1000) as u64,
+            total_seconds: total.as_secs(),
+            current_task_id: session.and_then(|s| s.task_id.clone()),
+            current_skill_id: session.map(|s| s.skill_id.clone()),
+            session_id: session.map(|s| s.id.clone()),
+            completed_pomodoros: self.completed_pomodoros,
+        }
+    }
+
+    fn transition(&mut self, phase: Phase) {
+        self.phase = phase;
+        self.generation += 1;
+    }
+
+    /// The session type that should follow `kind` once it completes.
+    fn next_after(&self, kind: TimerType, settings: &TimerSettings) -> TimerType {
+        if kind.is_break() {
+            TimerType::Pomodoro
+        } else if settings.long_break_interval > 0
+            && i64::from(self.completed_pomodoros) % settings.long_break_interval == 0
+        {
+            TimerType::LongBreak
+        } else {
+            TimerType::ShortBreak
+        }
+    }
+}
+
+/// Managed state wrapping the [`TimerMachine`].
+#[derive(Default)]
+pub struct Timer(Mutex<TimerMachine>);
+
+impl Timer {
+    pub async fn snapshot(&self) -> TimerSnapshot {
+        self.0.lock().await.snapshot()
+    }
+
+    /// Takes over a session a crash left unfinished, paused where its last
+    /// heartbeat left it. The time since then is not counted.
+    pub async fn adopt(&self, pool: &SqlitePool, unfinished: &Unfinished) -> Result<()> {
+        let kind = TimerType::from_name(&unfinished.kind)
+            .ok_or_else(|| Error::Invalid(format!("unknown session type {}", unfinished.kind)))?;
+        let mut machine = self.0.lock().await;
+        if machine.status() != TimerStatus::Idle {
+            return Err(Error::Timer("already running"));
+        }
+        sessions::reclaim(pool, &unfinished.id, Owner::App).await?;
+
+        let total = minutes(unfinished.planned_minutes);
+        let elapsed = Duration::from_secs(unfinished.elapsed_seconds.max(0) as u64);
+        machine.transition(Phase::Paused {
+            session: ActiveSession {
+                id: unfinished.id.clone(),
+                kind,
+                task_id: unfinished.task_id.clone(),
+                skill_id: unfinished.skill_id.clone(),
+                start_time: unfinished.start_time.clone(),
+                total,
+            },
+            remaining: total.saturating_sub(elapsed),
+            since: unfinished.end_time().to_owned(),
+        });
+        Ok(())
+    }
+}
+
+/// Writes a heartbeat for the current session at `at`. A failed write is
+/// only logged; the countdown itself does not depend on it.
+async fn beat(app: &AppHandle, machine: &TimerMachine, at: &str) {
+    let Some(session) = machine.session() else {
+        return;
+    };
+    let elapsed = session.total.saturating_sub(machine.remaining()).as_secs() as i64;
+    let pool = &app.state::<Db>().0;
+    if let Err(err) = sessions::heartbeat(pool, &session.id, at, elapsed).await {
+        log::warn!("failed to write timer heartbeat: {err}");
+    }
+}
+
+fn minutes(value: i64) -> Duration {
+    Duration::from_secs(value.max(0) as u64 * 60)
+}
+
+async fn begin_session(
+    app: &AppHandle,
+    machine: &mut TimerMachine,
+    kind: TimerType,
+    task_id: Option<String>,
+    skill_id: String,
+) -> Result<TimerSnapshot> {
+    let pool = &app.state::<Db>().0;
+    let planned = TimerSettings::load(pool).await?.minutes(kind);
+    let session = ActiveSession {
+        id: database::generate_id("session"),
+        kind,
+        task_id,
+        skill_id,
+        start_time: database::now_iso(),
+        total: minutes(planned),
+    };
+
+    sessions::start(
+        pool,
+        &NewSession {
+            owner: Owner::App,
+            id: &session.id,
+            task_id: session.task_id.as_deref(),
+            skill_id: &session.skill_id,
+            start_time: &session.start_time,
+            planned_minutes: planned,
+            kind: kind.as_str(),
+        },
+    )
+    .await?;
+
+    let total = session.total;
+    machine.transition(Phase::Running {
+        session,
+        resumed_at: Instant::now(),
+        remaining_at_resume: total,
+    });
+    spawn_ticker(app.clone(), machine.generation);
+
+    Ok(machine.snapshot())
+}
+
+/// Runs a finished session to completion and moves on to the next one.
+async fn complete(app: &AppHandle, machine: &mut TimerMachine) -> Result<()> {
+    let Some(session) = machine.session().cloned() else {
+        return Ok(());
+    };
+    let pool = &app.state::<Db>().0;
+    let settings = TimerSettings::load(pool).await?;
+    let worked = (session.total.as_secs() / 60) as i64;
+
+    sessions::complete(pool, &session.id, worked, true).await?;
+    achievements::refresh(app).await;
+
+    if session.kind == TimerType::Pomodoro {
+        machine.completed_pomodoros += 1;
+    }
+    let next = machine.next_after(session.kind, &settings);
+    machine.transition(Phase::Idle {
+        next,
+        total: minutes(settings.minutes(next)),
+    });
+
+    // The session is already complete, so a failed auto-start leaves the
+    // timer idle on the next phase instead of hiding the completion.
+    if settings.auto_starts(next) {
+        if let Err(err) = begin_session(
+            app,
+            machine,
+            next,
+            session.task_id.clone(),
+            session.skill_id.clone(),
+        )
+        .await
+        {
+            log::error!("failed to auto-start {}: {err}", next.as_str());
+        }
+    }
+
+    app.emit(
+        COMPLETE_EVENT,
+        CompletedSession {
+            session_id: session.id,
+            kind: session.kind,
+            task_id: session.task_id,
+            skill_id: session.skill_id,
+            minutes: worked,
+            next: machine.snapshot(),
+        },
+    )?;
+    Ok(())
+}
+
+/// Ends the current session early at `end_time`, with `remaining` left on
+/// it. Worked pomodoro minutes are kept; a session with nothing to credit is
+/// deleted.
+async fn end_early(
+    app: &AppHandle,
+    machine: &mut TimerMachine,
+    remaining: Duration,
+    end_time: &str,
+) -> Result<()> {
+    let Some(session) = machine.session().cloned() else {
+        return Ok(());
+    };
+    let pool = &app.state::<Db>().0;
+    let worked = (session.total.saturating_sub(remaining).as_secs() / 60) as i64;
+
+    if session.kind == TimerType::Pomodoro && worked > 0 {
+        sessions::complete_at(pool, &session.id, end_time, worked, false).await?;
+        achievements::refresh(app).await;
+    } else {
+        sessions::delete(pool, &session.id).await?;
+    }
+
+    let settings = TimerSettings::load(pool).await?;
+    machine.transition(Phase::Idle {
+        next: TimerType::Pomodoro,
+        total: minutes(settings.pomodoro_duration),
+    });
+    Ok(())
+}
+
+/// Takes a `gap` after the reading `before` out of the running session, as
+/// the sleep policy says.
+async fn interrupt(
+    app: &AppHandle,
+    machine: &mut TimerMachine,
+    before: Reading,
+    gap: Duration,
+) -> Result<()> {
+    let Some(session) = machine.session().cloned() else {
+        return Ok(());
+    };
+    let policy = TimerSettings::load(&app.state::<Db>().0)
+        .await?
+        .sleep_policy;
+    let remaining = machine.remaining_at(before.instant);
+    let since = database::iso(before.wall);
+    log::info!(
+        "timer missed {}s after {since}; applying the {policy:?} sleep policy",
+        gap.as_secs()
+    );
+
+    match policy {
+        SleepPolicy::Pause | SleepPolicy::Ask => {
+            machine.transition(Phase::Paused {
+                session: session.clone(),
+                remaining,
+                since: since.clone(),
+            });
+            beat(app, machine, &since).await;
+        }
+        SleepPolicy::Trim => end_early(app, machine, remaining, &since).await?,
+    }
+
+    app.emit(
+        INTERRUPTED_EVENT,
+        Interruption {
+            session_id: session.id,
+            since,
+            gap_seconds: gap.as_secs(),
+            policy,
+            next: machine.snapshot(),
+        },
+    )?;
+    Ok(())
+}
+
+/// Emits ticks for one running phase and completes it when the time is up.
+/// Exits as soon as the machine moves past `generation`.
+fn spawn_ticker(app: AppHandle, generation: u64) {
+    async_runtime::spawn(async move {
+        let timer = app.state::<Timer>();
+        let mut last = Reading::now();
+        let mut last_beat = last.instant;
+        loop {
+            let wait = {
+                let machine = timer.0.lock().await;
+                if machine.generation != generation {
+                    return;
+                }
+                machine.remaining().min(Duration::from_secs(1))
+            };
+            tokio::time::sleep(wait).await;
+
+            let mut machine = timer.0.lock().await;
+            if machine.generation != generation {
+                return;
+            }
+            // Checked first: where the monotonic clock counts the sleep, the
+            // countdown has already run out.
+            let now = Reading::now();
+            if let Some(gap) = gap(last, now, wait) {
+                if let Err(err) = interrupt(&app, &mut machine, last, gap).await {
+                    log::error!("failed to interrupt timer session: {err}");
+                }
+                return;
+            }
+            last = now;
+            if machine.remaining().is_zero() {
+                if let Err(err) = complete(&app, &mut machine).await {
+                    log::error!("failed to complete timer session: {err}");
+                }
+                return;
+            }
+            if now.instant.duration_since(last_beat) >= HEARTBEAT_INTERVAL {
+                beat(&app, &machine, &database::iso(now.wall)).await;
+                last_beat = now.instant;
+            }
+            if let Err(err) = app.emit(TICK_EVENT, machine.snapshot()) {
+                log::warn!("failed to emit timer tick: {err}");
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn timer_state(timer: State<'_, Timer>) -> Result<TimerSnapshot> {
+    Ok(timer.snapshot().await)
+}
+
+#[tauri::command]
+pub async fn timer_start(
+    app: AppHandle,
+    timer: State<'_, Timer>,
+    kind: TimerType,
+    task_id: Option<String>,
+    skill_id: String,
+) -> Result<TimerSnapshot> {
+    let mut machine = timer.0.lock().await;
+    if machine.status() != TimerStatus::Idle {
+        return Err(Error::Timer("already running"));
+    }
+    begin_session(&app, &mut machine, kind, task_id, skill_id).await
+}
+
+#[tauri::command]
+pub async fn timer_pause(app: AppHandle, timer: State<'_, Timer>) -> Result<TimerSnapshot> {
+    let mut machine = timer.0.lock().await;
+    let remaining = machine.remaining();
+    let Phase::Running { session, .. } = &machine.phase else {
+        return Err(Error::Timer("not running"));
+    };
+    let session = session.clone();
+    let since = database::now_iso();
+    machine.transition(Phase::Paused {
+        session,
+        remaining,
+        since: since.clone(),
+    });
+    beat(&app, &machine, &since).await;
+    Ok(machine.snapshot())
+}
+
+#[tauri::command]
+pub async fn timer_resume(app: AppHandle, timer: State<'_, Timer>) -> Result<TimerSnapshot> {
+    let mut machine = timer.0.lock().await;
+    let Phase::Paused {
+        session, remaining, ..
+    } = &machine.phase
+    else {
+        return Err(Error::Timer("not paused"));
+    };
+    let (session, remaining) = (session.clone(), *remaining);
+    machine.transition(Phase::Running {
+        session,
+        resumed_at: Instant::now(),
+        remaining_at_resume: remaining,
+    });
+    spawn_ticker(app, machine.generation);
+    Ok(machine.snapshot())
+}
+
+/// Ends the current session early. Worked pomodoro minutes are kept; a session
+/// with nothing to credit is deleted. A paused session ends where it paused.
+#[tauri::command]
+pub async fn timer_stop(app: AppHandle, timer: State<'_, Timer>) -> Result<TimerSnapshot> {
+    let mut machine = timer.0.lock().await;
+    let end_time = match &machine.phase {
+        Phase::Paused { since, .. } => since.clone(),
+        _ => database::now_iso(),
+    };
+    let remaining = machine.remaining();
+    end_early(&app, &mut machine, remaining, &end_time).await?;
+    Ok(machine.snapshot())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn after(before: Reading, monotonic: u64, wall: u64) -> Reading {
+        Reading {
+            instant: before.instant + Duration::from_secs(monotonic),
+            wall: before.wall + Duration::from_secs(wall),
+        }
+    }
+
+    #[test]
+    fn regular_ticks_are_not_gaps() {
+        let before = Reading::now();
+        let second = Duration::from_secs(1);
+        assert_eq!(gap(before, after(before, 1, 1), second), None);
+        assert_eq!(gap(before, after(before, 20, 20), second), None);
+    }
+
+    #[test]
+    fn sleep_shows_on_either_clock() {
+        let before = Reading::now();
+        let second = Duration::from_secs(1);
+        // The monotonic clock stopped while the wall clock went on, as on
+        // Linux, or both went on, as on Windows.
+        assert_eq!(
+            gap(before, after(before, 1, 3601), second),
+            Some(Duration::from_secs(3600))
+        );
+        assert_eq!(
+            gap(before, after(before, 601, 601), second),
+            Some(Duration::from_secs(600))
+        );
+    }
+
+    #[test]
+    fn clock_set_back_is_not_a_gap() {
+        let before = Reading::now();
+        let later = Reading {
+            instant: before.instant + Duration::from_secs(1),
+            wall: before.wall - Duration::from_secs(3600),
+        };
+        assert_eq!(gap(before, later, Duration::from_secs(1)), None);
+    }
+
+    #[test]
+    fn remaining_stops_at_the_reading_before_the_gap() {
+        let started = Instant::now();
+        let mut machine = TimerMachine::default();
+        machine.transition(Phase::Running {
+            session: ActiveSession {
+                id: "session_1".into(),
+                kind: TimerType::Pomodoro,
+                task_id: None,
+                skill_id: "skill_1".into(),
+                start_time: database::now_iso(),
+                total: minutes(25),
+            },
+            resumed_at: started,
+            remaining_at_resume: minutes(25),
+        });
+        assert_eq!(machine.remaining_at(started + minutes(10)), minutes(15));
+        assert_eq!(machine.remaining_at(started + minutes(90)), Duration::ZERO);
+    }
+}
@@ -0,0 +1,353 @@
+//! Day boundaries in the user's time zone.
+//!
+//! Sessions keep UTC timestamps. The day a session counts for and the hour
+//! the night owl and early bird achievements look at depend on where the
+//! user is, so they are worked out here from the IANA zone in
+//! `user_settings.timezone` and stored on the session as `local_date` and
+//! `local_hour`. The aggregate triggers bucket `daily_activities` by
+//! `local_date`, and changing the zone re-buckets every session.
+//!
+//! Days start at `user_settings.day_start_hour` rather than midnight when it
+//! is set, so a session at 1 AM can count for the evening before it. The
+//! streaks follow, since they are read from `daily_activities`.
+
+use chrono::{DateTime, Days, NaiveDate, NaiveDateTime, Timelike, Utc};
+use chrono_tz::Tz;
+use sqlx::query::Query;
+use sqlx::sqlite::{SqliteArguments, SqliteExecutor};
+use sqlx::{Sqlite, SqliteConnection, SqlitePool};
+
+use crate::error::{Error, Result};
+
+/// Hours a day can start at, from midnight to 11 PM.
+pub const DAY_START_HOURS: std::ops::Range<i64> = 0..24;
+
+/// An IANA time zone, its name, and the local hour its days start at.
+#[derive(Clone, Debug)]
+pub struct Zone {
+    name: String,
+    tz: Tz,
+    day_start: i64,
+}
+
+impl Zone {
+    /// Looks `name` up in the bundled tz database.
+    pub fn new(name: &str) -> Result<Self> {
+        let tz: Tz = name
+            .parse()
+            .map_err(|_| Error::Invalid(format!("unknown time zone `{name}`")))?;
+        Ok(Zone {
+            name: name.to_owned(),
+            tz,
+            day_start: 0,
+        })
+    }
+
+    pub fn utc() -> Self {
+        Zone {
+            name: "UTC".into(),
+            tz: Tz::UTC,
+            day_start: 0,
+        }
+    }
+
+    /// Starts days at `hour` instead of midnight.
+    pub fn starting_at(self, hour: i64) -> Result<Self> {
+        if !DAY_START_HOURS.contains(&hour) {
+            return Err(Error::Invalid(format!("a day cannot start at hour {hour}")));
+        }
+        Ok(Zone {
+            day_start: hour,
+            ..self
+        })
+    }
+
+    /// The zone this computer is set to, or UTC when it has no IANA name.
+    pub fn system() -> Self {
+        iana_time_zone::get_timezone()
+            .ok()
+            .and_then(|name| Zone::new(&name).ok())
+            .unwrap_or_else(Zone::utc)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn local(&self, instant: DateTime<Utc>) -> NaiveDateTime {
+        instant.with_timezone(&self.tz).naive_local()
+    }
+
+    fn day_of(&self, instant: DateTime<Utc>) -> Option<NaiveDate> {
+        let local = self.local(instant);
+        if i64::from(local.hour()) < self.day_start {
+            local.date().pred_opt()
+        } else {
+            Some(local.date())
+        }
+    }
+
+    /// The day `timestamp` counts for in this zone: its calendar date, or
+    /// the one before when it falls before the day start hour.
+    pub fn date(&self, timestamp: &str) -> Option<NaiveDate> {
+        self.day_of(instant(timestamp)?)
+    }
+
+    /// Hour of the day, 0 to 23, of `timestamp` in this zone.
+    pub fn hour(&self, timestamp: &str) -> Option<i64> {
+        Some(self.local(instant(timestamp)?).hour().into())
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.day_of(Utc::now())
+            .expect("the current date has a day before it")
+    }
+}
+
+/// The day `days` before `date`, where reports that look back that far
+/// start. Refuses negative counts and ones that run off the calendar.
+pub fn days_before(date: NaiveDate, days: i64) -> Result<NaiveDate> {
+    u64::try_from(days)
+        .ok()
+        .and_then(|count| date.checked_sub_days(Days::new(count)))
+        .ok_or_else(|| Error::Invalid(format!("cannot look back {days} days")))
+}
+
+/// Reads RFC 3339 timestamps as the app writes them, and the offset-less
+/// UTC ones SQLite's `datetime('now')` writes.
+fn instant(text: &str) -> Option<DateTime<Utc>> {
+    if let Ok(instant) = DateTime::parse_from_rfc3339(text) {
+        return Some(instant.to_utc());
+    }
+    ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .map(|civil| civil.and_utc())
+}
+
+/// The zone and day start from the settings, or the system zone before one
+/// is stored.
+pub async fn load<'e>(executor: impl SqliteExecutor<'e>) -> Result<Zone> {
+    let stored: Option<(Option<String>, i64)> =
+        sqlx::query_as("SELECT timezone, day_start_hour FROM user_settings WHERE id = 1")
+            .fetch_optional(executor)
+            .await?;
+    let Some((name, day_start)) = stored else {
+        return Ok(Zone::system());
+    };
+    let zone = match name {
+        Some(name) => Zone::new(&name).unwrap_or_else(|err| {
+            log::warn!("{err}; using the system time zone");
+            Zone::system()
+        }),
+        None => Zone::system(),
+    };
+    zone.clone().starting_at(day_start).or_else(|err| {
+        log::warn!("{err}; starting days at midnight");
+        Ok(zone)
+    })
+}
+
+/// `local_date` and `local_hour` for a session, as stored.
+fn local_columns(
+    zone: &Zone,
+    start_time: &str,
+    end_time: Option<&str>,
+) -> (Option<String>, Option<i64>) {
+    (
+        zone.date(start_time).map(|date| date.to_string()),
+        zone.hour(end_time.unwrap_or(start_time)),
+    )
+}
+
+/// Fills in the local day and hour of session `id`. Called in the same
+/// transaction that writes its start or end time.
+pub async fn stamp(conn: &mut SqliteConnection, id: &str) -> Result<()> {
+    let zone = load(&mut *conn).await?;
+    let Some((start_time, end_time)): Option<(String, Option<String>)> =
+        sqlx::query_as("SELECT start_time, end_time FROM timer_sessions WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await?
+    else {
+        return Ok(());
+    };
+    let (date, hour) = local_columns(&zone, &start_time, end_time.as_deref());
+    sqlx::query("UPDATE timer_sessions SET local_date = ?, local_hour = ? WHERE id = ?")
+        .bind(date)
+        .bind(hour)
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Rewrites `local_date` and `local_hour` wherever they differ from `zone`.
+/// The triggers move each re-bucketed session's minutes to its new day.
+async fn restamp(conn: &mut SqliteConnection, zone: &Zone) -> Result<u64> {
+    type Row = (String, String, Option<String>, Option<String>, Option<i64>);
+    let rows: Vec<Row> = sqlx::query_as(
+        "SELECT id, start_time, end_time, local_date, local_hour FROM timer_sessions",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut changed = 0;
+    for (id, start_time, end_time, date, hour) in rows {
+        let local = local_columns(zone, &start_time, end_time.as_deref());
+        if local == (date, hour) {
+            continue;
+        }
+        sqlx::query("UPDATE timer_sessions SET local_date = ?, local_hour = ? WHERE id = ?")
+            .bind(local.0)
+            .bind(local.1)
+            .bind(&id)
+            .execute(&mut *conn)
+            .await?;
+        changed += 1;
+    }
+    Ok(changed)
+}
+
+/// Re-buckets every session in the stored zone and returns how many moved.
+/// Also catches rows written without local columns, such as those restored
+/// from an older backup.
+pub async fn rebucket(pool: &SqlitePool) -> Result<u64> {
+    let mut tx = pool.begin().await?;
+    let changed = rebucket_in(&mut tx).await?;
+    tx.commit().await?;
+    Ok(changed)
+}
+
+/// [`rebucket`] inside the caller's transaction, for updates that change
+/// the zone or day start along with other settings.
+pub async fn rebucket_in(conn: &mut SqliteConnection) -> Result<u64> {
+    let zone = load(&mut *conn).await?;
+    restamp(conn, &zone).await
+}
+
+/// Stores `name` as the user's zone and re-buckets past sessions in it.
+pub async fn set(pool: &SqlitePool, name: &str) -> Result<u64> {
+    let zone = Zone::new(name)?;
+    change(
+        pool,
+        sqlx::query(
+            "UPDATE user_settings SET timezone = ?, updated_at = datetime('now') WHERE id = 1",
+        )
+        .bind(zone.name().to_owned()),
+    )
+    .await
+}
+
+/// Starts days at local `hour` from now on, re-bucketing past sessions too.
+pub async fn set_day_start(pool: &SqlitePool, hour: i64) -> Result<u64> {
+    Zone::utc().starting_at(hour)?;
+    change(
+        pool,
+        sqlx::query(
+            "UPDATE user_settings SET day_start_hour = ?, updated_at = datetime('now') WHERE id = 1",
+        )
+        .bind(hour),
+    )
+    .await
+}
+
+/// Runs a settings update and restamps every session to match, in one
+/// transaction.
+async fn change<'q>(
+    pool: &SqlitePool,
+    update: Query<'q, Sqlite, SqliteArguments<'q>>,
+) -> Result<u64> {
+    let mut tx = pool.begin().await?;
+    update.execute(&mut *tx).await?;
+    let changed = rebucket_in(&mut tx).await?;
+    tx.commit().await?;
+    Ok(changed)
+}
+
+/// Run at startup: adopts the system zone the first time, then re-buckets
+/// anything not yet in the stored zone.
+pub async fn init(pool: &SqlitePool) -> Result<u64> {
+    sqlx::query("UPDATE user_settings SET timezone = ? WHERE id = 1 AND timezone IS NULL")
+        .bind(Zone::system().name())
+        .execute(pool)
+        .await?;
+    rebucket(pool).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn late_evening_stays_on_the_local_day() {
+        let new_york = Zone::new("America/New_York").unwrap();
+        assert_eq!(
+            new_york.date("2024-03-02T03:30:00.000Z"),
+            Some(date("2024-03-01"))
+        );
+        assert_eq!(new_york.hour("2024-03-02T03:30:00.000Z"), Some(22));
+        assert_eq!(
+            Zone::utc().date("2024-03-02T03:30:00.000Z"),
+            Some(date("2024-03-02"))
+        );
+    }
+
+    #[test]
+    fn dst_changes_the_offset() {
+        let berlin = Zone::new("Europe/Berlin").unwrap();
+        // 22:30 UTC is 23:30 in winter and 00:30 the next day in summer.
+        assert_eq!(
+            berlin.date("2024-03-30T22:30:00Z"),
+            Some(date("2024-03-30"))
+        );
+        assert_eq!(
+            berlin.date("2024-03-31T22:30:00Z"),
+            Some(date("2024-04-01"))
+        );
+        // Clocks go forward at 01:00 UTC on 31 March: 02:00 local never happens.
+        assert_eq!(berlin.hour("2024-03-31T00:59:00Z"), Some(1));
+        assert_eq!(berlin.hour("2024-03-31T01:00:00Z"), Some(3));
+        // And back at 01:00 UTC on 27 October: 02:00 local happens twice.
+        assert_eq!(berlin.hour("2024-10-27T00:30:00Z"), Some(2));
+        assert_eq!(berlin.hour("2024-10-27T01:30:00Z"), Some(2));
+    }
+
+    #[test]
+    fn early_hours_count_for_the_day_before() {
+        let night = Zone::new("Europe/Berlin").unwrap().starting_at(4).unwrap();
+        // 01:30 and 03:59 local still belong to Friday; 04:00 is Saturday.
+        assert_eq!(night.date("2024-03-02T00:30:00Z"), Some(date("2024-03-01")));
+        assert_eq!(night.date("2024-03-02T02:59:00Z"), Some(date("2024-03-01")));
+        assert_eq!(night.date("2024-03-02T03:00:00Z"), Some(date("2024-03-02")));
+        // The hour itself is still the clock hour.
+        assert_eq!(night.hour("2024-03-02T00:30:00Z"), Some(1));
+        assert!(Zone::utc().starting_at(24).is_err());
+    }
+
+    #[test]
+    fn sqlite_timestamps_are_utc() {
+        let tokyo = Zone::new("Asia/Tokyo").unwrap();
+        assert_eq!(tokyo.date("2024-01-01 20:00:00"), Some(date("2024-01-02")));
+        assert_eq!(tokyo.date("not a time"), None);
+    }
+
+    #[test]
+    fn look_backs_stay_on_the_calendar() {
+        let today = date("2024-03-02");
+        assert_eq!(days_before(today, 2).unwrap(), date("2024-02-29"));
+        assert!(days_before(today, -1).is_err());
+        assert!(days_before(today, 1_000_000_000).is_err());
+        assert!(days_before(today, i64::MAX).is_err());
+    }
+
+    #[test]
+    fn unknown_zones_are_rejected() {
+        assert!(Zone::new("Mars/Olympus_Mons").is_err());
+        assert_eq!(Zone::new("Asia/Kolkata").unwrap().name(), "Asia/Kolkata");
+    }
+}
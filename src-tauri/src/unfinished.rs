@@ -0,0 +1,166 @@
+//! Sessions the app was still running when it was killed.
+//!
+//! A crash leaves the session row with `completed = 0` and no `end_time`,
+//! counting towards nothing. While a session runs, the timer keeps
+//! `heartbeat_at` and `elapsed_seconds` on it up to date, so on the next
+//! launch [`recover`] knows how far it got and handles it as
+//! `user_settings.recovery_policy` says.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::{AppHandle, State};
+
+use crate::achievements;
+use crate::database::Db;
+use crate::error::{Error, Result};
+use crate::repository::sessions;
+use crate::timer::{Timer, TimerType};
+
+/// What launch does with sessions a crash left unfinished.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum RecoveryPolicy {
+    /// Credit the time up to the last heartbeat and close them.
+    #[default]
+    Close,
+    /// Leave them for the window to offer crediting or discarding.
+    Ask,
+    /// Bring the latest one back paused, and close the rest.
+    Resume,
+}
+
+/// How the user settled an unfinished session.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Resolution {
+    Credit,
+    Discard,
+}
+
+/// A session left running, and how far it got.
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Unfinished {
+    pub id: String,
+    pub task_id: Option<String>,
+    pub skill_id: String,
+    #[serde(rename = "type")]
+    #[sqlx(rename = "type")]
+    pub kind: String,
+    pub start_time: String,
+    /// `None` when the app died before the first heartbeat.
+    pub heartbeat_at: Option<String>,
+    pub elapsed_seconds: i64,
+    pub planned_minutes: i64,
+}
+
+impl Unfinished {
+    /// Whole minutes counted up to the last heartbeat.
+    pub fn worked_minutes(&self) -> i64 {
+        (self.elapsed_seconds / 60).min(self.planned_minutes)
+    }
+
+    /// Where the session is closed: its last heartbeat, or its start.
+    pub fn end_time(&self) -> &str {
+        self.heartbeat_at.as_deref().unwrap_or(&self.start_time)
+    }
+}
+
+/// Incomplete sessions without an end that nobody is running, oldest first.
+/// A session claimed in `active_timer`, like one the CLI is timing, is left
+/// alone.
+pub async fn find(pool: &SqlitePool) -> Result<Vec<Unfinished>> {
+    Ok(sqlx::query_as(
+        "SELECT id, task_id, skill_id, type, start_time, heartbeat_at,
+                COALESCE(elapsed_seconds, 0) AS elapsed_seconds,
+                COALESCE(planned_duration, duration) AS planned_minutes
+         FROM timer_sessions
+         WHERE completed = 0 AND end_time IS NULL
+           AND id NOT IN (SELECT session_id FROM active_timer)
+         ORDER BY start_time",
+    )
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Closes `session` at its last heartbeat with the minutes worked until then,
+/// or deletes it when there is nothing to credit, as stopping the timer would
+/// have.
+pub async fn credit(pool: &SqlitePool, session: &Unfinished) -> Result<()> {
+    let worked = session.worked_minutes();
+    if session.kind == TimerType::Pomodoro.as_str() && worked > 0 {
+        sessions::complete_at(
+            pool,
+            &session.id,
+            session.end_time(),
+            worked,
+            worked >= session.planned_minutes,
+        )
+        .await
+    } else {
+        sessions::delete(pool, &session.id).await
+    }
+}
+
+/// Run at launch, before the timer is used. Returns the sessions left for the
+/// window to ask about.
+pub async fn recover(pool: &SqlitePool, timer: &Timer) -> Result<Vec<Unfinished>> {
+    let mut found = find(pool).await?;
+    let policy: Option<(RecoveryPolicy,)> =
+        sqlx::query_as("SELECT recovery_policy FROM user_settings WHERE id = 1")
+            .fetch_optional(pool)
+            .await?;
+    match policy.map(|(policy,)| policy).unwrap_or_default() {
+        RecoveryPolicy::Ask => return Ok(found),
+        RecoveryPolicy::Resume => {
+            if let Some(latest) = found.pop() {
+                match timer.adopt(pool, &latest).await {
+                    Ok(()) => log::info!("resumed unfinished session {}", latest.id),
+                    Err(err) => {
+                        log::warn!("could not resume session {}: {err}", latest.id);
+                        found.push(latest);
+                    }
+                }
+            }
+        }
+        RecoveryPolicy::Close => {}
+    }
+    for session in &found {
+        credit(pool, session).await?;
+        log::info!(
+            "closed unfinished session {} at {} with {}m",
+            session.id,
+            session.end_time(),
+            session.worked_minutes()
+        );
+    }
+    Ok(Vec::new())
+}
+
+#[tauri::command]
+pub async fn unfinished_sessions(db: State<'_, Db>) -> Result<Vec<Unfinished>> {
+    find(&db.0).await
+}
+
+#[tauri::command]
+pub async fn resolve_unfinished_session(
+    app: AppHandle,
+    db: State<'_, Db>,
+    id: String,
+    resolution: Resolution,
+) -> Result<()> {
+    let session = find(&db.0)
+        .await?
+        .into_iter()
+        .find(|session| session.id == id)
+        .ok_or_else(|| Error::Invalid(format!("session {id} is not unfinished")))?;
+    match resolution {
+        Resolution::Credit => {
+            credit(&db.0, &session).await?;
+            achievements::refresh(&app).await;
+        }
+        Resolution::Discard => sessions::delete(&db.0, &session.id).await?,
+    }
+    Ok(())
+}
@@ -0,0 +1,362 @@
+//! Round trips through [`backup`] archives and the settings page's JSON
+//! export on migrated in-memory databases.
+
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::SqlitePool;
+use ten_k_hours_app_lib::backup::{self, Archive, ConflictPolicy, RestoreOptions};
+use ten_k_hours_app_lib::database::{get_migrations, migrate};
+use ten_k_hours_app_lib::repository::data::{self, DataExport};
+use ten_k_hours_app_lib::schema;
+use ten_k_hours_app_lib::timezone;
+
+async fn memory_pool() -> SqlitePool {
+    // Every in-memory connection is its own database, so keep exactly one.
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(SqliteConnectOptions::new().in_memory(true))
+        .await
+        .unwrap();
+    migrate(&pool).await.unwrap();
+    pool
+}
+
+/// A database with a row in every table, including the columns the old JSON
+/// export dropped.
+async fn seeded_pool() -> SqlitePool {
+    let pool = memory_pool().await;
+    sqlx::raw_sql(
+        "UPDATE user_settings SET name = 'Ada', pomodoro_duration = 50, api_port = 48000,
+             api_token = 'secret-api-token', spotify_refresh_token = 'secret-refresh-token';
+         INSERT INTO skills (id, name, goal_hours, color)
+         VALUES ('skill_1', 'Piano', 10000, '#1A73E8'), ('skill_2', 'Go', 2500.5, '#34A853');
+         INSERT INTO tasks (id, skill_id, title, priority, due_date, estimated_pomodoros)
+         VALUES ('task_1', 'skill_1', 'Chopin op. 10', 'high', '2024-06-01', 8);
+         INSERT INTO timer_sessions (id, task_id, skill_id, start_time, end_time, duration, type, completed, planned_duration)
+         VALUES ('session_1', 'task_1', 'skill_1', '2024-02-01T09:00:00.000Z', '2024-02-01T09:25:00.000Z', 25, 'pomodoro', 1, 25),
+                ('session_2', NULL, 'skill_1', '2024-02-01T09:30:00.000Z', NULL, 5, 'short-break', 1, 5),
+                ('session_3', NULL, 'skill_2', '2024-02-02T21:00:00.000Z', NULL, 40, 'pomodoro', 1, 50);
+         -- Minutes imported without sessions behind them
+         UPDATE skills SET current_minutes = current_minutes + 600 WHERE id = 'skill_2';
+         INSERT INTO reflections (id, date, content, mood)
+         VALUES ('reflection_1', '2024-02-01', 'Slow practice pays off', 'good');
+         INSERT INTO reflection_skills (reflection_id, skill_id) VALUES ('reflection_1', 'skill_1');
+         UPDATE achievements SET progress = 25, unlocked_at = '2024-02-01T09:25:00.000Z'
+         WHERE id = 'ach_first_hour';",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    // Stamps the local days the raw inserts above left out.
+    timezone::set(&pool, "UTC").await.unwrap();
+    pool
+}
+
+fn replace() -> RestoreOptions {
+    RestoreOptions {
+        on_conflict: ConflictPolicy::Replace,
+        dry_run: false,
+    }
+}
+
+fn rows<'a>(archive: &'a Archive, table: &str) -> &'a [backup::Row] {
+    &archive
+        .tables
+        .iter()
+        .find(|dump| dump.name == table)
+        .unwrap()
+        .rows
+}
+
+#[test]
+fn every_table_is_backed_up() {
+    let mut expected: Vec<&str> = schema::EXPECTED
+        .iter()
+        .map(|(table, _)| *table)
+        .filter(|table| *table != "active_timer")
+        .collect();
+    let mut tables = backup::TABLES.to_vec();
+    expected.sort_unstable();
+    tables.sort_unstable();
+    assert_eq!(tables, expected);
+}
+
+#[tokio::test]
+async fn round_trip_into_a_fresh_database() {
+    let source = seeded_pool().await;
+    let archive = backup::create(&source).await.unwrap();
+    let latest = get_migrations().iter().map(|m| m.version).max().unwrap();
+    assert_eq!(archive.schema_version, latest);
+    assert_eq!(rows(&archive, "timer_sessions").len(), 3);
+    assert_eq!(rows(&archive, "tasks")[0]["due_date"], "2024-06-01");
+
+    // Through the file format and back.
+    let json = serde_json::to_string_pretty(&archive).unwrap();
+    let read: Archive = serde_json::from_str(&json).unwrap();
+    assert_eq!(read, archive);
+    read.verify().unwrap();
+
+    let target = memory_pool().await;
+    let report = backup::restore(&target, &read, replace()).await.unwrap();
+    let sessions = report
+        .tables
+        .iter()
+        .find(|changes| changes.table == "timer_sessions")
+        .unwrap();
+    assert_eq!(sessions.added, 3);
+
+    let copy = backup::create(&target).await.unwrap();
+    for table in backup::TABLES {
+        assert_eq!(rows(&copy, table), rows(&archive, table), "{table}");
+    }
+    let (minutes,): (i64,) =
+        sqlx::query_as("SELECT current_minutes FROM skills WHERE id = 'skill_2'")
+            .fetch_one(&target)
+            .await
+            .unwrap();
+    assert_eq!(minutes, 640);
+}
+
+#[tokio::test]
+async fn json_import_keeps_the_history_already_here() {
+    let pool = seeded_pool().await;
+    // The JSON export has whole hours only.
+    sqlx::query("UPDATE skills SET goal_hours = 2500 WHERE id = 'skill_2'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let json = serde_json::to_string(&data::export(&pool).await.unwrap()).unwrap();
+    sqlx::raw_sql(
+        "INSERT INTO timer_sessions (id, task_id, skill_id, start_time, duration, type, completed, local_date)
+         VALUES ('session_4', 'task_1', 'skill_1', '2024-02-03T09:00:00.000Z', 30, 'pomodoro', 1, '2024-02-03');",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let count = |sql: &'static str| {
+        let pool = pool.clone();
+        async move {
+            let (count,): (i64,) = sqlx::query_as(sql).fetch_one(&pool).await.unwrap();
+            count
+        }
+    };
+    let export: DataExport = serde_json::from_str(&json).unwrap();
+    data::import(&pool, export).await.unwrap();
+    assert_eq!(count("SELECT COUNT(*) FROM timer_sessions").await, 4);
+    assert_eq!(count("SELECT COUNT(*) FROM tasks").await, 1);
+    assert_eq!(count("SELECT COUNT(*) FROM reflection_skills").await, 1);
+    assert_eq!(
+        count("SELECT current_minutes FROM skills WHERE id = 'skill_1'").await,
+        55
+    );
+    assert_eq!(
+        count("SELECT total_minutes FROM daily_activities WHERE date = '2024-02-03'").await,
+        30
+    );
+
+    // Into an empty database, the sessions come along and count once.
+    let target = memory_pool().await;
+    timezone::set(&target, "UTC").await.unwrap();
+    let export: DataExport = serde_json::from_str(&json).unwrap();
+    data::import(&target, export).await.unwrap();
+    let minutes: Vec<(String, i64)> =
+        sqlx::query_as("SELECT id, current_minutes FROM skills ORDER BY id")
+            .fetch_all(&target)
+            .await
+            .unwrap();
+    assert_eq!(minutes, [("skill_1".into(), 25), ("skill_2".into(), 640)]);
+    let days: Vec<(String, i64, i64)> = sqlx::query_as(
+        "SELECT date, total_minutes, total_sessions FROM daily_activities ORDER BY date",
+    )
+    .fetch_all(&target)
+    .await
+    .unwrap();
+    assert_eq!(
+        days,
+        [("2024-02-01".into(), 25, 1), ("2024-02-02".into(), 40, 1)]
+    );
+}
+
+#[tokio::test]
+async fn restoring_onto_itself_changes_nothing() {
+    let pool = seeded_pool().await;
+    let archive = backup::create(&pool).await.unwrap();
+
+    let report = backup::restore(&pool, &archive, replace()).await.unwrap();
+    for changes in &report.tables {
+        assert_eq!(
+            (changes.added, changes.updated, changes.skipped),
+            (0, 0, 0),
+            "{}",
+            changes.table
+        );
+    }
+    let again = backup::create(&pool).await.unwrap();
+    assert_eq!(again.tables, archive.tables);
+}
+
+#[tokio::test]
+async fn dry_run_reports_conflicts_without_writing() {
+    let pool = seeded_pool().await;
+    let archive = backup::create(&pool).await.unwrap();
+    sqlx::raw_sql(
+        "UPDATE skills SET name = 'Harpsichord' WHERE id = 'skill_1';
+         DELETE FROM reflections;",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    let before = backup::create(&pool).await.unwrap();
+
+    let options = RestoreOptions {
+        on_conflict: ConflictPolicy::Replace,
+        dry_run: true,
+    };
+    let report = backup::restore(&pool, &archive, options).await.unwrap();
+    assert!(report.dry_run);
+    let changes = |table: &str| {
+        report
+            .tables
+            .iter()
+            .find(|changes| changes.table == table)
+            .unwrap()
+            .clone()
+    };
+    assert_eq!(changes("skills").updated, 1);
+    assert_eq!(changes("skills").conflicts, ["skill_1"]);
+    assert_eq!(changes("reflections").added, 1);
+    assert_eq!(changes("reflection_skills").added, 1);
+    assert_eq!(backup::create(&pool).await.unwrap().tables, before.tables);
+
+    // Skipping keeps the local name; failing leaves everything untouched.
+    let skip = RestoreOptions::default();
+    let report = backup::restore(&pool, &archive, skip).await.unwrap();
+    let skills = report.tables.iter().find(|c| c.table == "skills").unwrap();
+    assert_eq!((skills.skipped, skills.updated), (1, 0));
+    let (name,): (String,) = sqlx::query_as("SELECT name FROM skills WHERE id = 'skill_1'")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(name, "Harpsichord");
+
+    sqlx::raw_sql("DELETE FROM reflections")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let fail = RestoreOptions {
+        on_conflict: ConflictPolicy::Fail,
+        dry_run: false,
+    };
+    let err = backup::restore(&pool, &archive, fail).await.unwrap_err();
+    assert!(err.to_string().contains("skill_1"), "{err}");
+    let (reflections,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM reflections")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(reflections, 0);
+}
+
+#[tokio::test]
+async fn replacing_keeps_what_only_this_database_has() {
+    let pool = seeded_pool().await;
+    let archive = backup::create(&pool).await.unwrap();
+    // Since the backup: a session the archive does not have, a renamed skill
+    // so the restore replaces it, and the reflection rewritten under a new id
+    // with its skill link.
+    sqlx::raw_sql(
+        "INSERT INTO timer_sessions (id, skill_id, start_time, duration, type, completed, local_date)
+         VALUES ('session_4', 'skill_2', '2024-02-02T22:00:00.000Z', 15, 'pomodoro', 1, '2024-02-02');
+         UPDATE skills SET name = 'Baduk' WHERE id = 'skill_2';
+         DELETE FROM reflections;
+         INSERT INTO reflections (id, date, content, mood)
+         VALUES ('reflection_2', '2024-02-01', 'Rewritten', 'great');
+         INSERT INTO reflection_skills (reflection_id, skill_id) VALUES ('reflection_2', 'skill_1');",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let report = backup::restore(&pool, &archive, replace()).await.unwrap();
+    let changes = |table: &str| {
+        report
+            .tables
+            .iter()
+            .find(|changes| changes.table == table)
+            .unwrap()
+            .clone()
+    };
+    assert_eq!(changes("skills").updated, 1);
+    let reflections = changes("reflections");
+    assert_eq!((reflections.added, reflections.skipped), (0, 1));
+    assert_eq!(reflections.conflicts, ["reflection_1"]);
+    assert_eq!(changes("reflection_skills").skipped, 1);
+
+    let (name, minutes): (String, i64) =
+        sqlx::query_as("SELECT name, current_minutes FROM skills WHERE id = 'skill_2'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!((name.as_str(), minutes), ("Go", 655));
+    let (day_minutes, day_sessions): (i64, i64) = sqlx::query_as(
+        "SELECT total_minutes, total_sessions FROM daily_activities WHERE date = '2024-02-02'",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!((day_minutes, day_sessions), (55, 2));
+    let links: Vec<(String,)> = sqlx::query_as("SELECT reflection_id FROM reflection_skills")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(links, [("reflection_2".into(),)]);
+}
+
+#[tokio::test]
+async fn credentials_stay_out_of_archives() {
+    let source = seeded_pool().await;
+    let archive = backup::create(&source).await.unwrap();
+    let json = serde_json::to_string(&archive).unwrap();
+    assert!(!json.contains("secret-api-token"));
+    assert!(!json.contains("secret-refresh-token"));
+    let settings = &rows(&archive, "user_settings")[0];
+    assert!(!settings.contains_key("api_token"));
+    assert!(!settings.contains_key("api_port"));
+
+    // Even an older archive that has them leaves the target's own in place.
+    let mut old = archive.clone();
+    let dump = &mut old.tables[0];
+    assert_eq!(dump.name, "user_settings");
+    dump.rows[0].insert("api_token".into(), "leaked".into());
+    dump.rows[0].insert("api_port".into(), 1.into());
+    dump.checksum = backup::checksum(&dump.rows);
+    let target = memory_pool().await;
+    sqlx::raw_sql("UPDATE user_settings SET api_token = 'mine'")
+        .execute(&target)
+        .await
+        .unwrap();
+    backup::restore(&target, &old, replace()).await.unwrap();
+    let (token, port, name): (Option<String>, i64, String) =
+        sqlx::query_as("SELECT api_token, api_port, name FROM user_settings")
+            .fetch_one(&target)
+            .await
+            .unwrap();
+    assert_eq!((token.as_deref(), name.as_str()), (Some("mine"), "Ada"));
+    assert_ne!(port, 1);
+}
+
+#[tokio::test]
+async fn damaged_and_newer_archives_are_refused() {
+    let pool = seeded_pool().await;
+    let archive = backup::create(&pool).await.unwrap();
+
+    let mut damaged = archive.clone();
+    damaged.tables[1].rows[0].insert("goal_hours".into(), 1.into());
+    assert!(backup::restore(&pool, &damaged, replace()).await.is_err());
+
+    let mut newer = archive;
+    newer.schema_version += 1;
+    let err = backup::restore(&pool, &newer, replace()).await.unwrap_err();
+    assert!(err.to_string().contains("newer"), "{err}");
+}
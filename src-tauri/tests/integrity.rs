@@ -0,0 +1,135 @@
+//! Orphan repair on databases written without foreign key enforcement.
+
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::SqlitePool;
+use ten_k_hours_app_lib::database::{self, migrate};
+use ten_k_hours_app_lib::integrity::{self, Action};
+
+/// A migrated in-memory database that accepts orphans, like the ones the
+/// webview used to write.
+async fn unenforced_pool() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(
+            SqliteConnectOptions::new()
+                .in_memory(true)
+                .foreign_keys(false),
+        )
+        .await
+        .unwrap();
+    migrate(&pool).await.unwrap();
+    pool
+}
+
+#[tokio::test]
+async fn orphans_are_reattached_or_purged() {
+    let pool = unenforced_pool().await;
+    sqlx::raw_sql(
+        "INSERT INTO skills (id, name) VALUES ('skill_1', 'Piano');
+         INSERT INTO tasks (id, skill_id, title)
+         VALUES ('task_1', 'skill_1', 'Scales'), ('task_gone', 'skill_gone', 'Etude');
+         INSERT INTO timer_sessions (id, task_id, skill_id, start_time, duration, type, completed)
+         VALUES ('kept', 'task_1', 'skill_1', '2024-03-01T08:00:00.000Z', 25, 'pomodoro', 1),
+                ('moved', 'task_1', 'skill_gone', '2024-03-01T09:00:00.000Z', 25, 'pomodoro', 1),
+                ('detached', 'task_gone', 'skill_1', '2024-03-01T10:00:00.000Z', 25, 'pomodoro', 1),
+                ('purged', NULL, 'skill_gone', '2024-03-02T09:00:00.000Z', 50, 'pomodoro', 1);
+         INSERT INTO reflections (id, date, content) VALUES ('reflection_1', '2024-03-01', 'Fine');
+         INSERT INTO reflection_skills (reflection_id, skill_id)
+         VALUES ('reflection_1', 'skill_1'), ('reflection_1', 'skill_gone'), ('reflection_gone', 'skill_1');",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let mut found: Vec<(String, String, Action)> = integrity::repair(&pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|orphan| (orphan.table, orphan.id, orphan.action))
+        .collect();
+    found.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+    assert_eq!(
+        found,
+        [
+            (
+                "reflection_skills".into(),
+                "reflection_1/skill_gone".into(),
+                Action::Purged
+            ),
+            (
+                "reflection_skills".into(),
+                "reflection_gone/skill_1".into(),
+                Action::Purged
+            ),
+            ("tasks".into(), "task_gone".into(), Action::Purged),
+            ("timer_sessions".into(), "detached".into(), Action::Detached),
+            ("timer_sessions".into(), "moved".into(), Action::Reattached),
+            ("timer_sessions".into(), "purged".into(), Action::Purged),
+        ]
+    );
+
+    let sessions: Vec<(String, Option<String>, String)> =
+        sqlx::query_as("SELECT id, task_id, skill_id FROM timer_sessions ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert_eq!(
+        sessions,
+        [
+            ("detached".into(), None, "skill_1".into()),
+            ("kept".into(), Some("task_1".into()), "skill_1".into()),
+            ("moved".into(), Some("task_1".into()), "skill_1".into()),
+        ]
+    );
+    // The purged session no longer counts for its day.
+    let days: Vec<(String, i64, i64)> = sqlx::query_as(
+        "SELECT date, total_minutes, total_sessions FROM daily_activities ORDER BY date",
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(days, [("2024-03-01".into(), 75, 3)]);
+
+    // Nothing is left to do the next time.
+    assert!(integrity::repair(&pool).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn opened_databases_enforce_foreign_keys() {
+    let root = std::env::temp_dir().join(format!("tenk-{}", uuid::Uuid::new_v4().simple()));
+    let pool = database::open(&root.join(database::DB_FILE)).await.unwrap();
+    sqlx::raw_sql(
+        "INSERT INTO skills (id, name) VALUES ('skill_1', 'Piano');
+         INSERT INTO tasks (id, skill_id, title) VALUES ('task_1', 'skill_1', 'Scales');
+         INSERT INTO timer_sessions (id, task_id, skill_id, start_time, duration, type, completed)
+         VALUES ('session_1', 'task_1', 'skill_1', '2024-03-01T09:00:00.000Z', 25, 'pomodoro', 1);",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let orphan = sqlx::query(
+        "INSERT INTO tasks (id, skill_id, title) VALUES ('task_2', 'skill_gone', 'Etude')",
+    )
+    .execute(&pool)
+    .await;
+    assert!(orphan.is_err());
+
+    // Deleting a task cascades to its sessions and out of the totals.
+    sqlx::query("DELETE FROM tasks WHERE id = 'task_1'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let (sessions, days): (i64, i64) = sqlx::query_as(
+        "SELECT (SELECT COUNT(*) FROM timer_sessions), (SELECT COUNT(*) FROM daily_activities)",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!((sessions, days), (0, 0));
+
+    pool.close().await;
+    std::fs::remove_dir_all(root).unwrap();
+}
@@ -0,0 +1,1007 @@
+//! Replays [`get_migrations`] step by step on in-memory SQLite, seeding rows
+//! the way the app writes them between steps, and checks that the final
+//! schema and data come out as the repository layer expects.
+
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{Connection, SqliteConnection, SqlitePool};
+use tauri_plugin_sql::MigrationKind;
+use ten_k_hours_app_lib::database::{get_migrations, migrate, revert, MIN_REVERT_VERSION};
+use ten_k_hours_app_lib::repository::achievements::{self as repo, CreateAchievementInput};
+use ten_k_hours_app_lib::repository::activities;
+use ten_k_hours_app_lib::repository::sessions::{self, NewSession, Owner};
+use ten_k_hours_app_lib::repository::settings::{self, UpdateSettingsInput};
+use ten_k_hours_app_lib::schema;
+use ten_k_hours_app_lib::timezone;
+use ten_k_hours_app_lib::{achievements, analytics};
+
+/// `id, task_id, duration, type, planned_duration, session_type, created_at`
+type SessionRow = (
+    String,
+    Option<String>,
+    i64,
+    String,
+    Option<i64>,
+    Option<String>,
+    String,
+);
+
+async fn memory_connection() -> SqliteConnection {
+    SqliteConnection::connect_with(&SqliteConnectOptions::new().in_memory(true))
+        .await
+        .unwrap()
+}
+
+async fn memory_pool() -> SqlitePool {
+    // Every in-memory connection is its own database, so keep exactly one.
+    SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(SqliteConnectOptions::new().in_memory(true))
+        .await
+        .unwrap()
+}
+
+async fn exec(conn: &mut SqliteConnection, sql: &str) {
+    sqlx::raw_sql(sql)
+        .execute(&mut *conn)
+        .await
+        .unwrap_or_else(|e| panic!("{e}\n{sql}"));
+}
+
+/// Rows written by the app as it existed at `version`, before the next
+/// migration runs.
+async fn seed_after(conn: &mut SqliteConnection, version: i64) {
+    match version {
+        1 => {
+            exec(
+                conn,
+                "INSERT INTO skills (id, name, description, goal_hours, current_minutes, color, is_active, created_at, updated_at)
+                 VALUES ('skill_1', 'Piano', 'Classical repertoire', 10000, 75, '#1A73E8', 1, '2024-01-01 08:00:00', '2024-01-02 09:00:00'),
+                        ('skill_2', 'Go', NULL, 5000, 0, '#34A853', 0, '2024-01-03 08:00:00', '2024-01-03 08:00:00');
+
+                 INSERT INTO tasks (id, skill_id, title, status, pomodoro_sessions, total_minutes, order_index, created_at)
+                 VALUES ('task_1', 'skill_1', 'Scales', 'in-progress', 2, 50, 0, '2024-01-01 08:05:00'),
+                        ('task_2', 'skill_1', 'Nocturne', 'done', 1, 25, 1, '2024-01-01 08:06:00');
+
+                 INSERT INTO timer_sessions (id, task_id, skill_id, start_time, end_time, duration, type, completed, created_at)
+                 VALUES ('session_1', 'task_1', 'skill_1', '2024-01-01T09:00:00.000Z', '2024-01-01T09:25:00.000Z', 25, 'pomodoro', 1, '2024-01-01 09:00:00'),
+                        ('session_2', 'task_2', 'skill_1', '2024-01-02T09:00:00.000Z', '2024-01-02T09:05:00.000Z', 5, 'short-break', 1, '2024-01-02 09:00:00');
+
+                 INSERT INTO reflections (id, date, content, mood, created_at, updated_at)
+                 VALUES ('reflection_1', '2024-01-01', 'Slow practice works', 'good', '2024-01-01 21:00:00', '2024-01-01 21:00:00');
+                 INSERT INTO reflection_skills (reflection_id, skill_id) VALUES ('reflection_1', 'skill_1');
+
+                 INSERT INTO daily_activities (date, total_minutes, total_sessions)
+                 VALUES ('2024-01-01', 50, 2), ('2024-01-02', 25, 1);",
+            )
+            .await;
+        }
+        3 => {
+            exec(
+                conn,
+                "INSERT INTO timer_sessions
+                    (id, task_id, skill_id, start_time, end_time, duration, type, completed, created_at, planned_duration, session_type)
+                 VALUES ('session_3', 'task_1', 'skill_1', '2024-01-03T09:00:00.000Z', '2024-01-03T09:50:00.000Z', 50, 'pomodoro', 1, '2024-01-03 09:00:00', 50, 'pomodoro');",
+            )
+            .await;
+        }
+        4 => {
+            exec(
+                conn,
+                "INSERT INTO timer_sessions (id, task_id, skill_id, start_time, duration, type, completed, planned_duration, session_type, created_at)
+                 VALUES ('session_4', NULL, 'skill_2', '2024-01-04T09:00:00.000Z', 25, 'pomodoro', 0, 25, 'pomodoro', '2024-01-04 09:00:00');",
+            )
+            .await;
+        }
+        _ => {}
+    }
+}
+
+/// Asserts what each migration itself is responsible for.
+async fn check_after(conn: &mut SqliteConnection, version: i64) {
+    match version {
+        2 => {
+            let (achievements,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM achievements")
+                .fetch_one(&mut *conn)
+                .await
+                .unwrap();
+            assert_eq!(achievements, 15);
+        }
+        3 => {
+            let backfilled: Vec<(String, i64, String)> = sqlx::query_as(
+                "SELECT id, planned_duration, session_type FROM timer_sessions ORDER BY id",
+            )
+            .fetch_all(&mut *conn)
+            .await
+            .unwrap();
+            assert_eq!(
+                backfilled,
+                [
+                    ("session_1".into(), 25, "pomodoro".into()),
+                    ("session_2".into(), 5, "pomodoro".into()),
+                ]
+            );
+        }
+        7 => {
+            let goals: Vec<(String, i64)> =
+                sqlx::query_as("SELECT id, daily_goal_minutes FROM skills ORDER BY id")
+                    .fetch_all(&mut *conn)
+                    .await
+                    .unwrap();
+            assert_eq!(goals, [("skill_1".into(), 60), ("skill_2".into(), 60)]);
+        }
+        _ => {}
+    }
+}
+
+/// Columns in order, foreign keys, indexes and triggers, for comparing
+/// schemas that `ALTER TABLE` may have left with different `CREATE` text.
+type Shape = (
+    Vec<(String, String, String, bool, Option<String>, i64)>,
+    Vec<(String, String, String, String)>,
+    Vec<(String, String, String)>,
+);
+
+async fn schema_shape(conn: &mut SqliteConnection) -> Shape {
+    let columns = sqlx::query_as(
+        "SELECT m.name, p.name, p.type, p.\"notnull\", p.dflt_value, p.pk
+         FROM sqlite_master m JOIN pragma_table_info(m.name) p
+         WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%' AND m.name NOT LIKE '_sqlx_%'
+         ORDER BY m.name, p.cid",
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .unwrap();
+    let foreign_keys = sqlx::query_as(
+        "SELECT m.name, f.\"from\", f.\"table\", f.on_delete
+         FROM sqlite_master m JOIN pragma_foreign_key_list(m.name) f
+         WHERE m.type = 'table'
+         ORDER BY m.name, f.\"from\"",
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .unwrap();
+    let objects = sqlx::query_as(
+        "SELECT type, name, tbl_name FROM sqlite_master
+         WHERE type IN ('index', 'trigger') AND name NOT LIKE 'sqlite_%'
+         ORDER BY type, name",
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .unwrap();
+    (columns, foreign_keys, objects)
+}
+
+/// `sqlite_master` minus sqlx bookkeeping, for comparing two databases.
+async fn schema_sql(conn: &mut SqliteConnection) -> Vec<(String, String, Option<String>)> {
+    sqlx::query_as(
+        "SELECT type, name, sql FROM sqlite_master
+         WHERE name NOT LIKE 'sqlite_%' AND name NOT LIKE '_sqlx_%'
+         ORDER BY type, name",
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .unwrap()
+}
+
+async fn replay() -> SqliteConnection {
+    replay_to(i64::MAX).await
+}
+
+/// [`replay`] of the up migrations up to and including `version`.
+async fn replay_to(version: i64) -> SqliteConnection {
+    let mut conn = memory_connection().await;
+    exec(&mut conn, "PRAGMA foreign_keys = ON").await;
+    for migration in get_migrations()
+        .into_iter()
+        .filter(|m| matches!(m.kind, MigrationKind::Up) && m.version <= version)
+    {
+        exec(&mut conn, migration.sql).await;
+        check_after(&mut conn, migration.version).await;
+        seed_after(&mut conn, migration.version).await;
+    }
+    conn
+}
+
+#[test]
+fn versions_are_sequential_and_reversible() {
+    let migrations = get_migrations();
+    let ups: Vec<_> = migrations
+        .iter()
+        .filter(|m| matches!(m.kind, MigrationKind::Up))
+        .collect();
+    for (index, migration) in ups.iter().enumerate() {
+        assert_eq!(
+            migration.version,
+            index as i64 + 1,
+            "{}",
+            migration.description
+        );
+        assert!(!migration.sql.trim().is_empty());
+    }
+
+    // Each version from MIN_REVERT_VERSION + 1 on is followed by its down
+    // migration, so sqlx reverts them newest first.
+    let mut expected = Vec::new();
+    for up in &ups {
+        expected.push((up.version, up.description, true));
+        if up.version > MIN_REVERT_VERSION {
+            expected.push((up.version, up.description, false));
+        }
+    }
+    let actual: Vec<_> = migrations
+        .iter()
+        .map(|m| {
+            (
+                m.version,
+                m.description,
+                matches!(m.kind, MigrationKind::Up),
+            )
+        })
+        .collect();
+    assert_eq!(actual, expected);
+}
+
+#[tokio::test]
+async fn final_schema_matches_repository() {
+    let mut conn = replay().await;
+
+    let tables: Vec<(String,)> = sqlx::query_as(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )
+    .fetch_all(&mut conn)
+    .await
+    .unwrap();
+    let mut expected: Vec<&str> = schema::EXPECTED.iter().map(|(table, _)| *table).collect();
+    expected.sort_unstable();
+    assert_eq!(
+        tables
+            .iter()
+            .map(|(name,)| name.as_str())
+            .collect::<Vec<_>>(),
+        expected
+    );
+
+    for (table, columns) in schema::EXPECTED {
+        let live: Vec<(String,)> = sqlx::query_as("SELECT name FROM pragma_table_info(?)")
+            .bind(table)
+            .fetch_all(&mut conn)
+            .await
+            .unwrap();
+        let mut have: Vec<&str> = live.iter().map(|(name,)| name.as_str()).collect();
+        let mut want = columns.to_vec();
+        want.sort_unstable();
+        have.sort_unstable();
+        assert_eq!(have, want, "columns of {table}");
+    }
+}
+
+#[tokio::test]
+async fn indexes_survive_table_rebuilds() {
+    let mut conn = replay().await;
+
+    let indexes: Vec<(String, String)> = sqlx::query_as(
+        "SELECT name, tbl_name FROM sqlite_master
+         WHERE type = 'index' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )
+    .fetch_all(&mut conn)
+    .await
+    .unwrap();
+    assert_eq!(
+        indexes,
+        [
+            (
+                "idx_daily_activities_date".into(),
+                "daily_activities".into()
+            ),
+            (
+                "idx_reflection_tags_tag_id".into(),
+                "reflection_tags".into()
+            ),
+            ("idx_session_tags_tag_id".into(), "session_tags".into()),
+            ("idx_subskills_parent_id".into(), "subskills".into()),
+            ("idx_subtasks_parent_id".into(), "subtasks".into()),
+            (
+                "idx_task_dependencies_blocked_by_id".into(),
+                "task_dependencies".into()
+            ),
+            ("idx_task_tags_tag_id".into(), "task_tags".into()),
+            ("idx_tasks_previous_id".into(), "tasks".into()),
+            ("idx_tasks_series_id".into(), "tasks".into()),
+            ("idx_tasks_skill_id".into(), "tasks".into()),
+            ("idx_tasks_status".into(), "tasks".into()),
+            (
+                "idx_timer_sessions_created_at".into(),
+                "timer_sessions".into()
+            ),
+            (
+                "idx_timer_sessions_local_date".into(),
+                "timer_sessions".into()
+            ),
+            (
+                "idx_timer_sessions_skill_id".into(),
+                "timer_sessions".into()
+            ),
+            ("idx_timer_sessions_task_id".into(), "timer_sessions".into()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn foreign_keys_are_declared_and_hold() {
+    let mut conn = replay().await;
+
+    let keys: Vec<(String, String, String, String)> = sqlx::query_as(
+        "SELECT m.name, f.\"from\", f.\"table\", f.on_delete
+         FROM sqlite_master m, pragma_foreign_key_list(m.name) f
+         WHERE m.type = 'table'
+         ORDER BY m.name, f.\"from\"",
+    )
+    .fetch_all(&mut conn)
+    .await
+    .unwrap();
+    let cascade = |table: &str, from: &str, to: &str| {
+        (table.into(), from.into(), to.into(), "CASCADE".into())
+    };
+    assert_eq!(
+        keys,
+        [
+            cascade("active_timer", "session_id", "timer_sessions"),
+            cascade("reflection_skills", "reflection_id", "reflections"),
+            cascade("reflection_skills", "skill_id", "skills"),
+            cascade("reflection_tags", "reflection_id", "reflections"),
+            cascade("reflection_tags", "tag_id", "tags"),
+            cascade("session_tags", "session_id", "timer_sessions"),
+            cascade("session_tags", "tag_id", "tags"),
+            cascade("subskills", "parent_id", "skills"),
+            cascade("subskills", "skill_id", "skills"),
+            cascade("subtasks", "parent_id", "tasks"),
+            cascade("subtasks", "task_id", "tasks"),
+            cascade("task_dependencies", "blocked_by_id", "tasks"),
+            cascade("task_dependencies", "task_id", "tasks"),
+            cascade("task_tags", "tag_id", "tags"),
+            cascade("task_tags", "task_id", "tasks"),
+            cascade("tasks", "skill_id", "skills"),
+            cascade("timer_sessions", "skill_id", "skills"),
+            cascade("timer_sessions", "task_id", "tasks"),
+        ]
+    );
+
+    let violations: Vec<(String, Option<i64>)> =
+        sqlx::query_as("SELECT \"table\", rowid FROM pragma_foreign_key_check")
+            .fetch_all(&mut conn)
+            .await
+            .unwrap();
+    assert!(violations.is_empty(), "{violations:?}");
+}
+
+#[tokio::test]
+async fn seeded_rows_are_preserved() {
+    let mut conn = replay().await;
+
+    // Migration 4 rebuilt timer_sessions; migration 8 must have put every
+    // value back under its own column.
+    let sessions: Vec<SessionRow> = sqlx::query_as(
+        "SELECT id, task_id, duration, type, planned_duration, session_type, created_at
+             FROM timer_sessions ORDER BY id",
+    )
+    .fetch_all(&mut conn)
+    .await
+    .unwrap();
+    assert_eq!(
+        sessions,
+        [
+            (
+                "session_1".into(),
+                Some("task_1".into()),
+                25,
+                "pomodoro".into(),
+                Some(25),
+                Some("pomodoro".into()),
+                "2024-01-01 09:00:00".into(),
+            ),
+            (
+                "session_2".into(),
+                Some("task_2".into()),
+                5,
+                "short-break".into(),
+                Some(5),
+                Some("pomodoro".into()),
+                "2024-01-02 09:00:00".into(),
+            ),
+            (
+                "session_3".into(),
+                Some("task_1".into()),
+                50,
+                "pomodoro".into(),
+                Some(50),
+                Some("pomodoro".into()),
+                "2024-01-03 09:00:00".into(),
+            ),
+            (
+                "session_4".into(),
+                None,
+                25,
+                "pomodoro".into(),
+                Some(25),
+                Some("pomodoro".into()),
+                "2024-01-04 09:00:00".into(),
+            ),
+        ]
+    );
+
+    let skills: Vec<(String, i64, bool, String)> =
+        sqlx::query_as("SELECT id, current_minutes, is_active, updated_at FROM skills ORDER BY id")
+            .fetch_all(&mut conn)
+            .await
+            .unwrap();
+    assert_eq!(
+        skills,
+        [
+            ("skill_1".into(), 75, true, "2024-01-02 09:00:00".into()),
+            ("skill_2".into(), 0, false, "2024-01-03 08:00:00".into()),
+        ]
+    );
+
+    let tasks: Vec<(String, String, String, i64, i64)> = sqlx::query_as(
+        "SELECT id, status, priority, estimated_pomodoros, total_minutes FROM tasks ORDER BY id",
+    )
+    .fetch_all(&mut conn)
+    .await
+    .unwrap();
+    assert_eq!(
+        tasks,
+        [
+            (
+                "task_1".into(),
+                "in-progress".into(),
+                "medium".into(),
+                1,
+                50
+            ),
+            ("task_2".into(), "done".into(), "medium".into(), 1, 25),
+        ]
+    );
+
+    let (links,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM reflection_skills
+         WHERE reflection_id = 'reflection_1' AND skill_id = 'skill_1'",
+    )
+    .fetch_one(&mut conn)
+    .await
+    .unwrap();
+    assert_eq!(links, 1);
+
+    // Migration 9 rebuilt the days that have completed pomodoros and kept
+    // the rest.
+    let days: Vec<(String, i64, i64)> = sqlx::query_as(
+        "SELECT date, total_minutes, total_sessions FROM daily_activities ORDER BY date",
+    )
+    .fetch_all(&mut conn)
+    .await
+    .unwrap();
+    assert_eq!(
+        days,
+        [
+            ("2024-01-01".into(), 25, 1),
+            ("2024-01-02".into(), 25, 1),
+            ("2024-01-03".into(), 50, 1),
+        ]
+    );
+
+    let (name, daily, weekly): (String, i64, i64) = sqlx::query_as(
+        "SELECT name, daily_goal_minutes, weekly_goal_minutes FROM user_settings WHERE id = 1",
+    )
+    .fetch_one(&mut conn)
+    .await
+    .unwrap();
+    assert_eq!((name.as_str(), daily, weekly), ("User", 240, 420));
+}
+
+async fn skill_minutes(conn: &mut SqliteConnection, id: &str) -> i64 {
+    let (minutes,): (i64,) = sqlx::query_as("SELECT current_minutes FROM skills WHERE id = ?")
+        .bind(id)
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+    minutes
+}
+
+async fn day(conn: &mut SqliteConnection, date: &str) -> Option<(i64, i64)> {
+    sqlx::query_as("SELECT total_minutes, total_sessions FROM daily_activities WHERE date = ?")
+        .bind(date)
+        .fetch_optional(&mut *conn)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn triggers_follow_session_changes() {
+    let mut conn = replay().await;
+    assert_eq!(skill_minutes(&mut conn, "skill_2").await, 0);
+    assert_eq!(day(&mut conn, "2024-01-04").await, None);
+
+    exec(
+        &mut conn,
+        "UPDATE timer_sessions SET completed = 1 WHERE id = 'session_4'",
+    )
+    .await;
+    assert_eq!(skill_minutes(&mut conn, "skill_2").await, 25);
+    assert_eq!(day(&mut conn, "2024-01-04").await, Some((25, 1)));
+
+    // Days follow `local_date`, which the app restamps with the start time.
+    exec(
+        &mut conn,
+        "UPDATE timer_sessions SET duration = 40, start_time = '2024-01-05T09:00:00.000Z',
+            local_date = '2024-01-05'
+         WHERE id = 'session_4'",
+    )
+    .await;
+    assert_eq!(skill_minutes(&mut conn, "skill_2").await, 40);
+    assert_eq!(day(&mut conn, "2024-01-04").await, None);
+    assert_eq!(day(&mut conn, "2024-01-05").await, Some((40, 1)));
+
+    exec(
+        &mut conn,
+        "INSERT INTO timer_sessions (id, skill_id, start_time, duration, type, completed)
+         VALUES ('session_5', 'skill_2', '2024-01-05T10:00:00.000Z', 5, 'short-break', 1),
+                ('session_6', 'skill_2', '2024-01-05T11:00:00.000Z', 25, 'pomodoro', 1)",
+    )
+    .await;
+    assert_eq!(skill_minutes(&mut conn, "skill_2").await, 65);
+    assert_eq!(day(&mut conn, "2024-01-05").await, Some((65, 2)));
+
+    exec(
+        &mut conn,
+        "DELETE FROM timer_sessions WHERE id IN ('session_4', 'session_5')",
+    )
+    .await;
+    assert_eq!(skill_minutes(&mut conn, "skill_2").await, 25);
+    assert_eq!(day(&mut conn, "2024-01-05").await, Some((25, 1)));
+}
+
+#[tokio::test]
+async fn changing_the_time_zone_moves_days() {
+    let pool = memory_pool().await;
+    migrate(&pool).await.unwrap();
+    // 22:30 UTC either side of the switch to summer time in Berlin.
+    sqlx::raw_sql(
+        "INSERT INTO skills (id, name) VALUES ('skill_1', 'Piano');
+         INSERT INTO timer_sessions (id, skill_id, start_time, end_time, duration, type, completed)
+         VALUES ('winter', 'skill_1', '2024-03-30T22:30:00.000Z', '2024-03-30T22:55:00.000Z', 25, 'pomodoro', 1),
+                ('summer', 'skill_1', '2024-03-31T22:30:00.000Z', '2024-03-31T22:55:00.000Z', 30, 'pomodoro', 1);",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    let days = || async {
+        sqlx::query_as::<_, (String, i64)>(
+            "SELECT date, total_minutes FROM daily_activities ORDER BY date",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap()
+    };
+
+    assert_eq!(timezone::set(&pool, "UTC").await.unwrap(), 2);
+    assert_eq!(
+        days().await,
+        [("2024-03-30".into(), 25), ("2024-03-31".into(), 30)]
+    );
+
+    assert_eq!(timezone::set(&pool, "Europe/Berlin").await.unwrap(), 2);
+    assert_eq!(
+        days().await,
+        [("2024-03-30".into(), 25), ("2024-04-01".into(), 30)]
+    );
+    let (hour,): (i64,) =
+        sqlx::query_as("SELECT local_hour FROM timer_sessions WHERE id = 'summer'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(hour, 0);
+    // Moving days leaves the skill total alone.
+    let (minutes,): (i64,) = sqlx::query_as("SELECT current_minutes FROM skills")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(minutes, 55);
+
+    assert!(timezone::set(&pool, "Europe/Atlantis").await.is_err());
+    assert_eq!(timezone::rebucket(&pool).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn late_sessions_count_for_the_day_before() {
+    let pool = memory_pool().await;
+    migrate(&pool).await.unwrap();
+    sqlx::raw_sql(
+        "INSERT INTO skills (id, name) VALUES ('skill_1', 'Piano');
+         INSERT INTO timer_sessions (id, skill_id, start_time, duration, type, completed)
+         VALUES ('friday', 'skill_1', '2024-03-01T20:00:00.000Z', 25, 'pomodoro', 1),
+                ('after_midnight', 'skill_1', '2024-03-03T01:00:00.000Z', 25, 'pomodoro', 1),
+                ('sunday', 'skill_1', '2024-03-03T20:00:00.000Z', 25, 'pomodoro', 1);",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    timezone::set(&pool, "UTC").await.unwrap();
+    let dates = || async {
+        sqlx::query_as::<_, (String,)>("SELECT date FROM daily_activities ORDER BY date")
+            .fetch_all(&pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(date,)| date)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(dates().await, ["2024-03-01", "2024-03-03"]);
+    let history = achievements::History::load(&pool).await.unwrap();
+    assert_eq!(history.longest_streak, 1);
+
+    assert_eq!(timezone::set_day_start(&pool, 4).await.unwrap(), 1);
+    assert_eq!(dates().await, ["2024-03-01", "2024-03-02", "2024-03-03"]);
+    let history = achievements::History::load(&pool).await.unwrap();
+    assert_eq!(history.longest_streak, 3);
+
+    assert!(timezone::set_day_start(&pool, 24).await.is_err());
+
+    // Through the settings form, the row and the re-bucketing go together.
+    let update = |day_start_hour, timezone: &str| UpdateSettingsInput {
+        day_start_hour: Some(day_start_hour),
+        timezone: Some(timezone.into()),
+        ..Default::default()
+    };
+    assert!(settings::update(&pool, update(0, "Europe/Atlantis"))
+        .await
+        .is_err());
+    assert_eq!(settings::get(&pool).await.unwrap().day_start_hour, 4);
+    assert_eq!(dates().await, ["2024-03-01", "2024-03-02", "2024-03-03"]);
+    let updated = settings::update(&pool, update(0, "UTC")).await.unwrap();
+    assert_eq!(updated.day_start_hour, 0);
+    assert_eq!(dates().await, ["2024-03-01", "2024-03-03"]);
+}
+
+#[tokio::test]
+async fn recompute_matches_triggers() {
+    let pool = memory_pool().await;
+    migrate(&pool).await.unwrap();
+    sqlx::raw_sql(
+        "INSERT INTO skills (id, name) VALUES ('skill_1', 'Piano');
+         INSERT INTO timer_sessions (id, skill_id, start_time, duration, type, completed)
+         VALUES ('session_1', 'skill_1', '2024-02-01T09:00:00.000Z', 25, 'pomodoro', 1),
+                ('session_2', 'skill_1', '2024-02-01T10:00:00.000Z', 5, 'short-break', 1),
+                ('session_3', 'skill_1', '2024-02-02T09:00:00.000Z', 30, 'pomodoro', 0);
+         UPDATE timer_sessions SET completed = 1, duration = 20 WHERE id = 'session_3';",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let snapshot = || async {
+        let skills: Vec<(String, i64)> =
+            sqlx::query_as("SELECT id, current_minutes FROM skills ORDER BY id")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        let days: Vec<(String, i64, i64)> =
+            sqlx::query_as("SELECT * FROM daily_activities ORDER BY date")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        (skills, days)
+    };
+
+    let from_triggers = snapshot().await;
+    assert_eq!(from_triggers.0, [("skill_1".into(), 45)]);
+
+    // Imported history: minutes and a day without sessions behind them.
+    sqlx::raw_sql(
+        "UPDATE skills SET current_minutes = current_minutes + 600;
+         INSERT INTO daily_activities VALUES ('2024-01-01', 600, 0);",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    let imported = snapshot().await;
+
+    // Drift a day with sessions, then rebuild it from them.
+    sqlx::raw_sql("UPDATE daily_activities SET total_minutes = 999 WHERE date = '2024-02-01'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    activities::recompute(&pool).await.unwrap();
+    assert_eq!(snapshot().await, imported);
+    assert_eq!(imported.1.len(), from_triggers.1.len() + 1);
+}
+
+#[tokio::test]
+async fn corrections_count_for_minutes_only() {
+    let pool = memory_pool().await;
+    migrate(&pool).await.unwrap();
+    timezone::set(&pool, "UTC").await.unwrap();
+    sqlx::raw_sql("INSERT INTO skills (id, name) VALUES ('skill_1', 'Piano')")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    sessions::record_manual(&pool, "skill_1", None, 30)
+        .await
+        .unwrap();
+    let correction = sessions::record_manual(&pool, "skill_1", None, -10)
+        .await
+        .unwrap();
+    assert_eq!(correction.kind, "adjustment");
+    assert!(sessions::record_manual(&pool, "skill_1", None, 0)
+        .await
+        .is_err());
+
+    let totals = || async {
+        let (minutes,): (i64,) =
+            sqlx::query_as("SELECT current_minutes FROM skills WHERE id = 'skill_1'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        let (day_minutes, sessions): (i64, i64) =
+            sqlx::query_as("SELECT total_minutes, total_sessions FROM daily_activities")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        (minutes, day_minutes, sessions)
+    };
+    assert_eq!(totals().await, (20, 20, 1));
+    let (today,): (String,) = sqlx::query_as("SELECT local_date FROM timer_sessions WHERE id = ?")
+        .bind(&correction.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    // The dashboard and reports see the same 20 minutes from one session.
+    assert_eq!(
+        sessions::skill_minutes_on(&pool, "skill_1", &today)
+            .await
+            .unwrap(),
+        20
+    );
+    let by_day = sessions::minutes_by_day(&pool, 1).await.unwrap();
+    assert_eq!(by_day[0].total_minutes, 20);
+    let week = analytics::weekly_stats(&pool, 1, None).await.unwrap();
+    assert_eq!((week[0].total_minutes, week[0].total_sessions), (20, 1));
+
+    activities::recompute(&pool).await.unwrap();
+    assert_eq!(totals().await, (20, 20, 1));
+}
+
+#[tokio::test]
+async fn achievements_follow_history() {
+    let pool = memory_pool().await;
+    migrate(&pool).await.unwrap();
+    sqlx::raw_sql(
+        "INSERT INTO skills (id, name) VALUES ('skill_1', 'Piano');
+         INSERT INTO timer_sessions (id, skill_id, start_time, duration, type, completed)
+         VALUES ('session_1', 'skill_1', '2024-02-01T12:00:00.000Z', 40, 'pomodoro', 1),
+                ('session_2', 'skill_1', '2024-02-02T12:00:00.000Z', 20, 'pomodoro', 1);",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let unlocked = achievements::evaluate(&pool, false).await.unwrap();
+    let mut kinds: Vec<&str> = unlocked.iter().map(|a| a.kind.as_str()).collect();
+    kinds.sort();
+    assert_eq!(kinds, ["first_hour", "first_skill"]);
+    let (progress,): (i64,) =
+        sqlx::query_as("SELECT progress FROM achievements WHERE type = 'streak_7_days'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(progress, 2);
+
+    // Nothing new to announce the second time round.
+    assert!(achievements::evaluate(&pool, false)
+        .await
+        .unwrap()
+        .is_empty());
+
+    sqlx::query("DELETE FROM timer_sessions WHERE id = 'session_2'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let unlocked_at = || async {
+        let (at,): (Option<String>,) =
+            sqlx::query_as("SELECT unlocked_at FROM achievements WHERE type = 'first_hour'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        at
+    };
+    achievements::evaluate(&pool, false).await.unwrap();
+    assert!(unlocked_at().await.is_some());
+    achievements::evaluate(&pool, true).await.unwrap();
+    assert!(unlocked_at().await.is_none());
+}
+
+#[tokio::test]
+async fn custom_rules_are_evaluated() {
+    let pool = memory_pool().await;
+    migrate(&pool).await.unwrap();
+    sqlx::raw_sql(
+        "INSERT INTO skills (id, name) VALUES ('skill_1', 'Guitar'), ('skill_2', 'Piano');
+         INSERT INTO timer_sessions (id, skill_id, start_time, duration, type, completed)
+         VALUES ('session_1', 'skill_1', '2024-02-01T12:00:00.000Z', 90, 'pomodoro', 1),
+                ('session_2', 'skill_1', '2024-02-20T12:00:00.000Z', 40, 'pomodoro', 1),
+                ('session_3', 'skill_2', '2024-02-20T13:00:00.000Z', 600, 'pomodoro', 1),
+                ('session_4', 'skill_1', '2024-03-01T12:00:00.000Z', 100, 'pomodoro', 1);",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    timezone::set(&pool, "UTC").await.unwrap();
+
+    let input = |skill: &str, threshold: i64| {
+        serde_json::from_value::<CreateAchievementInput>(serde_json::json!({
+            "name": "Monthly practice",
+            "rule": { "metric": "hours", "skillId": skill, "window": "month", "threshold": threshold },
+        }))
+        .unwrap()
+    };
+    let reached = repo::create(&pool, input("skill_1", 2)).await.unwrap();
+    let missed = repo::create(&pool, input("skill_1", 3)).await.unwrap();
+    assert!(reached.kind.starts_with(repo::CUSTOM_PREFIX));
+    assert_eq!(reached.target, 2);
+    assert!(repo::create(&pool, input("skill_9", 1)).await.is_err());
+
+    let unlocked = achievements::evaluate(&pool, false).await.unwrap();
+    assert!(unlocked.iter().any(|a| a.id == reached.id));
+    assert!(!unlocked.iter().any(|a| a.id == missed.id));
+    let (progress,): (i64,) = sqlx::query_as("SELECT progress FROM achievements WHERE id = ?")
+        .bind(&missed.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(progress, 2);
+
+    repo::delete(&pool, &reached.id).await.unwrap();
+    assert!(repo::delete(&pool, "ach_first_hour").await.is_err());
+}
+
+#[tokio::test]
+async fn only_one_session_runs_at_a_time() {
+    let pool = memory_pool().await;
+    migrate(&pool).await.unwrap();
+    sqlx::query("INSERT INTO skills (id, name) VALUES ('skill_1', 'Piano')")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let new = |owner, id| NewSession {
+        owner,
+        id,
+        task_id: None,
+        skill_id: "skill_1",
+        start_time: "2024-02-01T09:00:00.000Z",
+        planned_minutes: 25,
+        kind: "pomodoro",
+    };
+    sessions::start(&pool, &new(Owner::Cli, "session_1"))
+        .await
+        .unwrap();
+    let err = sessions::start(&pool, &new(Owner::App, "session_2"))
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "timer is already running in the tenk CLI");
+    assert!(sessions::get(&pool, "session_2").await.unwrap().is_none());
+
+    let running = sessions::running(&pool).await.unwrap().unwrap();
+    assert_eq!(
+        (running.owner, running.session.id.as_str()),
+        (Owner::Cli, "session_1")
+    );
+
+    // The app only drops its own leftover claims.
+    sessions::release(&pool, Owner::App).await.unwrap();
+    assert!(sessions::running(&pool).await.unwrap().is_some());
+
+    sessions::complete(&pool, "session_1", 25, true)
+        .await
+        .unwrap();
+    assert!(sessions::running(&pool).await.unwrap().is_none());
+    sessions::start(&pool, &new(Owner::App, "session_2"))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn migrator_builds_the_replayed_schema() {
+    let pool = memory_pool().await;
+    migrate(&pool).await.unwrap();
+    // Running again must be a no-op, as on every app start.
+    migrate(&pool).await.unwrap();
+
+    assert!(schema::check(&pool).await.unwrap().is_empty());
+
+    let mut migrated = pool.acquire().await.unwrap();
+    let mut replayed = replay().await;
+    assert_eq!(
+        schema_sql(&mut migrated).await,
+        schema_sql(&mut replayed).await
+    );
+}
+
+#[tokio::test]
+async fn down_migrations_restore_each_earlier_schema() {
+    let latest = get_migrations().iter().map(|m| m.version).max().unwrap();
+    for version in (MIN_REVERT_VERSION..latest).rev() {
+        let pool = memory_pool().await;
+        migrate(&pool).await.unwrap();
+        revert(&pool, version).await.unwrap();
+
+        let mut reverted = pool.acquire().await.unwrap();
+        let mut replayed = replay_to(version).await;
+        assert_eq!(
+            schema_shape(&mut reverted).await,
+            schema_shape(&mut replayed).await,
+            "version {version}"
+        );
+        let (applied,): (i64,) = sqlx::query_as("SELECT MAX(version) FROM _sqlx_migrations")
+            .fetch_one(&mut *reverted)
+            .await
+            .unwrap();
+        assert_eq!(applied, version);
+        drop(reverted);
+
+        // And forward again to the current schema.
+        migrate(&pool).await.unwrap();
+        assert!(schema::check(&pool).await.unwrap().is_empty());
+    }
+}
+
+#[tokio::test]
+async fn downgrade_keeps_what_the_older_schema_can_hold() {
+    let pool = memory_pool().await;
+    migrate(&pool).await.unwrap();
+    sqlx::raw_sql(
+        "INSERT INTO skills (id, name) VALUES ('skill_1', 'Piano');
+         INSERT INTO tasks (id, skill_id, title) VALUES ('task_1', 'skill_1', 'Scales');
+         INSERT INTO timer_sessions (id, task_id, skill_id, start_time, duration, type, completed, planned_duration, session_type)
+         VALUES ('session_1', 'task_1', 'skill_1', '2024-03-01T09:00:00.000Z', 25, 'pomodoro', 1, 25, 'pomodoro'),
+                ('session_2', NULL, 'skill_1', '2024-03-01T10:00:00.000Z', 25, 'pomodoro', 1, 25, 'pomodoro');
+         INSERT INTO achievements (id, type, name, description, icon, target, rule)
+         VALUES ('ach_custom', 'custom_1', 'Custom', 'Mine', 'Star', 1, '{}');",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    assert!(revert(&pool, 1).await.is_err());
+    let latest = get_migrations().iter().map(|m| m.version).max().unwrap();
+    assert!(revert(&pool, latest + 1).await.is_err());
+
+    revert(&pool, 3).await.unwrap();
+    let sessions: Vec<(String, String, i64)> =
+        sqlx::query_as("SELECT id, task_id, planned_duration FROM timer_sessions ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert_eq!(sessions, [("session_1".into(), "task_1".into(), 25)]);
+    let (custom,): (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM achievements WHERE id = 'ach_custom'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(custom, 0);
+    // The triggers kept these totals and they outlive the triggers.
+    let (minutes,): (i64,) = sqlx::query_as("SELECT current_minutes FROM skills")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(minutes, 50);
+
+    migrate(&pool).await.unwrap();
+    assert!(schema::check(&pool).await.unwrap().is_empty());
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM timer_sessions")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+}
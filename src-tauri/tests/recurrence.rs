@@ -0,0 +1,137 @@
+//! Instances of recurring tasks, created on completion and by the schedule.
+
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::SqlitePool;
+use ten_k_hours_app_lib::database::migrate;
+use ten_k_hours_app_lib::recurrence;
+use ten_k_hours_app_lib::repository::tasks::{self, CreateTaskInput, TaskRecord};
+use ten_k_hours_app_lib::timezone;
+
+async fn memory_pool() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(SqliteConnectOptions::new().in_memory(true))
+        .await
+        .unwrap();
+    migrate(&pool).await.unwrap();
+    timezone::set(&pool, "UTC").await.unwrap();
+    sqlx::query("INSERT INTO skills (id, name) VALUES ('skill_1', 'Piano')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    pool
+}
+
+async fn create(pool: &SqlitePool, due_date: &str, recurrence: &str) -> TaskRecord {
+    tasks::create(
+        pool,
+        CreateTaskInput {
+            skill_id: "skill_1".into(),
+            title: "Scales".into(),
+            description: None,
+            status: None,
+            priority: None,
+            due_date: Some(due_date.into()),
+            estimated_pomodoros: Some(2),
+            recurrence: Some(recurrence.into()),
+        },
+    )
+    .await
+    .unwrap()
+}
+
+fn dues(series: &[TaskRecord]) -> Vec<Option<&str>> {
+    series.iter().map(|task| task.due_date.as_deref()).collect()
+}
+
+#[tokio::test]
+async fn finishing_an_instance_adds_the_next() {
+    let pool = memory_pool().await;
+    // A Monday, far enough ahead that today does not move it.
+    let first = create(&pool, "2030-03-04", "rrule:FREQ=WEEKLY;BYDAY=TH,MO").await;
+    assert_eq!(first.recurrence.as_deref(), Some("FREQ=WEEKLY;BYDAY=MO,TH"));
+    assert_eq!(first.series_id.as_deref(), Some(first.id.as_str()));
+
+    tasks::set_status(&pool, &first.id, "done").await.unwrap();
+    // Finishing it again does not add a second one.
+    tasks::set_status(&pool, &first.id, "done").await.unwrap();
+    let series = tasks::series(&pool, &first.id).await.unwrap();
+    assert_eq!(dues(&series), [Some("2030-03-04"), Some("2030-03-07")]);
+    let second = &series[1];
+    assert_eq!(
+        (second.status.as_str(), second.estimated_pomodoros),
+        ("todo", 2)
+    );
+    assert_eq!(second.previous_id.as_deref(), Some(first.id.as_str()));
+
+    tasks::set_status(&pool, &second.id, "done").await.unwrap();
+    let series = tasks::series(&pool, &first.id).await.unwrap();
+    assert_eq!(
+        dues(&series),
+        [Some("2030-03-04"), Some("2030-03-07"), Some("2030-03-11")]
+    );
+
+    // Deleting the middle one keeps the chain.
+    tasks::delete(&pool, &second.id).await.unwrap();
+    let series = tasks::series(&pool, &first.id).await.unwrap();
+    assert_eq!(series[1].previous_id.as_deref(), Some(first.id.as_str()));
+}
+
+#[tokio::test]
+async fn overdue_series_catch_up_to_today() {
+    let pool = memory_pool().await;
+    let missed = create(&pool, "2020-01-01", "FREQ=DAILY").await;
+    // Finished late, so its next one is already after today.
+    let finished = create(&pool, "2020-01-01", "FREQ=DAILY").await;
+    tasks::set_status(&pool, &finished.id, "done")
+        .await
+        .unwrap();
+    let used_up = create(&pool, "2020-01-01", "FREQ=DAILY;COUNT=1").await;
+
+    let added = recurrence::catch_up(&pool).await.unwrap();
+    assert_eq!(added.len(), 1);
+    let series = tasks::series(&pool, &missed.id).await.unwrap();
+    let today = timezone::load(&pool).await.unwrap().today().to_string();
+    assert_eq!(dues(&series), [Some("2020-01-01"), Some(today.as_str())]);
+    assert_eq!(tasks::series(&pool, &used_up.id).await.unwrap().len(), 1);
+
+    // The series is up to date now.
+    assert!(recurrence::catch_up(&pool).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn rules_are_checked_and_can_be_cleared() {
+    let pool = memory_pool().await;
+    let err = tasks::create(
+        &pool,
+        CreateTaskInput {
+            skill_id: "skill_1".into(),
+            title: "Katas".into(),
+            description: None,
+            status: None,
+            priority: None,
+            due_date: None,
+            estimated_pomodoros: None,
+            recurrence: Some("FREQ=SECONDLY".into()),
+        },
+    )
+    .await;
+    assert!(err.is_err());
+
+    let task = create(&pool, "2030-03-04", "FREQ=DAILY").await;
+    let cleared = tasks::update(
+        &pool,
+        tasks::UpdateTaskInput {
+            id: task.id.clone(),
+            recurrence: Some(String::new()),
+            status: Some("done".into()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(cleared.recurrence, None);
+    assert_eq!(tasks::series(&pool, &task.id).await.unwrap().len(), 1);
+}
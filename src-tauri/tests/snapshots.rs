@@ -0,0 +1,209 @@
+//! Snapshots of a real database file through the SQLite backup API.
+
+use std::path::PathBuf;
+
+use tauri_plugin_sql::{Migration, MigrationKind};
+use ten_k_hours_app_lib::database::{self, migrate_safely, pending_migration};
+use ten_k_hours_app_lib::error::Error;
+use ten_k_hours_app_lib::snapshot;
+
+fn scratch_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tenk-{}", uuid::Uuid::new_v4().simple()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+async fn skill_names(pool: &sqlx::SqlitePool) -> Vec<String> {
+    sqlx::query_scalar("SELECT name FROM skills ORDER BY id")
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn snapshot_restores_earlier_contents() {
+    let root = scratch_dir();
+    let db_path = root.join(database::DB_FILE);
+    let dir = snapshot::dir(&db_path);
+    let pool = database::open(&db_path).await.unwrap();
+    // A brand new database has nothing worth a snapshot.
+    assert!(snapshot::list(&dir).unwrap().is_empty());
+
+    sqlx::query("INSERT INTO skills (id, name) VALUES ('skill_1', 'Piano')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let taken = snapshot::take(&pool, &dir, "manual").await.unwrap();
+    assert_eq!(snapshot::list(&dir).unwrap(), std::slice::from_ref(&taken));
+    assert!(taken.size > 0);
+
+    sqlx::raw_sql(
+        "UPDATE skills SET name = 'Harpsichord';
+         INSERT INTO skills (id, name) VALUES ('skill_2', 'Go');",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    snapshot::restore(&pool, &dir, &taken.name).await.unwrap();
+    assert_eq!(skill_names(&pool).await, ["Piano"]);
+
+    // The state before the restore was kept as well.
+    let reasons: Vec<String> = snapshot::list(&dir)
+        .unwrap()
+        .into_iter()
+        .map(|s| s.reason)
+        .collect();
+    assert!(
+        reasons.contains(&"before-restore".to_owned()),
+        "{reasons:?}"
+    );
+
+    assert!(snapshot::restore(&pool, &dir, "../app.db").await.is_err());
+    pool.close().await;
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[tokio::test]
+async fn pending_migrations_are_detected() {
+    let root = scratch_dir();
+    let db_path = root.join(database::DB_FILE);
+    let pool = database::open(&db_path).await.unwrap();
+    assert_eq!(
+        pending_migration(&pool, &database::get_migrations())
+            .await
+            .unwrap(),
+        None
+    );
+
+    let latest = database::get_migrations()
+        .iter()
+        .map(|m| m.version)
+        .max()
+        .unwrap();
+    sqlx::query("DELETE FROM _sqlx_migrations WHERE version = ?")
+        .bind(latest)
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        pending_migration(&pool, &database::get_migrations())
+            .await
+            .unwrap(),
+        Some(latest)
+    );
+    pool.close().await;
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[tokio::test]
+async fn failed_migration_restores_its_own_snapshot() {
+    let root = scratch_dir();
+    let db_path = root.join(database::DB_FILE);
+    let pool = database::open(&db_path).await.unwrap();
+    sqlx::query("INSERT INTO skills (id, name) VALUES ('skill_1', 'Piano')")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // One version that succeeds, then one that fails halfway through.
+    let latest = database::get_migrations()
+        .iter()
+        .map(|m| m.version)
+        .max()
+        .unwrap();
+    let mut migrations = database::get_migrations();
+    migrations.push(Migration {
+        version: latest + 1,
+        description: "rename_everything",
+        sql: "UPDATE skills SET name = 'Renamed';",
+        kind: MigrationKind::Up,
+    });
+    migrations.push(Migration {
+        version: latest + 2,
+        description: "broken",
+        sql: "DELETE FROM skills; INSERT INTO no_such_table VALUES (1);",
+        kind: MigrationKind::Up,
+    });
+
+    let Err(Error::Migration(failure)) = migrate_safely(&pool, &db_path, migrations).await else {
+        panic!("the broken migration should fail");
+    };
+    assert_eq!(failure.version, Some(latest + 2));
+    assert_eq!(failure.description.as_deref(), Some("broken"));
+    assert!(
+        failure.message.contains("no_such_table"),
+        "{}",
+        failure.message
+    );
+    assert!(failure.restored);
+    let snapshot = failure.snapshot.as_ref().unwrap();
+    assert!(snapshot.exists());
+    assert!(snapshot
+        .to_string_lossy()
+        .ends_with(&format!("-before-v{}.db", latest + 2)));
+
+    // Only the broken version is undone; the one before it stays applied.
+    assert_eq!(skill_names(&pool).await, ["Renamed"]);
+    let applied: i64 = sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(applied, latest + 1);
+    let reasons: Vec<String> = snapshot::list(&snapshot::dir(&db_path))
+        .unwrap()
+        .into_iter()
+        .map(|s| s.reason)
+        .collect();
+    assert!(
+        reasons.contains(&format!("before-v{}", latest + 1)),
+        "{reasons:?}"
+    );
+
+    // The database is now ahead of this build, which refuses to open it.
+    let Err(Error::Migration(failure)) =
+        migrate_safely(&pool, &db_path, database::get_migrations()).await
+    else {
+        panic!("a database from a newer app should be refused");
+    };
+    assert!(failure.from_newer_app);
+    assert_eq!(failure.version, Some(latest + 1));
+    assert_eq!(skill_names(&pool).await, ["Renamed"]);
+    pool.close().await;
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[tokio::test]
+async fn snapshot_fails_while_the_database_stays_locked() {
+    use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+    use sqlx::{ConnectOptions, Connection};
+
+    let root = scratch_dir();
+    let db_path = root.join(database::DB_FILE);
+    let dir = snapshot::dir(&db_path);
+    // With a rollback journal a writer shuts readers out, which WAL never does.
+    let options = SqliteConnectOptions::new()
+        .filename(&db_path)
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Delete);
+    let pool = SqlitePoolOptions::new()
+        .connect_with(options.clone())
+        .await
+        .unwrap();
+    database::migrate(&pool).await.unwrap();
+
+    let mut writer = options.connect().await.unwrap();
+    sqlx::raw_sql("BEGIN EXCLUSIVE; INSERT INTO skills (id, name) VALUES ('skill_1', 'Piano');")
+        .execute(&mut writer)
+        .await
+        .unwrap();
+
+    // Gives up after the wait rather than keeping a partial copy.
+    assert!(snapshot::take(&pool, &dir, "manual").await.is_err());
+    // No half-copied file is left to be mistaken for a snapshot.
+    assert!(snapshot::list(&dir).unwrap().is_empty());
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+    writer.close().await.unwrap();
+    pool.close().await;
+    std::fs::remove_dir_all(root).unwrap();
+}
@@ -0,0 +1,163 @@
+//! Sub-skills, and time on them rolling up into every skill above.
+
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::SqlitePool;
+use ten_k_hours_app_lib::achievements::rules::{Metric, Rule, Window};
+use ten_k_hours_app_lib::achievements::History;
+use ten_k_hours_app_lib::database::migrate;
+use ten_k_hours_app_lib::repository::skills;
+use ten_k_hours_app_lib::repository::subskills;
+use ten_k_hours_app_lib::{analytics, projection, timezone};
+
+async fn memory_pool() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(SqliteConnectOptions::new().in_memory(true))
+        .await
+        .unwrap();
+    migrate(&pool).await.unwrap();
+    pool
+}
+
+/// Music > Piano > Jazz voicings, with Guitar beside Piano. Jazz voicings
+/// was practised yesterday and today, Piano itself two days ago and Guitar
+/// today.
+async fn music_pool() -> SqlitePool {
+    let pool = memory_pool().await;
+    sqlx::raw_sql(
+        "INSERT INTO skills (id, name, goal_hours, created_at)
+         VALUES ('music', 'Music', 100, '2024-01-01'), ('piano', 'Piano', 10, '2024-01-02'),
+                ('jazz', 'Jazz voicings', 2, '2024-01-03'), ('guitar', 'Guitar', 10, '2024-01-04');
+         INSERT INTO timer_sessions (id, skill_id, start_time, duration, type, completed)
+         VALUES ('s1', 'jazz', strftime('%Y-%m-%dT09:00:00.000Z', 'now', '-1 day'), 60, 'pomodoro', 1),
+                ('s2', 'jazz', strftime('%Y-%m-%dT09:00:00.000Z', 'now'), 30, 'pomodoro', 1),
+                ('s3', 'piano', strftime('%Y-%m-%dT09:00:00.000Z', 'now', '-2 days'), 30, 'pomodoro', 1),
+                ('s4', 'guitar', strftime('%Y-%m-%dT10:00:00.000Z', 'now'), 120, 'pomodoro', 1);",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    // Stamps the local dates the raw inserts left out.
+    timezone::set(&pool, "UTC").await.unwrap();
+    for (skill, parent) in [("piano", "music"), ("jazz", "piano"), ("guitar", "music")] {
+        subskills::set_parent(&pool, skill, Some(parent))
+            .await
+            .unwrap();
+    }
+    pool
+}
+
+#[tokio::test]
+async fn time_rolls_up_against_each_goal() {
+    let pool = music_pool().await;
+
+    let rollup = |skill: &'static str| {
+        let pool = pool.clone();
+        async move {
+            let rollup = subskills::rollup(&pool, skill).await.unwrap();
+            (
+                rollup.own_minutes,
+                rollup.total_minutes,
+                rollup.subskills,
+                rollup.progress_percentage,
+            )
+        }
+    };
+    assert_eq!(rollup("music").await, (0, 240, 3, 4.0));
+    assert_eq!(rollup("piano").await, (30, 120, 1, 20.0));
+    assert_eq!(rollup("jazz").await, (90, 90, 0, 75.0));
+
+    let projections = projection::projections(&pool, None).await.unwrap();
+    let piano = projections.iter().find(|p| p.skill_id == "piano").unwrap();
+    assert_eq!((piano.current_minutes, piano.goal_minutes), (120, 600));
+    assert_eq!(piano.paces[0].minutes_per_day, 40.0);
+
+    let progress = analytics::skill_progress(&pool, 7, None).await.unwrap();
+    let hours = |id: &str| {
+        let skill = progress.iter().find(|skill| skill.skill_id == id);
+        skill.unwrap().data.last().map(|point| point.hours)
+    };
+    assert_eq!((hours("music"), hours("piano")), (Some(4.0), Some(2.0)));
+
+    // The weekly breakdown still counts each session once.
+    let weeks = analytics::weekly_stats(&pool, 2, None).await.unwrap();
+    let minutes: i64 = weeks.iter().map(|week| week.total_minutes).sum();
+    assert_eq!(minutes, 240);
+
+    // Moving Piano out takes its sub-skill with it.
+    subskills::set_parent(&pool, "piano", None).await.unwrap();
+    assert_eq!(rollup("music").await, (0, 120, 1, 2.0));
+    assert_eq!(subskills::children(&pool, "piano").await.unwrap().len(), 1);
+    // Deleting a parent leaves its sub-skills at the top level.
+    skills::delete(&pool, "piano").await.unwrap();
+    assert_eq!(subskills::list(&pool).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn loops_are_refused() {
+    let pool = music_pool().await;
+
+    assert!(subskills::set_parent(&pool, "music", Some("music"))
+        .await
+        .is_err());
+    assert!(subskills::set_parent(&pool, "music", Some("jazz"))
+        .await
+        .is_err());
+    assert!(subskills::set_parent(&pool, "jazz", Some("missing"))
+        .await
+        .is_err());
+    // Moving within the tree is fine.
+    subskills::set_parent(&pool, "jazz", Some("guitar"))
+        .await
+        .unwrap();
+    assert_eq!(
+        subskills::rollup(&pool, "guitar")
+            .await
+            .unwrap()
+            .total_minutes,
+        210
+    );
+}
+
+#[tokio::test]
+async fn streaks_and_achievements_see_sub_skills() {
+    let pool = music_pool().await;
+
+    let streak = |skill: &'static str| {
+        let pool = pool.clone();
+        async move {
+            let streaks = analytics::practice_streaks(&pool, None, Some(skill))
+                .await
+                .unwrap();
+            (streaks.current, streaks.longest)
+        }
+    };
+    assert_eq!(streak("music").await, (3, 3));
+    assert_eq!(streak("piano").await, (3, 3));
+    assert_eq!(streak("jazz").await, (2, 2));
+    assert_eq!(streak("guitar").await, (1, 1));
+
+    // Music has the most time once its sub-skills count.
+    let history = History::load(&pool).await.unwrap();
+    assert_eq!(
+        (history.total_minutes, history.best_skill_minutes),
+        (240, 240)
+    );
+
+    let rule = Rule {
+        metric: Metric::Minutes,
+        skill_id: Some("piano".into()),
+        window: Window::AllTime,
+        threshold: 100,
+    };
+    let minutes: i64 = rule
+        .days(&pool)
+        .await
+        .unwrap()
+        .iter()
+        .map(|day| day.minutes)
+        .sum();
+    assert_eq!(minutes, 120);
+}
@@ -0,0 +1,166 @@
+//! Subtask rollups, "blocked by" links and the loops both refuse.
+
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::SqlitePool;
+use ten_k_hours_app_lib::database::migrate;
+use ten_k_hours_app_lib::repository::subtasks::{self, Rollup};
+use ten_k_hours_app_lib::repository::{dependencies, tasks};
+
+async fn memory_pool() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(SqliteConnectOptions::new().in_memory(true))
+        .await
+        .unwrap();
+    migrate(&pool).await.unwrap();
+    pool
+}
+
+/// A recital with two pieces, one of them split into sections, and a task
+/// outside the tree.
+async fn recital_pool() -> SqlitePool {
+    let pool = memory_pool().await;
+    sqlx::raw_sql(
+        "INSERT INTO skills (id, name) VALUES ('skill_1', 'Piano');
+         INSERT INTO tasks (id, skill_id, title, status, estimated_pomodoros, pomodoro_sessions, total_minutes, order_index)
+         VALUES ('recital', 'skill_1', 'Recital', 'todo', 1, 0, 0, 0),
+                ('bach', 'skill_1', 'Bach', 'in-progress', 4, 2, 50, 1),
+                ('chopin', 'skill_1', 'Chopin', 'todo', 2, 1, 25, 2),
+                ('chopin_a', 'skill_1', 'Chopin, part A', 'done', 3, 3, 75, 3),
+                ('chopin_b', 'skill_1', 'Chopin, part B', 'todo', 3, 0, 0, 4),
+                ('tuning', 'skill_1', 'Book a tuner', 'todo', 1, 0, 0, 5);",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    for (task, parent) in [
+        ("bach", "recital"),
+        ("chopin", "recital"),
+        ("chopin_a", "chopin"),
+        ("chopin_b", "chopin"),
+    ] {
+        subtasks::set_parent(&pool, task, Some(parent))
+            .await
+            .unwrap();
+    }
+    pool
+}
+
+async fn unblocked(pool: &SqlitePool) -> Vec<String> {
+    dependencies::unblocked(pool, Some("skill_1"))
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|task| task.id)
+        .collect()
+}
+
+#[tokio::test]
+async fn totals_roll_up_to_every_ancestor() {
+    let pool = recital_pool().await;
+
+    assert_eq!(
+        subtasks::rollup(&pool, "recital").await.unwrap(),
+        Rollup {
+            task_id: "recital".into(),
+            estimated_pomodoros: 13,
+            pomodoro_sessions: 6,
+            total_minutes: 150,
+            subtasks: 4,
+            done_subtasks: 1,
+        }
+    );
+    let chopin = subtasks::rollup(&pool, "chopin").await.unwrap();
+    assert_eq!((chopin.total_minutes, chopin.subtasks), (100, 2));
+    let tuning = subtasks::rollup(&pool, "tuning").await.unwrap();
+    assert_eq!((tuning.estimated_pomodoros, tuning.subtasks), (1, 0));
+
+    // Moving a section to the top level takes its time with it.
+    subtasks::set_parent(&pool, "chopin_a", None).await.unwrap();
+    let recital = subtasks::rollup(&pool, "recital").await.unwrap();
+    assert_eq!((recital.total_minutes, recital.subtasks), (75, 3));
+
+    // Deleting a parent leaves its subtasks at the top level.
+    tasks::delete(&pool, "chopin").await.unwrap();
+    assert_eq!(subtasks::children(&pool, "recital").await.unwrap().len(), 1);
+    assert_eq!(subtasks::list(&pool).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn loops_are_refused() {
+    let pool = recital_pool().await;
+
+    assert!(subtasks::set_parent(&pool, "recital", Some("recital"))
+        .await
+        .is_err());
+    assert!(subtasks::set_parent(&pool, "recital", Some("chopin_b"))
+        .await
+        .is_err());
+    assert!(subtasks::set_parent(&pool, "bach", Some("missing"))
+        .await
+        .is_err());
+    // A task stays in its own skill's tree.
+    sqlx::raw_sql(
+        "INSERT INTO skills (id, name) VALUES ('skill_2', 'Guitar');
+         INSERT INTO tasks (id, skill_id, title, status, order_index)
+         VALUES ('scales', 'skill_2', 'Scales', 'todo', 0);",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    assert!(subtasks::set_parent(&pool, "scales", Some("recital"))
+        .await
+        .is_err());
+    assert!(subtasks::set_parent(&pool, "bach", Some("scales"))
+        .await
+        .is_err());
+    // Moving within the tree is fine.
+    subtasks::set_parent(&pool, "chopin_b", Some("bach"))
+        .await
+        .unwrap();
+
+    dependencies::add(&pool, "bach", "chopin").await.unwrap();
+    dependencies::add(&pool, "chopin", "tuning").await.unwrap();
+    assert!(dependencies::add(&pool, "tuning", "bach").await.is_err());
+    assert!(dependencies::add(&pool, "tuning", "tuning").await.is_err());
+    // Adding the same link twice is not a loop.
+    dependencies::add(&pool, "bach", "chopin").await.unwrap();
+    assert_eq!(dependencies::list(&pool).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn blocked_tasks_and_their_subtasks_wait() {
+    let pool = recital_pool().await;
+    assert_eq!(
+        unblocked(&pool).await,
+        ["recital", "bach", "chopin", "chopin_b", "tuning"]
+    );
+
+    // Chopin, and with it part B, waits for the tuner.
+    dependencies::add(&pool, "chopin", "tuning").await.unwrap();
+    // A blocker that is already done holds nothing up.
+    dependencies::add(&pool, "bach", "chopin_a").await.unwrap();
+    assert_eq!(unblocked(&pool).await, ["recital", "bach", "tuning"]);
+
+    tasks::set_status(&pool, "tuning", "done").await.unwrap();
+    assert_eq!(
+        unblocked(&pool).await,
+        ["recital", "bach", "chopin", "chopin_b"]
+    );
+
+    // A parent cannot wait on its own subtask, which inherits the block.
+    assert!(dependencies::add(&pool, "recital", "bach").await.is_err());
+    // Nor can a task move under one that waits on it.
+    assert!(subtasks::set_parent(&pool, "tuning", Some("chopin_b"))
+        .await
+        .is_err());
+    dependencies::remove(&pool, "chopin", "tuning")
+        .await
+        .unwrap();
+    subtasks::set_parent(&pool, "tuning", Some("chopin_b"))
+        .await
+        .unwrap();
+    assert_eq!(dependencies::list(&pool).await.unwrap().len(), 1);
+}
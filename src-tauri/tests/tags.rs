@@ -0,0 +1,178 @@
+//! Tags on tasks, sessions and reflections, and the analytics cut by them.
+
+use std::slice;
+
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::SqlitePool;
+use ten_k_hours_app_lib::analytics;
+use ten_k_hours_app_lib::database::migrate;
+use ten_k_hours_app_lib::repository::tags::{self, CreateTagInput, Tagged, UpdateTagInput};
+use ten_k_hours_app_lib::timezone;
+
+async fn memory_pool() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(SqliteConnectOptions::new().in_memory(true))
+        .await
+        .unwrap();
+    migrate(&pool).await.unwrap();
+    timezone::set(&pool, "UTC").await.unwrap();
+    pool
+}
+
+async fn tag(pool: &SqlitePool, name: &str) -> String {
+    tags::create(
+        pool,
+        CreateTagInput {
+            name: name.into(),
+            color: None,
+        },
+    )
+    .await
+    .unwrap()
+    .id
+}
+
+/// Scales practised on a tagged task yesterday and today, a sight-reading
+/// session tagged on its own, and an untagged one.
+async fn practice_pool() -> (SqlitePool, String, String) {
+    let pool = memory_pool().await;
+    let scales = tag(&pool, "scales").await;
+    let sight_reading = tag(&pool, "Sight-reading").await;
+    sqlx::raw_sql(
+        "INSERT INTO skills (id, name) VALUES ('piano', 'Piano'), ('guitar', 'Guitar');
+         INSERT INTO tasks (id, skill_id, title) VALUES ('hanon', 'piano', 'Hanon');
+         INSERT INTO timer_sessions (id, task_id, skill_id, start_time, duration, type, completed)
+         VALUES ('s1', 'hanon', 'piano', strftime('%Y-%m-%dT09:00:00.000Z', 'now', '-1 day'), 25, 'pomodoro', 1),
+                ('s2', 'hanon', 'piano', strftime('%Y-%m-%dT09:00:00.000Z', 'now'), 25, 'pomodoro', 1),
+                ('s3', NULL, 'guitar', strftime('%Y-%m-%dT10:00:00.000Z', 'now'), 50, 'pomodoro', 1),
+                ('s4', NULL, 'piano', strftime('%Y-%m-%dT11:00:00.000Z', 'now'), 25, 'pomodoro', 1);
+         INSERT INTO reflections (id, date, content) VALUES ('r1', date('now'), 'Even semiquavers');",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    // Stamps the local dates the raw inserts left out.
+    timezone::set(&pool, "UTC").await.unwrap();
+    tags::set(&pool, Tagged::Task, "hanon", slice::from_ref(&scales))
+        .await
+        .unwrap();
+    tags::set(
+        &pool,
+        Tagged::Session,
+        "s3",
+        &[sight_reading.clone(), scales.clone()],
+    )
+    .await
+    .unwrap();
+    tags::set(&pool, Tagged::Reflection, "r1", slice::from_ref(&scales))
+        .await
+        .unwrap();
+    (pool, scales, sight_reading)
+}
+
+#[tokio::test]
+async fn names_are_unique_whatever_the_case() {
+    let pool = memory_pool().await;
+    let scales = tag(&pool, "Scales").await;
+    assert_eq!(tag(&pool, " scales ").await, scales);
+    assert!(tags::create(
+        &pool,
+        CreateTagInput {
+            name: "  ".into(),
+            color: None,
+        },
+    )
+    .await
+    .is_err());
+
+    let arpeggios = tag(&pool, "arpeggios").await;
+    let rename = |name: &str| UpdateTagInput {
+        id: arpeggios.clone(),
+        name: Some(name.into()),
+        color: Some("#FF0000".into()),
+    };
+    assert!(tags::update(&pool, rename("SCALES")).await.is_err());
+    let renamed = tags::update(&pool, rename("Arpeggios")).await.unwrap();
+    assert_eq!(
+        (renamed.name.as_str(), renamed.color.as_deref()),
+        ("Arpeggios", Some("#FF0000"))
+    );
+    let names: Vec<String> = tags::list(&pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|tag| tag.name)
+        .collect();
+    assert_eq!(names, ["Arpeggios", "Scales"]);
+}
+
+#[tokio::test]
+async fn time_is_grouped_by_tag() {
+    let (pool, scales, sight_reading) = practice_pool().await;
+
+    let breakdown = analytics::tag_breakdown(&pool, 7).await.unwrap();
+    let shares: Vec<(&str, i64, i64, f64)> = breakdown
+        .iter()
+        .map(|share| {
+            (
+                share.tag_name.as_str(),
+                share.minutes,
+                share.sessions,
+                share.percentage,
+            )
+        })
+        .collect();
+    // 125 minutes in all; the guitar session counts for both tags.
+    assert_eq!(
+        shares,
+        [("scales", 100, 3, 80.0), ("Sight-reading", 50, 1, 40.0)]
+    );
+
+    // Deleting a tag takes it off everything.
+    tags::delete(&pool, &sight_reading).await.unwrap();
+    assert_eq!(tags::links(&pool, Tagged::Session).await.unwrap().len(), 1);
+    assert_eq!(
+        tags::links(&pool, Tagged::Reflection).await.unwrap()[0].tag_id,
+        scales
+    );
+}
+
+#[tokio::test]
+async fn reports_filter_by_tag() {
+    let (pool, scales, sight_reading) = practice_pool().await;
+
+    let week = analytics::weekly_stats(&pool, 2, Some(&scales))
+        .await
+        .unwrap();
+    let minutes: i64 = week.iter().map(|week| week.total_minutes).sum();
+    assert_eq!(minutes, 100);
+
+    let days = analytics::activity_days(&pool, 7, Some(&scales))
+        .await
+        .unwrap();
+    let minutes: Vec<i64> = days.iter().map(|day| day.minutes).collect();
+    assert_eq!(minutes, [25, 75]);
+    let all = analytics::activity_days(&pool, 7, None).await.unwrap();
+    assert_eq!(all.last().unwrap().minutes, 100);
+
+    let streaks = analytics::practice_streaks(&pool, Some(&scales), None)
+        .await
+        .unwrap();
+    assert_eq!((streaks.current, streaks.longest), (2, 2));
+    let streaks = analytics::practice_streaks(&pool, Some(&sight_reading), None)
+        .await
+        .unwrap();
+    assert_eq!((streaks.current, streaks.longest), (1, 1));
+
+    let progress = analytics::skill_progress(&pool, 7, Some(&sight_reading))
+        .await
+        .unwrap();
+    let hours = |name: &str| {
+        let skill = progress.iter().find(|skill| skill.skill_name == name);
+        skill.unwrap().data.last().map(|point| point.hours)
+    };
+    assert_eq!((hours("Piano"), hours("Guitar")), (None, Some(0.83)));
+}
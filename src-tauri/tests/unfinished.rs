@@ -0,0 +1,138 @@
+//! Launch-time recovery of sessions a crash left running.
+
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::SqlitePool;
+use ten_k_hours_app_lib::database::migrate;
+use ten_k_hours_app_lib::repository::sessions::{self, Owner};
+use ten_k_hours_app_lib::timer::{Timer, TimerStatus};
+use ten_k_hours_app_lib::unfinished;
+
+async fn memory_pool() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(SqliteConnectOptions::new().in_memory(true))
+        .await
+        .unwrap();
+    migrate(&pool).await.unwrap();
+    pool
+}
+
+/// Two crashed pomodoros, a crashed break, one that died before its first
+/// heartbeat and one the CLI is still running.
+async fn crashed_pool(policy: &str) -> SqlitePool {
+    let pool = memory_pool().await;
+    sqlx::raw_sql(&format!(
+        "UPDATE user_settings SET recovery_policy = '{policy}', timezone = 'UTC';
+         INSERT INTO skills (id, name) VALUES ('skill_1', 'Piano');
+         INSERT INTO timer_sessions
+            (id, skill_id, start_time, duration, type, completed, planned_duration, heartbeat_at, elapsed_seconds)
+         VALUES ('older', 'skill_1', '2024-03-01T09:00:00.000Z', 25, 'pomodoro', 0, 25, '2024-03-01T09:12:30.000Z', 750),
+                ('break', 'skill_1', '2024-03-01T09:30:00.000Z', 5, 'short-break', 0, 5, '2024-03-01T09:33:00.000Z', 180),
+                ('early', 'skill_1', '2024-03-01T10:00:00.000Z', 25, 'pomodoro', 0, 25, NULL, NULL),
+                ('latest', 'skill_1', '2024-03-01T11:00:00.000Z', 50, 'pomodoro', 0, 50, '2024-03-01T11:20:00.000Z', 1200),
+                ('cli', 'skill_1', '2024-03-01T12:00:00.000Z', 25, 'pomodoro', 0, 25, NULL, NULL);
+         INSERT INTO active_timer (id, session_id, owner) VALUES (1, 'cli', 'cli');"
+    ))
+    .execute(&pool)
+    .await
+    .unwrap();
+    pool
+}
+
+async fn sessions_left(pool: &SqlitePool) -> Vec<(String, Option<String>, i64, bool)> {
+    sqlx::query_as(
+        "SELECT id, end_time, duration, completed FROM timer_sessions ORDER BY start_time",
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn crashed_sessions_close_at_their_last_heartbeat() {
+    let pool = crashed_pool("close").await;
+    let timer = Timer::default();
+    assert!(unfinished::recover(&pool, &timer).await.unwrap().is_empty());
+
+    assert_eq!(
+        sessions_left(&pool).await,
+        [
+            (
+                "older".into(),
+                Some("2024-03-01T09:12:30.000Z".into()),
+                12,
+                true
+            ),
+            (
+                "latest".into(),
+                Some("2024-03-01T11:20:00.000Z".into()),
+                20,
+                true
+            ),
+            ("cli".into(), None, 25, false),
+        ]
+    );
+    let (minutes,): (i64,) = sqlx::query_as("SELECT total_minutes FROM daily_activities")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(minutes, 32);
+    assert_eq!(timer.snapshot().await.status, TimerStatus::Idle);
+
+    // The CLI's session is still its own.
+    let running = sessions::running(&pool).await.unwrap().unwrap();
+    assert_eq!(
+        (running.owner, running.session.id.as_str()),
+        (Owner::Cli, "cli")
+    );
+}
+
+#[tokio::test]
+async fn latest_crashed_session_resumes_paused() {
+    let pool = crashed_pool("resume").await;
+    // The CLI finished in the meantime, so the app may claim the timer.
+    sqlx::query("DELETE FROM active_timer")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("DELETE FROM timer_sessions WHERE id = 'cli'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let timer = Timer::default();
+    assert!(unfinished::recover(&pool, &timer).await.unwrap().is_empty());
+
+    let snapshot = timer.snapshot().await;
+    assert_eq!(snapshot.status, TimerStatus::Paused);
+    assert_eq!(snapshot.session_id.as_deref(), Some("latest"));
+    assert_eq!(snapshot.remaining_seconds, 50 * 60 - 1200);
+    let running = sessions::running(&pool).await.unwrap().unwrap();
+    assert_eq!(
+        (running.owner, running.session.id.as_str()),
+        (Owner::App, "latest")
+    );
+
+    // Everything older was closed as usual.
+    let ids: Vec<String> = sessions_left(&pool)
+        .await
+        .into_iter()
+        .map(|(id, ..)| id)
+        .collect();
+    assert_eq!(ids, ["older", "latest"]);
+}
+
+#[tokio::test]
+async fn asking_leaves_sessions_for_the_window() {
+    let pool = crashed_pool("ask").await;
+    let pending = unfinished::recover(&pool, &Timer::default()).await.unwrap();
+    let ids: Vec<&str> = pending.iter().map(|session| session.id.as_str()).collect();
+    assert_eq!(ids, ["older", "break", "early", "latest"]);
+    assert_eq!(pending[0].worked_minutes(), 12);
+    assert_eq!(pending[2].end_time(), "2024-03-01T10:00:00.000Z");
+    assert_eq!(sessions_left(&pool).await.len(), 5);
+
+    unfinished::credit(&pool, &pending[0]).await.unwrap();
+    assert_eq!(unfinished::find(&pool).await.unwrap().len(), 3);
+}
@@ -1,7 +1,8 @@
 import { useEffect, useState } from 'react';
 import { BrowserRouter, Routes, Route, Navigate } from 'react-router-dom';
-import { Toaster } from 'sonner';
+import { Toaster, toast } from 'sonner';
 import { ErrorBoundary } from './components/ErrorBoundary';
+import { RecoveryDialog } from './components/RecoveryDialog';
 import { Confetti } from './components/ui/magic';
 import Layout from './components/layout/Layout';
 import Dashboard from './pages/Dashboard';
@@ -15,8 +16,11 @@ import SpotifyCallback from './pages/SpotifyCallback';
 import { useThemeStore } from './store/themeStore';
 import { useTimerStore } from './store/timerStore';
 import { useCelebrationStore } from './store/celebrationStore';
-import { preloadSounds, ensureNotificationPermission } from './lib/notifications';
+import { preloadSounds, ensureNotificationPermission, timerNotifications } from './lib/notifications';
 import { musicPlayer, MusicState } from './lib/music';
+import { isTauri } from './lib/database';
+import { listen } from '@tauri-apps/api/event';
+import { commands, AchievementRecord, MigrationFailure } from './lib/commands';
 
 // Global YouTube player that persists across ALL pages including FocusMode
 function GlobalYouTubePlayer() {
@@ -48,6 +52,7 @@ function App() {
   const { initTheme, resolvedTheme } = useThemeStore();
   const { loadSettings } = useTimerStore();
   const showConfetti = useCelebrationStore((state) => state.showConfetti);
+  const [startupFailure, setStartupFailure] = useState<MigrationFailure | null>(null);
 
   useEffect(() => {
     // Initialize theme
@@ -61,8 +66,62 @@ function App() {
     
     // Request notification permission
     ensureNotificationPermission();
+
+    // Surface schema problems found at startup instead of failing per query
+    if (isTauri) {
+      commands.startupFailure().then(setStartupFailure).catch(console.error);
+      commands.schemaDrift().then((drift) => {
+        if (drift.length === 0) return;
+        console.error('[schema] drift detected:', drift);
+        toast.error('Database schema is out of date', {
+          description: `${drift.length} problem(s) found. Some data may not load.`,
+        });
+      }).catch(console.error);
+      commands.repairedOrphans().then((orphans) => {
+        if (orphans.length === 0) return;
+        console.warn('[integrity] repaired orphaned rows:', orphans);
+        toast.warning('Repaired orphaned data', {
+          description: `${orphans.length} row(s) pointed at deleted skills, tasks or reflections.`,
+        });
+      }).catch(console.error);
+      commands.unfinishedSessions().then((sessions) => {
+        for (const session of sessions) {
+          const minutes = Math.min(Math.floor(session.elapsedSeconds / 60), session.plannedMinutes);
+          const resolve = (resolution: 'credit' | 'discard') =>
+            commands.resolveUnfinishedSession(session.id, resolution).catch((error) =>
+              toast.error(`Failed to update the session: ${error}`)
+            );
+          toast.warning('A session was cut short when the app closed', {
+            description: `Started ${new Date(session.startTime).toLocaleString()}, ${minutes} min recorded.`,
+            duration: Infinity,
+            action: { label: 'Keep time', onClick: () => { resolve('credit'); } },
+            cancel: { label: 'Discard', onClick: () => { resolve('discard'); } },
+          });
+        }
+      }).catch(console.error);
+    }
   }, [initTheme, loadSettings]);
 
+  // Achievements are evaluated in the backend after every committed change
+  useEffect(() => {
+    if (!isTauri) return;
+    const unlisten = listen<AchievementRecord>('achievement-unlocked', (event) => {
+      const { name, description } = event.payload;
+      timerNotifications.achievementUnlocked(name, description);
+      toast.success(`Achievement unlocked: ${name}`, { description });
+    });
+    return () => { unlisten.then((stop) => stop()); };
+  }, []);
+
+  if (startupFailure) {
+    return (
+      <>
+        <Toaster position="top-right" theme={resolvedTheme} richColors />
+        <RecoveryDialog failure={startupFailure} />
+      </>
+    );
+  }
+
   return (
     <ErrorBoundary>
       <BrowserRouter>
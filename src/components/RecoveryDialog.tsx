@@ -0,0 +1,77 @@
+/**
+ * Recovery Dialog
+ * Shown instead of the app when the database could not be updated at startup
+ */
+import { AlertTriangle, Copy, Power } from 'lucide-react';
+import { toast } from 'sonner';
+import { Button } from '@/components/ui/button';
+import { Modal } from '@/components/ui/modal';
+import { commands, MigrationFailure } from '@/lib/commands';
+
+interface RecoveryDialogProps {
+  failure: MigrationFailure;
+}
+
+export function RecoveryDialog({ failure }: RecoveryDialogProps) {
+  const details = JSON.stringify(failure, null, 2);
+  const quit = () => commands.quitApp();
+
+  return (
+    <Modal
+      open
+      onClose={quit}
+      title={failure.fromNewerApp ? 'Your data is from a newer version' : 'Your data could not be updated'}
+      description={
+        failure.fromNewerApp
+          ? 'A newer version of the app has updated the database, and this version cannot read it.'
+          : 'This version of the app needs to update the database, and the update failed.'
+      }
+      showCloseButton={false}
+      size="lg"
+    >
+      <div className="space-y-4">
+        <div className="flex gap-3 rounded-lg bg-destructive/10 p-4 text-sm">
+          <AlertTriangle className="w-5 h-5 shrink-0 text-destructive" />
+          <p>
+            {failure.fromNewerApp
+              ? 'Nothing was changed. Install the newer version again to open your data.'
+              : failure.restored
+                ? 'Nothing was lost: the database was put back exactly as it was before the step that failed. Earlier steps of the update were kept.'
+                : 'The database could not be put back automatically.'}
+            {failure.snapshot && (
+              <>
+                {' '}A copy was saved at <code className="break-all">{failure.snapshot}</code>.
+              </>
+            )}
+          </p>
+        </div>
+
+        <details className="text-sm">
+          <summary className="cursor-pointer text-muted-foreground hover:text-foreground">
+            Error details
+          </summary>
+          <pre className="mt-2 p-4 bg-muted rounded-lg text-xs overflow-auto max-h-48">
+            <code>{details}</code>
+          </pre>
+        </details>
+
+        <div className="flex justify-end gap-3">
+          <Button
+            variant="outline"
+            onClick={() => {
+              navigator.clipboard.writeText(details);
+              toast.success('Details copied');
+            }}
+          >
+            <Copy className="w-4 h-4 mr-2" />
+            Copy Details
+          </Button>
+          <Button variant="destructive" onClick={quit}>
+            <Power className="w-4 h-4 mr-2" />
+            Quit
+          </Button>
+        </div>
+      </div>
+    </Modal>
+  );
+}
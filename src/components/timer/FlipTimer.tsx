@@ -2,6 +2,9 @@ import { useEffect, useState } from 'react';
 import '@leenguyen/react-flip-clock-countdown/dist/index.css';
 import { useSkillsStore } from '@/store/skillsStore';
 import { Calendar } from 'lucide-react';
+import { db, isTauri } from '@/lib/database';
+import { commands } from '@/lib/commands';
+import { getDateKey } from '@/lib/utils';
 
 interface FlipTimerProps {
   skillId?: string;
@@ -113,20 +116,20 @@ function ConsistencyTracker({ skillId }: { skillId: string }) {
 
   const loadWeekData = async () => {
     try {
-      const Database = (await import('@tauri-apps/plugin-sql')).default;
-      const db = await Database.load('sqlite:app.db');
-      
       // Get last 7 days
       const dates = [];
       for (let i = 6; i >= 0; i--) {
         const date = new Date();
         date.setDate(date.getDate() - i);
-        dates.push(date.toISOString().split('T')[0]);
+        dates.push(getDateKey(date));
       }
       
       const data = await Promise.all(
         dates.map(async (date) => {
-          const result = await db.select<{ minutes: number }[]>(
+          if (isTauri) {
+            return { date, minutes: await commands.skillMinutesOn(skillId, date) };
+          }
+          const result = await db.select<{ minutes: number }>(
             `SELECT SUM(duration) as minutes FROM timer_sessions 
              WHERE skill_id = $1 AND DATE(created_at) = $2 AND completed = 1`,
             [skillId, date]
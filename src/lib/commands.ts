@@ -0,0 +1,519 @@
+/**
+ * Typed wrappers around the Tauri commands in src-tauri/src/commands.rs.
+ * Desktop only: the webview has no raw SQL access, so stores call these
+ * when `isTauri` and keep the IndexedDB path for the web build.
+ */
+import { invoke } from '@tauri-apps/api/core';
+import type { DayActivity, SkillProgress, TagShare, WeeklyStats } from '../types/analytics';
+
+// ============ RECORDS ============
+// Rows come back with their column names, as the SQL plugin returned them.
+export interface SkillRecord {
+  id: string;
+  name: string;
+  description: string | null;
+  goal_hours: number;
+  daily_goal_minutes: number;
+  current_minutes: number;
+  color: string;
+  is_active: boolean;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface SubskillRecord {
+  skill_id: string;
+  parent_id: string;
+  created_at: string;
+}
+
+// A skill's time including every sub-skill below it, against its own goal
+export interface SkillRollup {
+  skill_id: string;
+  parent_id: string | null;
+  goal_hours: number;
+  own_minutes: number;
+  total_minutes: number;
+  subskills: number;
+  progress_percentage: number;
+}
+
+export interface TaskRecord {
+  id: string;
+  skill_id: string;
+  title: string;
+  description: string | null;
+  status: string;
+  priority: string;
+  due_date: string | null;
+  estimated_pomodoros: number;
+  pomodoro_sessions: number;
+  total_minutes: number;
+  order_index: number;
+  created_at: string;
+  completed_at: string | null;
+  recurrence: string | null;
+  series_id: string | null;
+  previous_id: string | null;
+}
+
+export interface SubtaskRecord {
+  task_id: string;
+  parent_id: string;
+  created_at: string;
+}
+
+export interface DependencyRecord {
+  task_id: string;
+  blocked_by_id: string;
+  created_at: string;
+}
+
+// A task's totals including every subtask below it
+export interface TaskRollup {
+  task_id: string;
+  estimated_pomodoros: number;
+  pomodoro_sessions: number;
+  total_minutes: number;
+  subtasks: number;
+  done_subtasks: number;
+}
+
+export interface TagRecord {
+  id: string;
+  name: string;
+  color: string | null;
+  created_at: string;
+}
+
+export type Tagged = 'task' | 'session' | 'reflection';
+
+export interface TagLink {
+  item_id: string;
+  tag_id: string;
+}
+
+export interface TimerSessionRecord {
+  id: string;
+  task_id: string | null;
+  skill_id: string;
+  start_time: string;
+  end_time: string | null;
+  duration: number;
+  type: string;
+  completed: boolean;
+  planned_duration: number | null;
+  session_type: string | null;
+  created_at: string;
+}
+
+export interface DailyActivityRecord {
+  date: string;
+  total_minutes: number;
+  total_sessions: number;
+}
+
+export interface ReflectionRecord {
+  id: string;
+  date: string;
+  content: string;
+  mood: string | null;
+  total_minutes: number | null;
+  created_at: string;
+  updated_at: string;
+  skill_ids: string[];
+}
+
+export interface AchievementRecord {
+  id: string;
+  type: string;
+  name: string;
+  description: string;
+  icon: string;
+  unlocked_at: string | null;
+  progress: number;
+  target: number;
+  created_at: string;
+  rule: AchievementRule | null;
+}
+
+// User-defined achievement, e.g. 20 hours of one skill in a month
+export interface AchievementRule {
+  metric: 'minutes' | 'hours' | 'sessions' | 'earlyBirdSessions' | 'nightOwlSessions' | 'practiceDays';
+  skillId?: string | null;
+  window: 'day' | 'week' | 'month' | 'allTime';
+  threshold: number;
+}
+
+/** What the timer does with a session the computer slept through. */
+export type SleepPolicy = 'pause' | 'trim' | 'ask';
+
+/** What launch does with sessions a crash left running. */
+export type RecoveryPolicy = 'close' | 'ask' | 'resume';
+
+export interface UserSettingsRecord {
+  id: number;
+  name: string;
+  email: string | null;
+  avatar: string | null;
+  theme: string;
+  sound_enabled: boolean;
+  notifications_enabled: boolean;
+  pomodoro_duration: number;
+  short_break_duration: number;
+  long_break_duration: number;
+  auto_start_breaks: boolean;
+  auto_start_pomodoros: boolean;
+  long_break_interval: number;
+  daily_goal_minutes: number;
+  weekly_goal_minutes: number;
+  spotify_enabled: boolean;
+  spotify_access_token: string | null;
+  spotify_refresh_token: string | null;
+  spotify_token_expiry: string | null;
+  timezone: string | null;
+  day_start_hour: number;
+  sleep_policy: SleepPolicy;
+  recovery_policy: RecoveryPolicy;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface BackupArchive {
+  format: '10k-hours-backup';
+  formatVersion: number;
+  appVersion: string;
+  schemaVersion: number;
+  createdAt: string;
+  tables: { name: string; checksum: string; rows: Record<string, unknown>[] }[];
+}
+
+export type ConflictPolicy = 'skip' | 'replace' | 'fail';
+
+export interface RestoreOptions {
+  onConflict?: ConflictPolicy;
+  dryRun?: boolean;
+}
+
+export interface TableChanges {
+  table: string;
+  added: number;
+  updated: number;
+  unchanged: number;
+  skipped: number;
+  conflicts: string[];
+}
+
+export interface RestoreReport {
+  dryRun: boolean;
+  tables: TableChanges[];
+}
+
+export interface Snapshot {
+  name: string;
+  takenAt: string;
+  reason: string;
+  size: number;
+}
+
+export interface ApiSettings {
+  apiEnabled: boolean;
+  apiPort: number;
+  apiToken: string | null;
+}
+
+export interface ProfileStats {
+  total_minutes: number;
+  current_streak: number;
+  longest_streak: number;
+}
+
+export interface Streaks {
+  current: number;
+  longest: number;
+}
+
+export interface Pace {
+  windowDays: number;
+  minutesPerDay: number;
+}
+
+export interface MasteryProjection {
+  skillId: string;
+  skillName: string;
+  goalMinutes: number;
+  currentMinutes: number;
+  remainingMinutes: number;
+  paces: Pace[];
+  projectedDate: string | null;
+  earliestDate: string | null;
+  latestDate: string | null;
+  targetDate: string | null;
+  requiredDailyMinutes: number | null;
+}
+
+export interface MigrationFailure {
+  version: number | null;
+  description: string | null;
+  message: string;
+  snapshot: string | null;
+  restored: boolean;
+  fromNewerApp: boolean;
+}
+
+export type SchemaDrift =
+  | { kind: 'missing-table'; table: string }
+  | { kind: 'missing-column'; table: string; column: string };
+
+export interface Orphan {
+  table: string;
+  id: string;
+  parentTable: string;
+  parentId: string;
+  action: 'reattached' | 'detached' | 'purged';
+}
+
+/** A session the app was running when it was killed. */
+export interface UnfinishedSession {
+  id: string;
+  taskId: string | null;
+  skillId: string;
+  type: 'pomodoro' | 'short-break' | 'long-break';
+  startTime: string;
+  heartbeatAt: string | null;
+  elapsedSeconds: number;
+  plannedMinutes: number;
+}
+
+export interface DataExport {
+  skills: SkillRecord[];
+  tasks: TaskRecord[];
+  sessions: TimerSessionRecord[];
+  activities: DailyActivityRecord[];
+  reflections: ReflectionRecord[];
+  achievements: AchievementRecord[];
+  settings?: UserSettingsRecord;
+}
+
+// ============ INPUTS ============
+export interface SkillInput {
+  name: string;
+  description?: string | null;
+  goalHours: number;
+  dailyGoalMinutes?: number;
+  color?: string;
+}
+
+export interface SkillUpdate {
+  id: string;
+  name?: string;
+  description?: string;
+  goalHours?: number;
+  dailyGoalMinutes?: number;
+  color?: string;
+}
+
+export interface TaskInput {
+  skillId: string;
+  title: string;
+  description?: string | null;
+  status?: string;
+  priority?: string;
+  dueDate?: string | null;
+  estimatedPomodoros?: number;
+  recurrence?: string;
+}
+
+export interface TaskUpdate {
+  id: string;
+  title?: string;
+  description?: string;
+  status?: string;
+  priority?: string;
+  dueDate?: string;
+  estimatedPomodoros?: number;
+  order?: number;
+  pomodoroSessions?: number;
+  totalMinutes?: number;
+  recurrence?: string;
+}
+
+export interface TagInput {
+  name: string;
+  color?: string | null;
+}
+
+export interface TagUpdate {
+  id: string;
+  name?: string;
+  color?: string;
+}
+
+export interface SessionUpdate {
+  id: string;
+  endTime?: string;
+  completed?: boolean;
+  duration?: number;
+}
+
+export interface ReflectionInput {
+  date: string;
+  content: string;
+  mood?: string | null;
+  skillIds?: string[];
+}
+
+export interface AchievementInput {
+  name: string;
+  description?: string | null;
+  icon?: string | null;
+  rule: AchievementRule;
+}
+
+export interface SettingsUpdate {
+  name?: string;
+  email?: string;
+  avatar?: string;
+  theme?: string;
+  soundEnabled?: boolean;
+  notificationsEnabled?: boolean;
+  pomodoroDuration?: number;
+  shortBreakDuration?: number;
+  longBreakDuration?: number;
+  autoStartBreaks?: boolean;
+  autoStartPomodoros?: boolean;
+  longBreakInterval?: number;
+  dailyGoalMinutes?: number;
+  weeklyGoalMinutes?: number;
+  timezone?: string;
+  dayStartHour?: number;
+  sleepPolicy?: SleepPolicy;
+  recoveryPolicy?: RecoveryPolicy;
+}
+
+export interface ApiSettingsUpdate {
+  enabled?: boolean;
+  port?: number;
+}
+
+// ============ COMMANDS ============
+export const commands = {
+  // Schema
+  schemaDrift: () => invoke<SchemaDrift[]>('schema_drift'),
+  repairedOrphans: () => invoke<Orphan[]>('repaired_orphans'),
+  startupFailure: () => invoke<MigrationFailure | null>('startup_failure'),
+  quitApp: () => invoke<void>('quit_app'),
+  unfinishedSessions: () => invoke<UnfinishedSession[]>('unfinished_sessions'),
+  resolveUnfinishedSession: (id: string, resolution: 'credit' | 'discard') =>
+    invoke<void>('resolve_unfinished_session', { id, resolution }),
+
+  // Skills
+  listSkills: () => invoke<SkillRecord[]>('list_skills'),
+  createSkill: (input: SkillInput) => invoke<SkillRecord>('create_skill', { input }),
+  updateSkill: (input: SkillUpdate) => invoke<SkillRecord>('update_skill', { input }),
+  deleteSkill: (id: string) => invoke<void>('delete_skill', { id }),
+  setActiveSkill: (id: string | null) => invoke<void>('set_active_skill', { id }),
+  listSubskills: () => invoke<SubskillRecord[]>('list_subskills'),
+  setSkillParent: (skillId: string, parentId: string | null) =>
+    invoke<void>('set_skill_parent', { skillId, parentId }),
+  skillRollups: () => invoke<SkillRollup[]>('skill_rollups'),
+
+  // Tasks
+  listTasks: (skillId?: string) => invoke<TaskRecord[]>('list_tasks', { skillId: skillId ?? null }),
+  createTask: (input: TaskInput) => invoke<TaskRecord>('create_task', { input }),
+  updateTask: (input: TaskUpdate) => invoke<TaskRecord>('update_task', { input }),
+  setTaskStatus: (id: string, status: string) =>
+    invoke<TaskRecord>('set_task_status', { id, status }),
+  deleteTask: (id: string) => invoke<void>('delete_task', { id }),
+  reorderTasks: (ids: string[]) => invoke<void>('reorder_tasks', { ids }),
+  listTaskSeries: (seriesId: string) =>
+    invoke<TaskRecord[]>('list_task_series', { seriesId }),
+  listSubtasks: () => invoke<SubtaskRecord[]>('list_subtasks'),
+  setTaskParent: (taskId: string, parentId: string | null) =>
+    invoke<void>('set_task_parent', { taskId, parentId }),
+  taskRollups: (skillId?: string) =>
+    invoke<TaskRollup[]>('task_rollups', { skillId: skillId ?? null }),
+  listTaskDependencies: () => invoke<DependencyRecord[]>('list_task_dependencies'),
+  addTaskDependency: (taskId: string, blockedById: string) =>
+    invoke<void>('add_task_dependency', { taskId, blockedById }),
+  removeTaskDependency: (taskId: string, blockedById: string) =>
+    invoke<void>('remove_task_dependency', { taskId, blockedById }),
+  unblockedTasks: (skillId?: string) =>
+    invoke<TaskRecord[]>('unblocked_tasks', { skillId: skillId ?? null }),
+
+  // Timer sessions
+  listSessions: (skillId?: string) =>
+    invoke<TimerSessionRecord[]>('list_sessions', { skillId: skillId ?? null }),
+  recordManualSession: (skillId: string, taskId: string | null, minutes: number) =>
+    invoke<TimerSessionRecord>('record_manual_session', { skillId, taskId, minutes }),
+  updateSession: (input: SessionUpdate) => invoke<void>('update_session', { input }),
+  minutesBySkillOn: (date: string) =>
+    invoke<{ skill_id: string; total_minutes: number }[]>('minutes_by_skill_on', { date }),
+  minutesByDay: (days: number) =>
+    invoke<{ activity_date: string; total_minutes: number }[]>('minutes_by_day', { days }),
+  skillMinutesOn: (skillId: string, date: string) =>
+    invoke<number>('skill_minutes_on', { skillId, date }),
+
+  // Daily activity
+  listDailyActivities: (days: number) =>
+    invoke<DailyActivityRecord[]>('list_daily_activities', { days }),
+  recomputeAggregates: () => invoke<void>('recompute_aggregates'),
+  getProfileStats: () => invoke<ProfileStats>('get_profile_stats'),
+
+  // Tags
+  listTags: () => invoke<TagRecord[]>('list_tags'),
+  createTag: (input: TagInput) => invoke<TagRecord>('create_tag', { input }),
+  updateTag: (input: TagUpdate) => invoke<TagRecord>('update_tag', { input }),
+  deleteTag: (id: string) => invoke<void>('delete_tag', { id }),
+  listTagLinks: (kind: Tagged) => invoke<TagLink[]>('list_tag_links', { kind }),
+  setTags: (kind: Tagged, itemId: string, tagIds: string[]) =>
+    invoke<void>('set_tags', { kind, itemId, tagIds }),
+
+  // Analytics; a tag narrows each report to the time that counts for it
+  getActivityDays: (days: number, tagId?: string) =>
+    invoke<DayActivity[]>('get_activity_days', { days, tagId: tagId ?? null }),
+  getStreaks: (tagId?: string, skillId?: string) =>
+    invoke<Streaks>('get_streaks', { tagId: tagId ?? null, skillId: skillId ?? null }),
+  getWeeklyStats: (weeks: number, tagId?: string) =>
+    invoke<WeeklyStats[]>('get_weekly_stats', { weeks, tagId: tagId ?? null }),
+  getSkillProgress: (days: number, tagId?: string) =>
+    invoke<SkillProgress[]>('get_skill_progress', { days, tagId: tagId ?? null }),
+  getTagBreakdown: (days: number) => invoke<TagShare[]>('get_tag_breakdown', { days }),
+  getMasteryProjections: (targetDate?: string) =>
+    invoke<MasteryProjection[]>('get_mastery_projections', { targetDate: targetDate ?? null }),
+
+  // Reflections
+  listReflectionDates: () => invoke<string[]>('list_reflection_dates'),
+  getReflection: (date: string) => invoke<ReflectionRecord | null>('get_reflection', { date }),
+  saveReflection: (input: ReflectionInput) =>
+    invoke<ReflectionRecord>('save_reflection', { input }),
+
+  // Achievements
+  listAchievements: () => invoke<AchievementRecord[]>('list_achievements'),
+  recomputeAchievements: () => invoke<AchievementRecord[]>('recompute_achievements'),
+  createAchievement: (input: AchievementInput) =>
+    invoke<AchievementRecord>('create_achievement', { input }),
+  deleteAchievement: (id: string) => invoke<void>('delete_achievement', { id }),
+
+  // Settings
+  getSettings: () => invoke<UserSettingsRecord>('get_settings'),
+  updateSettings: (input: SettingsUpdate) =>
+    invoke<UserSettingsRecord>('update_settings', { input }),
+  getApiSettings: () => invoke<ApiSettings>('get_api_settings'),
+  updateApiSettings: (input: ApiSettingsUpdate) =>
+    invoke<ApiSettings>('update_api_settings', { input }),
+  regenerateApiToken: () => invoke<ApiSettings>('regenerate_api_token'),
+
+  // Data management
+  exportData: () => invoke<DataExport>('export_data'),
+  importData: (backup: Partial<DataExport>) => invoke<void>('import_data', { backup }),
+  clearData: () => invoke<void>('clear_data'),
+  createBackup: () => invoke<BackupArchive>('create_backup'),
+  restoreBackup: (archive: BackupArchive, options: RestoreOptions = {}) =>
+    invoke<RestoreReport>('restore_backup', { archive, options }),
+  listSnapshots: () => invoke<Snapshot[]>('list_snapshots'),
+  createSnapshot: () => invoke<Snapshot>('create_snapshot'),
+  restoreSnapshot: (name: string) => invoke<Snapshot>('restore_snapshot', { name }),
+};
+
+export default commands;
@@ -1,6 +1,6 @@
 /**
  * Cross-Platform Database Service
- * Uses SQLite (Tauri) for desktop, IndexedDB (Dexie) for web
+ * Uses SQLite through Tauri commands for desktop, IndexedDB (Dexie) for web
  */
 import Dexie, { Table } from 'dexie';
 
@@ -187,42 +187,17 @@ async function initWebDb(): Promise<void> {
   webDbInitialized = true;
 }
 
-// ============ TAURI/SQLITE (DESKTOP) ============
-let tauriDb: any = null;
-let tauriDbPromise: Promise<any> | null = null;
-
-async function getTauriDb(): Promise<any> {
-  if (tauriDb) return tauriDb;
-  if (tauriDbPromise) return tauriDbPromise;
-  
-  tauriDbPromise = (async () => {
-    const { default: Database } = await import('@tauri-apps/plugin-sql');
-    tauriDb = await Database.load('sqlite:app.db');
-    return tauriDb;
-  })();
-  
-  return tauriDbPromise;
-}
-
 // ============ UNIFIED DATABASE API ============
+// Desktop builds go through the typed commands in ./commands instead; the
+// webview has no raw SQL access there.
 export async function execute(query: string, params: any[] = []): Promise<void> {
-  if (isTauri) {
-    const db = await getTauriDb();
-    await db.execute(query, params);
-  } else {
-    await initWebDb();
-    await executeWebQuery(query, params);
-  }
+  await initWebDb();
+  await executeWebQuery(query, params);
 }
 
 export async function select<T = any>(query: string, params: any[] = []): Promise<T[]> {
-  if (isTauri) {
-    const db = await getTauriDb();
-    return (db as { select: <T>(s: string, p?: any[]) => Promise<T> }).select<T[]>(query, params);
-  } else {
-    await initWebDb();
-    return selectWebQuery<T>(query, params);
-  }
+  await initWebDb();
+  return selectWebQuery<T>(query, params);
 }
 
 // ============ WEB QUERY HANDLERS ============
@@ -1,5 +1,6 @@
 import { type ClassValue, clsx } from "clsx";
 import { twMerge } from "tailwind-merge";
+import { format } from "date-fns";
 
 export function cn(...inputs: ClassValue[]) {
   return twMerge(clsx(inputs));
@@ -57,9 +58,9 @@ export function formatRelativeTime(date: string | Date): string {
   return formatDate(date);
 }
 
+// Local calendar day, matching the day keys the backend stores
 export function getDateKey(date?: Date): string {
-  const d = date || new Date();
-  return d.toISOString().split('T')[0];
+  return format(date || new Date(), 'yyyy-MM-dd');
 }
 
 export function calculateStreak(activities: { date: string }[]): { current: number; longest: number } {
@@ -7,7 +7,7 @@ import { Input } from '../components/ui/input';
 import { useSkillsStore } from '../store/skillsStore';
 import { useTasksStore } from '../store/tasksStore';
 import { useTimerStore } from '../store/timerStore';
-import { cn } from '../lib/utils';
+import { cn, getDateKey } from '../lib/utils';
 
 // Format minutes to human readable string
 const formatHours = (minutes: number): string => {
@@ -25,7 +25,7 @@ const formatPercent = (current: number, total: number): number => {
 
 // Get today's date string
 const getTodayString = (): string => {
-  return new Date().toISOString().split('T')[0];
+  return getDateKey();
 };
 
 // Generate last 365 days for consistency calendar
@@ -36,7 +36,7 @@ const generateYearDays = () => {
     const date = new Date(today);
     date.setDate(date.getDate() - i);
     days.push({
-      date: date.toISOString().split('T')[0],
+      date: getDateKey(date),
       dayOfWeek: date.getDay(),
     });
   }
@@ -191,7 +191,7 @@ export default function Dashboard() {
     for (let i = 0; i < 365; i++) {
       const date = new Date(today);
       date.setDate(date.getDate() - i);
-      if (activityData[date.toISOString().split('T')[0]] > 0) streak++;
+      if (activityData[getDateKey(date)] > 0) streak++;
       else if (i > 0) break;
     }
     return streak;
@@ -1,7 +1,8 @@
 import { useEffect, useState } from 'react';
 import { Button } from '@/components/ui/button';
 import { useSkillsStore } from '@/store/skillsStore';
-import { db } from '@/lib/database';
+import { db, isTauri } from '@/lib/database';
+import { commands } from '@/lib/commands';
 import ReactMarkdown from 'react-markdown';
 import { ChevronLeft, ChevronRight, Save, Edit, BookOpen } from 'lucide-react';
 import { cn } from '@/lib/utils';
@@ -35,7 +36,11 @@ export default function Journal() {
 
   const loadReflectionDates = async () => {
     try {
-      
+      if (isTauri) {
+        setReflectionDates(new Set(await commands.listReflectionDates()));
+        return;
+      }
+
       const results = await db.select<{ date: string }>(
         'SELECT date FROM reflections'
       );
@@ -48,8 +53,18 @@ export default function Journal() {
   const loadReflection = async (date: string) => {
     try {
       setLoading(true);
-      
-      
+
+      if (isTauri) {
+        const r = await commands.getReflection(date);
+        setReflection(r as Reflection | null);
+        setContent(r?.content ?? '');
+        setMood(r?.mood ?? '');
+        setLinkedSkills(r?.skill_ids ?? []);
+        setIsEditing(false);
+        setLoading(false);
+        return;
+      }
+
       const result = await db.select<Reflection>(
         'SELECT * FROM reflections WHERE date = $1 LIMIT 1',
         [date]
@@ -86,9 +101,14 @@ export default function Journal() {
     if (!content.trim()) return;
 
     try {
-      
-      
-      if (reflection) {
+      if (isTauri) {
+        await commands.saveReflection({
+          date: selectedDate,
+          content,
+          mood: mood || null,
+          skillIds: linkedSkills,
+        });
+      } else if (reflection) {
         // Update existing
         await db.execute(
           'UPDATE reflections SET content = $1, mood = $2 WHERE id = $3',
@@ -1,7 +1,8 @@
 import { useEffect, useState } from 'react';
 import { useSkillsStore } from '@/store/skillsStore';
 import { useUserStore } from '@/store/userStore';
-import { db } from '@/lib/database';
+import { db, isTauri } from '@/lib/database';
+import { commands } from '@/lib/commands';
 import { BarChart, Bar, XAxis, YAxis, CartesianGrid, Tooltip, ResponsiveContainer } from 'recharts';
 import { Award, TrendingUp, Target, Calendar as CalendarIcon, Flame } from 'lucide-react';
 
@@ -34,10 +35,8 @@ export default function Profile() {
 
   const loadAnalytics = async () => {
     try {
-      
-      
       // Get last 365 days of activity for heatmap
-      const activities = await db.select<any>(
+      const activities: any[] = isTauri ? await commands.listDailyActivities(365) : await db.select<any>(
         `SELECT date, SUM(minutes) as total_minutes 
          FROM daily_activities 
          WHERE date >= date('now', '-365 days')
@@ -56,13 +55,15 @@ export default function Profile() {
       setDailyActivities(activitiesWithLevel);
 
       // Get last 7 days for chart
-      const weekly = await db.select<any>(
-        `SELECT date, SUM(total_minutes) as minutes
-         FROM daily_activities
-         WHERE date >= date('now', '-7 days')
-         GROUP BY date
-         ORDER BY date ASC`
-      );
+      const weekly: any[] = isTauri
+        ? (await commands.listDailyActivities(7)).map(a => ({ date: a.date, minutes: a.total_minutes }))
+        : await db.select<any>(
+            `SELECT date, SUM(total_minutes) as minutes
+             FROM daily_activities
+             WHERE date >= date('now', '-7 days')
+             GROUP BY date
+             ORDER BY date ASC`
+          );
       
       const weeklyFormatted = weekly.map(w => ({
         date: new Date(w.date).toLocaleDateString('en-US', { weekday: 'short' }),
@@ -72,9 +73,9 @@ export default function Profile() {
       setWeeklyData(weeklyFormatted);
 
       // Get achievements
-      const achievementsList = await db.select<Achievement>(
-        'SELECT * FROM achievements ORDER BY unlocked_at DESC'
-      );
+      const achievementsList = isTauri
+        ? ((await commands.listAchievements()) as Achievement[])
+        : await db.select<Achievement>('SELECT * FROM achievements ORDER BY unlocked_at DESC');
       setAchievements(achievementsList);
 
       setLoading(false);
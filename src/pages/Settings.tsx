@@ -1,6 +1,7 @@
 import { useEffect, useState } from 'react';
 import { useUserStore } from '@/store/userStore';
 import { useThemeStore } from '@/store/themeStore';
+import { useSkillsStore } from '@/store/skillsStore';
 import { Button } from '@/components/ui/button';
 import { Input } from '@/components/ui/input';
 import { 
@@ -12,10 +13,22 @@ import {
   Target,
   Save,
   Download,
-  Trash2
+  Trash2,
+  RefreshCw,
+  Copy
 } from 'lucide-react';
 import { toast } from 'sonner';
-import { db } from '@/lib/database';
+import { db, isTauri } from '@/lib/database';
+import {
+  commands,
+  type ApiSettings,
+  type BackupArchive,
+  type ConflictPolicy,
+  type RecoveryPolicy,
+  type RestoreReport,
+  type SleepPolicy,
+  type Snapshot,
+} from '@/lib/commands';
 import { cn } from '@/lib/utils';
 
 interface SettingsSection {
@@ -50,11 +63,41 @@ export default function Settings() {
   const [soundEnabled, setSoundEnabled] = useState(true);
   const [notificationsEnabled, setNotificationsEnabled] = useState(true);
   const [autoStartBreaks, setAutoStartBreaks] = useState(false);
+  const [timezone, setTimezone] = useState(Intl.DateTimeFormat().resolvedOptions().timeZone);
+  const [savedTimezone, setSavedTimezone] = useState<string | null>(null);
+  const [dayStartHour, setDayStartHour] = useState(0);
+  const [savedDayStartHour, setSavedDayStartHour] = useState(0);
+  const [sleepPolicy, setSleepPolicy] = useState<SleepPolicy>('pause');
+  const [recoveryPolicy, setRecoveryPolicy] = useState<RecoveryPolicy>('close');
+  const [api, setApi] = useState<ApiSettings | null>(null);
+  const [apiPort, setApiPort] = useState(47600);
+  const [backup, setBackup] = useState<BackupArchive | null>(null);
+  const [conflictPolicy, setConflictPolicy] = useState<ConflictPolicy>('skip');
+  const [restorePreview, setRestorePreview] = useState<RestoreReport | null>(null);
+  const [snapshots, setSnapshots] = useState<Snapshot[]>([]);
 
   useEffect(() => {
     fetchProfile();
   }, [fetchProfile]);
 
+  useEffect(() => {
+    if (!isTauri) return;
+    commands.getApiSettings().then((loaded) => {
+      setApi(loaded);
+      setApiPort(loaded.apiPort);
+    });
+    commands.listSnapshots().then(setSnapshots);
+    commands.getSettings().then((loaded) => {
+      setDayStartHour(loaded.day_start_hour);
+      setSavedDayStartHour(loaded.day_start_hour);
+      setSleepPolicy(loaded.sleep_policy);
+      setRecoveryPolicy(loaded.recovery_policy);
+      if (!loaded.timezone) return;
+      setTimezone(loaded.timezone);
+      setSavedTimezone(loaded.timezone);
+    });
+  }, []);
+
   useEffect(() => {
     if (profile) {
       setName(profile.name || '');
@@ -86,12 +129,24 @@ export default function Settings() {
       });
       
       // Update name separately if changed
-      if (profile && name !== profile.name) {
+      if (profile && name !== profile.name && isTauri) {
+        await commands.updateSettings({ name });
+      } else if (profile && name !== profile.name) {
         await db.execute(
           'UPDATE user_settings SET name = $1 WHERE id = 1',
           [name]
         );
       }
+
+      if (isTauri) {
+        await commands.updateSettings({ sleepPolicy, recoveryPolicy });
+      }
+      // Past sessions move to their day in the new zone.
+      if (isTauri && (timezone !== savedTimezone || dayStartHour !== savedDayStartHour)) {
+        await commands.updateSettings({ timezone, dayStartHour });
+        setSavedTimezone(timezone);
+        setSavedDayStartHour(dayStartHour);
+      }
       
       toast.success('Settings saved');
       await fetchProfile();
@@ -104,21 +159,17 @@ export default function Settings() {
 
   const handleExportData = async () => {
     try {
-      const skills = await db.select('SELECT * FROM skills');
-      const tasks = await db.select('SELECT * FROM tasks');
-      const sessions = await db.select('SELECT * FROM timer_sessions');
-      const achievements = await db.select('SELECT * FROM achievements');
-      const reflections = await db.select('SELECT * FROM reflections');
-      
-      const data = {
-        exportedAt: new Date().toISOString(),
-        version: '1.0.0',
-        skills,
-        tasks,
-        sessions,
-        achievements,
-        reflections,
-      };
+      const data = isTauri
+        ? await commands.createBackup()
+        : {
+            exportedAt: new Date().toISOString(),
+            version: '1.0.0',
+            skills: await db.select('SELECT * FROM skills'),
+            tasks: await db.select('SELECT * FROM tasks'),
+            sessions: await db.select('SELECT * FROM timer_sessions'),
+            achievements: await db.select('SELECT * FROM achievements'),
+            reflections: await db.select('SELECT * FROM reflections'),
+          };
       
       const blob = new Blob([JSON.stringify(data, null, 2)], { type: 'application/json' });
       const url = URL.createObjectURL(blob);
@@ -134,18 +185,111 @@ export default function Settings() {
     }
   };
 
+  const previewRestore = async (archive: BackupArchive, onConflict: ConflictPolicy) => {
+    try {
+      setRestorePreview(await commands.restoreBackup(archive, { onConflict, dryRun: true }));
+    } catch (error) {
+      setRestorePreview(null);
+      toast.error(`Cannot restore this backup: ${error}`);
+    }
+  };
+
+  const handleChooseBackup = async (file: File | undefined) => {
+    if (!file) return;
+    try {
+      const archive = JSON.parse(await file.text()) as BackupArchive;
+      setBackup(archive);
+      await previewRestore(archive, conflictPolicy);
+    } catch (error) {
+      toast.error('That file is not a backup');
+    }
+  };
+
+  const handleRestoreBackup = async () => {
+    if (!backup) return;
+    try {
+      await commands.restoreBackup(backup, { onConflict: conflictPolicy });
+      await Promise.all([fetchProfile(), useSkillsStore.getState().fetchSkills()]);
+      setBackup(null);
+      setRestorePreview(null);
+      toast.success('Backup restored');
+    } catch (error) {
+      toast.error(`Restore failed: ${error}`);
+    }
+  };
+
+  const handleTakeSnapshot = async () => {
+    try {
+      await commands.createSnapshot();
+      setSnapshots(await commands.listSnapshots());
+      toast.success('Snapshot saved');
+    } catch (error) {
+      toast.error(`Snapshot failed: ${error}`);
+    }
+  };
+
+  const handleRestoreSnapshot = async (snapshot: Snapshot) => {
+    const takenAt = new Date(snapshot.takenAt).toLocaleString();
+    if (!confirm(`Go back to your data as it was on ${takenAt}? Your current data is snapshotted first.`)) {
+      return;
+    }
+    try {
+      await commands.restoreSnapshot(snapshot.name);
+      toast.success('Snapshot restored');
+      window.location.reload();
+    } catch (error) {
+      toast.error(`Restore failed: ${error}`);
+    }
+  };
+
+  const handleRecomputeTotals = async () => {
+    if (!confirm('Rebuild daily activity from your recorded sessions? Days with sessions are recalculated from them.')) {
+      return;
+    }
+    try {
+      await commands.recomputeAggregates();
+      await Promise.all([fetchProfile(), useSkillsStore.getState().fetchSkills()]);
+      toast.success('Daily activity rebuilt from your sessions');
+    } catch (error) {
+      toast.error('Failed to rebuild totals');
+    }
+  };
+
+  const handleUpdateApi = async (enabled: boolean) => {
+    try {
+      const updated = await commands.updateApiSettings({ enabled, port: apiPort });
+      setApi(updated);
+      toast.success(enabled ? `API listening on port ${updated.apiPort}` : 'API turned off');
+    } catch (error) {
+      toast.error(`Failed to update API: ${error}`);
+    }
+  };
+
+  const handleRegenerateToken = async () => {
+    try {
+      setApi(await commands.regenerateApiToken());
+      toast.success('New token generated; update your scripts');
+    } catch (error) {
+      toast.error('Failed to generate a new token');
+    }
+  };
+
   const handleClearData = async () => {
     if (!confirm('Are you sure you want to clear ALL data? This cannot be undone!')) {
       return;
     }
     
     try {
-      await db.execute('DELETE FROM timer_sessions');
-      await db.execute('DELETE FROM tasks');
-      await db.execute('DELETE FROM reflections');
-      await db.execute('DELETE FROM daily_activities');
-      await db.execute('DELETE FROM skills');
-      await db.execute('DELETE FROM achievements');
+      if (isTauri) {
+        await commands.clearData();
+      } else {
+        await db.execute('DELETE FROM timer_sessions');
+        await db.execute('DELETE FROM tasks');
+        await db.execute('DELETE FROM reflections');
+        await db.execute('DELETE FROM daily_activities');
+        await db.execute('DELETE FROM skills');
+        await db.execute('DELETE FROM achievements');
+      }
       
       toast.success('All data cleared');
       window.location.reload();
@@ -284,6 +428,46 @@ export default function Settings() {
                     />
                   </button>
                 </div>
+
+                {isTauri && (
+                  <div className="flex items-center justify-between py-3 border-t border-gray-100 dark:border-gray-800">
+                    <div>
+                      <p className="font-medium text-gray-900 dark:text-white">When the Computer Sleeps</p>
+                      <p className="text-sm text-gray-500">
+                        Time asleep is never counted towards a session
+                      </p>
+                    </div>
+                    <select
+                      value={sleepPolicy}
+                      onChange={(e) => setSleepPolicy(e.target.value as SleepPolicy)}
+                      className="h-10 rounded-md border border-input bg-background px-3 text-sm"
+                    >
+                      <option value="pause">Pause the timer</option>
+                      <option value="trim">End the session</option>
+                      <option value="ask">Pause and ask</option>
+                    </select>
+                  </div>
+                )}
+
+                {isTauri && (
+                  <div className="flex items-center justify-between py-3 border-t border-gray-100 dark:border-gray-800">
+                    <div>
+                      <p className="font-medium text-gray-900 dark:text-white">After a Crash</p>
+                      <p className="text-sm text-gray-500">
+                        What to do with a session that was running when the app closed
+                      </p>
+                    </div>
+                    <select
+                      value={recoveryPolicy}
+                      onChange={(e) => setRecoveryPolicy(e.target.value as RecoveryPolicy)}
+                      className="h-10 rounded-md border border-input bg-background px-3 text-sm"
+                    >
+                      <option value="close">Keep the time recorded</option>
+                      <option value="resume">Resume it paused</option>
+                      <option value="ask">Ask me</option>
+                    </select>
+                  </div>
+                )}
               </div>
             </div>
           )}
@@ -322,6 +506,38 @@ export default function Settings() {
                     <p className="text-xs text-gray-500">Hours to practice each week</p>
                   </div>
                 </div>
+                {isTauri && (
+                  <div className="space-y-2">
+                    <label className="text-sm font-medium text-gray-700 dark:text-gray-300">Time Zone</label>
+                    <select
+                      value={timezone}
+                      onChange={(e) => setTimezone(e.target.value)}
+                      className="h-10 w-full rounded-md border border-input bg-background px-3 text-sm"
+                    >
+                      {Intl.supportedValuesOf('timeZone').map((zone) => (
+                        <option key={zone} value={zone}>{zone}</option>
+                      ))}
+                    </select>
+                    <p className="text-xs text-gray-500">Days, streaks and goals follow this zone</p>
+                  </div>
+                )}
+                {isTauri && (
+                  <div className="space-y-2">
+                    <label className="text-sm font-medium text-gray-700 dark:text-gray-300">Day Starts At</label>
+                    <select
+                      value={dayStartHour}
+                      onChange={(e) => setDayStartHour(parseInt(e.target.value))}
+                      className="h-10 w-full rounded-md border border-input bg-background px-3 text-sm"
+                    >
+                      {Array.from({ length: 24 }, (_, hour) => (
+                        <option key={hour} value={hour}>
+                          {hour === 0 ? 'Midnight' : `${hour.toString().padStart(2, '0')}:00`}
+                        </option>
+                      ))}
+                    </select>
+                    <p className="text-xs text-gray-500">Sessions before this hour count for the day before</p>
+                  </div>
+                )}
               </div>
             </div>
           )}
@@ -436,6 +652,171 @@ export default function Settings() {
                 </div>
               </div>
 
+              {isTauri && (
+                <div className="elevation-1 rounded-xl bg-white dark:bg-card">
+                  <div className="p-5 border-b border-gray-100 dark:border-gray-800">
+                    <h3 className="text-base font-medium text-gray-900 dark:text-white">Restore Backup</h3>
+                    <p className="text-sm text-gray-500 mt-1">Preview what a backup would change before restoring it</p>
+                  </div>
+                  <div className="p-5 space-y-4">
+                    <div className="flex items-center gap-3">
+                      <Input
+                        type="file"
+                        accept="application/json,.json"
+                        onChange={(e) => handleChooseBackup(e.target.files?.[0])}
+                        className="flex-1"
+                      />
+                      <select
+                        value={conflictPolicy}
+                        onChange={(e) => {
+                          const policy = e.target.value as ConflictPolicy;
+                          setConflictPolicy(policy);
+                          if (backup) previewRestore(backup, policy);
+                        }}
+                        className="h-10 rounded-md border border-input bg-background px-3 text-sm"
+                      >
+                        <option value="skip">Keep my changes</option>
+                        <option value="replace">Use the backup</option>
+                        <option value="fail">Stop on differences</option>
+                      </select>
+                    </div>
+                    {restorePreview && (
+                      <>
+                        <table className="w-full text-sm">
+                          <thead>
+                            <tr className="text-left text-gray-500">
+                              <th className="font-normal">Table</th>
+                              <th className="font-normal text-right">New</th>
+                              <th className="font-normal text-right">Replaced</th>
+                              <th className="font-normal text-right">Kept</th>
+                              <th className="font-normal text-right">Same</th>
+                            </tr>
+                          </thead>
+                          <tbody>
+                            {restorePreview.tables.map((changes) => (
+                              <tr key={changes.table} className="text-gray-900 dark:text-white">
+                                <td>{changes.table.replace(/_/g, ' ')}</td>
+                                <td className="text-right">{changes.added}</td>
+                                <td className="text-right">{changes.updated}</td>
+                                <td className="text-right">{changes.skipped}</td>
+                                <td className="text-right text-gray-500">{changes.unchanged}</td>
+                              </tr>
+                            ))}
+                          </tbody>
+                        </table>
+                        <Button onClick={handleRestoreBackup}>
+                          <RefreshCw className="w-4 h-4 mr-2" />
+                          Restore
+                        </Button>
+                      </>
+                    )}
+                  </div>
+                </div>
+              )}
+
+              {isTauri && (
+                <div className="elevation-1 rounded-xl bg-white dark:bg-card">
+                  <div className="p-5 border-b border-gray-100 dark:border-gray-800">
+                    <h3 className="text-base font-medium text-gray-900 dark:text-white">Snapshots</h3>
+                    <p className="text-sm text-gray-500 mt-1">
+                      Copies of the whole database, taken daily and before updates. Recent days, weeks and months are kept.
+                    </p>
+                  </div>
+                  <div className="p-5 space-y-3">
+                    {snapshots.length === 0 && (
+                      <p className="text-sm text-gray-500">No snapshots yet.</p>
+                    )}
+                    {snapshots.slice(0, 10).map((snapshot) => (
+                      <div key={snapshot.name} className="flex items-center justify-between text-sm">
+                        <span className="text-gray-900 dark:text-white">
+                          {new Date(snapshot.takenAt).toLocaleString()}
+                          <span className="ml-2 text-gray-500">{snapshot.reason.replace(/-/g, ' ')}</span>
+                        </span>
+                        <Button variant="outline" size="sm" onClick={() => handleRestoreSnapshot(snapshot)}>
+                          Restore
+                        </Button>
+                      </div>
+                    ))}
+                    <Button variant="outline" onClick={handleTakeSnapshot}>
+                      <Save className="w-4 h-4 mr-2" />
+                      Take Snapshot Now
+                    </Button>
+                  </div>
+                </div>
+              )}
+
+              {isTauri && (
+                <div className="elevation-1 rounded-xl bg-white dark:bg-card">
+                  <div className="p-5 border-b border-gray-100 dark:border-gray-800">
+                    <h3 className="text-base font-medium text-gray-900 dark:text-white">Rebuild Totals</h3>
+                    <p className="text-sm text-gray-500 mt-1">Recalculate daily activity from your recorded sessions. Skill hours, including imported ones, are kept</p>
+                  </div>
+                  <div className="p-5">
+                    <Button variant="outline" onClick={handleRecomputeTotals}>
+                      <RefreshCw className="w-4 h-4 mr-2" />
+                      Rebuild Totals
+                    </Button>
+                  </div>
+                </div>
+              )}
+
+              {isTauri && api && (
+                <div className="elevation-1 rounded-xl bg-white dark:bg-card">
+                  <div className="p-5 border-b border-gray-100 dark:border-gray-800">
+                    <h3 className="text-base font-medium text-gray-900 dark:text-white">Local API</h3>
+                    <p className="text-sm text-gray-500 mt-1">
+                      Let scripts and editor plugins on this computer read your data and control the timer
+                    </p>
+                  </div>
+                  <div className="p-5 space-y-4">
+                    <div className="flex items-center gap-3">
+                      <label className="text-sm text-gray-500">Port</label>
+                      <Input
+                        type="number"
+                        value={apiPort}
+                        onChange={(e) => setApiPort(Number(e.target.value))}
+                        className="w-28"
+                      />
+                      <Button
+                        variant={api.apiEnabled ? 'outline' : 'default'}
+                        onClick={() => handleUpdateApi(!api.apiEnabled)}
+                      >
+                        {api.apiEnabled ? 'Turn Off' : 'Turn On'}
+                      </Button>
+                      {api.apiEnabled && apiPort !== api.apiPort && (
+                        <Button variant="outline" onClick={() => handleUpdateApi(true)}>
+                          Apply Port
+                        </Button>
+                      )}
+                    </div>
+                    {api.apiToken && (
+                      <div className="flex items-center gap-2">
+                        <code className="flex-1 truncate rounded bg-gray-100 dark:bg-gray-800 px-3 py-2 text-xs">
+                          {api.apiToken}
+                        </code>
+                        <Button
+                          variant="outline"
+                          size="icon"
+                          onClick={() => {
+                            navigator.clipboard.writeText(api.apiToken ?? '');
+                            toast.success('Token copied');
+                          }}
+                        >
+                          <Copy className="w-4 h-4" />
+                        </Button>
+                        <Button variant="outline" onClick={handleRegenerateToken}>
+                          <RefreshCw className="w-4 h-4 mr-2" />
+                          Regenerate
+                        </Button>
+                      </div>
+                    )}
+                    <p className="text-xs text-gray-500">
+                      Send the token as <code>Authorization: Bearer &lt;token&gt;</code> to http://127.0.0.1:{api.apiPort}
+                    </p>
+                  </div>
+                </div>
+              )}
+
               <div className="elevation-1 rounded-xl bg-white dark:bg-card border-2 border-gred/30">
                 <div className="p-5 border-b border-gray-100 dark:border-gray-800">
                   <h3 className="text-base font-medium text-gred">Danger Zone</h3>
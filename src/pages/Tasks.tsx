@@ -15,9 +15,11 @@ import {
   Target,
   CheckCircle2,
   Calendar,
+  Repeat,
 } from 'lucide-react';
 import { Task, TaskPriority } from '@/types';
 import { cn } from '@/lib/utils';
+import { isTauri } from '@/lib/database';
 import { toast } from 'sonner';
 
 // ============================================
@@ -174,6 +176,14 @@ function TaskCard({ task, skill, onEdit, onComplete, onStart }: TaskCardProps) {
         </div>
       )}
 
+      {/* Recurrence */}
+      {task.recurrence && (
+        <div className="flex items-center gap-1.5 text-xs mb-3 text-gray-500 dark:text-gray-400">
+          <Repeat className="w-3.5 h-3.5" />
+          <span>{recurrenceLabel(task.recurrence)}</span>
+        </div>
+      )}
+
       {/* Spacer to push buttons to bottom */}
       <div className="flex-1" />
 
@@ -208,6 +218,20 @@ interface TaskFormData {
   estimatedPomodoros: number;
   pomodoroSessions: number;
   totalMinutes: number;
+  recurrence: string;
+}
+
+// Common RRULEs; the backend generates the next instance when one is done
+const recurrenceOptions = [
+  { value: '', label: 'Does not repeat' },
+  { value: 'FREQ=DAILY', label: 'Daily' },
+  { value: 'FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR', label: 'Every weekday' },
+  { value: 'FREQ=WEEKLY', label: 'Weekly' },
+  { value: 'FREQ=MONTHLY', label: 'Monthly' },
+];
+
+function recurrenceLabel(rule: string) {
+  return recurrenceOptions.find((option) => option.value === rule)?.label ?? rule;
 }
 
 const defaultTaskForm: TaskFormData = {
@@ -218,6 +242,7 @@ const defaultTaskForm: TaskFormData = {
   estimatedPomodoros: 1,
   pomodoroSessions: 0,
   totalMinutes: 0,
+  recurrence: '',
 };
 
 // ============================================
@@ -300,6 +325,7 @@ export default function Tasks() {
       estimatedPomodoros: latestTask.estimatedPomodoros || 1,
       pomodoroSessions: latestTask.pomodoroSessions || 0,
       totalMinutes: latestTask.totalMinutes || 0,
+      recurrence: latestTask.recurrence || '',
     });
     setNewTaskSkillId(latestTask.skillId);
     setShowTaskModal(true);
@@ -332,12 +358,16 @@ export default function Tasks() {
           pomodoroSessions: formData.pomodoroSessions,
           totalMinutes: formData.totalMinutes,
           dueDate: formData.dueDate || undefined,
+          recurrence: formData.recurrence,
         });
 
         // If user manually added/removed time, record it as a timer session and update skill
         if (minutesDiff !== 0) {
-          const { addMinutesToSkill } = useSkillsStore.getState();
-          await addMinutesToSkill(editingTask.skillId, minutesDiff);
+          // On desktop the session itself updates the skill total
+          if (!isTauri) {
+            const { addMinutesToSkill } = useSkillsStore.getState();
+            await addMinutesToSkill(editingTask.skillId, minutesDiff);
+          }
           // Record manual time as a timer session so it shows in Dashboard/Focus Today
           await recordManualTime(editingTask.skillId, editingTask.id, minutesDiff);
         }
@@ -609,6 +639,29 @@ export default function Tasks() {
             />
           </div>
 
+          {/* Recurrence */}
+          {isTauri && (
+            <div>
+              <label className="block text-xs font-medium text-gray-500 uppercase tracking-wide mb-1.5">
+                Repeats
+              </label>
+              <select
+                value={formData.recurrence}
+                onChange={(e) => setFormData({ ...formData, recurrence: e.target.value })}
+                className="w-full px-3 py-2 text-sm border border-gray-200 rounded-lg bg-white focus:outline-none focus:ring-2 focus:ring-blue-500"
+              >
+                {recurrenceOptions.map((option) => (
+                  <option key={option.value} value={option.value}>
+                    {option.label}
+                  </option>
+                ))}
+                {!recurrenceOptions.some((option) => option.value === formData.recurrence) && (
+                  <option value={formData.recurrence}>{formData.recurrence}</option>
+                )}
+              </select>
+            </div>
+          )}
+
           {/* Progress (Edit mode) */}
           {editingTask && (
             <div className="pt-4 border-t border-gray-200">
@@ -1,5 +1,6 @@
 import { create } from 'zustand';
-import { db, generateId } from '@/lib/database';
+import { db, generateId, isTauri } from '@/lib/database';
+import { commands } from '@/lib/commands';
 import { Skill, CreateSkillInput, UpdateSkillInput } from '@/types';
 
 interface SkillsState {
@@ -26,7 +27,9 @@ export const useSkillsStore = create<SkillsState>((set, get) => ({
   fetchSkills: async () => {
     set({ loading: true, error: null });
     try {
-      const skills = await db.select<any[]>('SELECT * FROM skills ORDER BY created_at DESC');
+      const skills: any[] = isTauri
+        ? await commands.listSkills()
+        : await db.select<any[]>('SELECT * FROM skills ORDER BY created_at DESC');
       
       const mappedSkills: Skill[] = skills.map((s: any) => ({
         id: s.id,
@@ -52,6 +55,23 @@ export const useSkillsStore = create<SkillsState>((set, get) => ({
   },
 
   createSkill: async (input) => {
+    if (isTauri) {
+      try {
+        const { id } = await commands.createSkill({
+          name: input.name,
+          description: input.description || null,
+          goalHours: input.goalHours,
+          dailyGoalMinutes: input.dailyGoalMinutes,
+          color: input.color,
+        });
+        await get().fetchSkills();
+        return get().skills.find(s => s.id === id)!;
+      } catch (error) {
+        set({ error: String(error) });
+        throw error;
+      }
+    }
+
     const id = generateId('skill');
     try {
       await db.execute(
@@ -79,6 +99,18 @@ export const useSkillsStore = create<SkillsState>((set, get) => ({
   },
 
   updateSkill: async (input) => {
+    if (isTauri) {
+      try {
+        await commands.updateSkill(input);
+        await get().fetchSkills();
+      } catch (error) {
+        console.error('[updateSkill] Error:', error);
+        set({ error: String(error) });
+        throw error;
+      }
+      return;
+    }
+
     try {
       const updates: string[] = [];
       const values: any[] = [];
@@ -99,6 +131,10 @@ export const useSkillsStore = create<SkillsState>((set, get) => ({
         updates.push('color = $' + (values.length + 1));
         values.push(input.color);
       }
+      if (input.dailyGoalMinutes !== undefined) {
+        updates.push('daily_goal_minutes = $' + (values.length + 1));
+        values.push(input.dailyGoalMinutes);
+      }
 
       if (updates.length === 0) {
         console.log('[updateSkill] No fields to update');
@@ -125,6 +161,17 @@ export const useSkillsStore = create<SkillsState>((set, get) => ({
   },
 
   deleteSkill: async (id) => {
+    if (isTauri) {
+      try {
+        await commands.deleteSkill(id);
+        await get().fetchSkills();
+      } catch (error) {
+        set({ error: String(error) });
+        throw error;
+      }
+      return;
+    }
+
     try {
       // First check if there are any tasks for this skill
       const tasks = await db.select<any[]>('SELECT id FROM tasks WHERE skill_id = $1', [id]);
@@ -147,6 +194,18 @@ export const useSkillsStore = create<SkillsState>((set, get) => ({
   },
 
   setActiveSkill: async (skill) => {
+    if (isTauri) {
+      try {
+        await commands.setActiveSkill(skill?.id ?? null);
+        set({ activeSkill: skill });
+        await get().fetchSkills();
+      } catch (error) {
+        set({ error: String(error) });
+        throw error;
+      }
+      return;
+    }
+
     try {
       // First deactivate all skills
       await db.execute('UPDATE skills SET is_active = $1', [false]);
@@ -166,6 +225,13 @@ export const useSkillsStore = create<SkillsState>((set, get) => ({
 
   addMinutesToSkill: async (skillId, minutes) => {
     try {
+      if (isTauri) {
+        // Skill totals follow sessions on desktop, so log the time as one.
+        await commands.recordManualSession(skillId, null, minutes);
+        await get().fetchSkills();
+        return;
+      }
+
       await db.execute(
         'UPDATE skills SET current_minutes = current_minutes + $1, updated_at = datetime(\'now\') WHERE id = $2',
         [minutes, skillId]
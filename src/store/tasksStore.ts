@@ -1,5 +1,6 @@
 import { create } from 'zustand';
-import { db, generateId } from '@/lib/database';
+import { db, generateId, isTauri } from '@/lib/database';
+import { commands } from '@/lib/commands';
 import { Task, CreateTaskInput, UpdateTaskInput, TaskStatus } from '@/types';
 
 interface TasksState {
@@ -28,7 +29,9 @@ export const useTasksStore = create<TasksState>((set, get) => ({
         ? 'SELECT * FROM tasks WHERE skill_id = $1 ORDER BY order_index, created_at DESC'
         : 'SELECT * FROM tasks ORDER BY order_index, created_at DESC';
       const params = skillId ? [skillId] : [];
-      const tasks = await db.select<any[]>(query, params);
+      const tasks: any[] = isTauri
+        ? await commands.listTasks(skillId)
+        : await db.select<any[]>(query, params);
       
       const mappedTasks: Task[] = tasks.map((t: any) => ({
         id: t.id,
@@ -44,6 +47,8 @@ export const useTasksStore = create<TasksState>((set, get) => ({
         order: t.order_index,
         createdAt: t.created_at,
         completedAt: t.completed_at,
+        recurrence: t.recurrence ?? undefined,
+        seriesId: t.series_id ?? undefined,
       }));
       
       set({ tasks: mappedTasks, loading: false });
@@ -53,6 +58,21 @@ export const useTasksStore = create<TasksState>((set, get) => ({
   },
 
   createTask: async (input) => {
+    if (isTauri) {
+      try {
+        const { id } = await commands.createTask({
+          ...input,
+          description: input.description || null,
+          dueDate: input.dueDate || null,
+        });
+        await get().fetchTasks(input.skillId);
+        return get().tasks.find(t => t.id === id)!;
+      } catch (error) {
+        set({ error: String(error) });
+        throw error;
+      }
+    }
+
     const id = generateId('task');
     try {
       await db.execute(
@@ -80,6 +100,17 @@ export const useTasksStore = create<TasksState>((set, get) => ({
   },
 
   updateTask: async (input) => {
+    if (isTauri) {
+      try {
+        await commands.updateTask(input);
+        await get().fetchTasks();
+      } catch (error) {
+        set({ error: String(error) });
+        throw error;
+      }
+      return;
+    }
+
     try {
       const updates: string[] = [];
       const values: any[] = [];
@@ -140,6 +171,12 @@ export const useTasksStore = create<TasksState>((set, get) => ({
 
   deleteTask: async (id) => {
     try {
+      if (isTauri) {
+        await commands.deleteTask(id);
+        await get().fetchTasks();
+        return;
+      }
+
       await db.execute('DELETE FROM tasks WHERE id = $1', [id]);
       await get().fetchTasks();
     } catch (error) {
@@ -150,6 +187,12 @@ export const useTasksStore = create<TasksState>((set, get) => ({
 
   updateTaskStatus: async (id, status) => {
     try {
+      if (isTauri) {
+        await commands.setTaskStatus(id, status);
+        await get().fetchTasks();
+        return;
+      }
+
       const updates = ['status = $1'];
       const values: any[] = [status];
       
@@ -176,6 +219,12 @@ export const useTasksStore = create<TasksState>((set, get) => ({
 
   reorderTasks: async (taskIds) => {
     try {
+      if (isTauri) {
+        await commands.reorderTasks(taskIds);
+        await get().fetchTasks();
+        return;
+      }
+
       for (let i = 0; i < taskIds.length; i++) {
         await db.execute(
           'UPDATE tasks SET order_index = $1 WHERE id = $2',
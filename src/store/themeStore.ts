@@ -4,7 +4,8 @@
  */
 import { create } from 'zustand';
 import { persist } from 'zustand/middleware';
-import { db } from '@/lib/database';
+import { db, isTauri } from '@/lib/database';
+import { commands } from '@/lib/commands';
 
 type Theme = 'light' | 'dark' | 'system';
 
@@ -41,7 +42,10 @@ export const useThemeStore = create<ThemeState>()(
         set({ theme, resolvedTheme: resolved });
         
         // Save to database
-        db.execute('UPDATE user_settings SET theme = $1', [theme]).catch(console.error);
+        const save = isTauri
+          ? commands.updateSettings({ theme })
+          : db.execute('UPDATE user_settings SET theme = $1', [theme]);
+        save.catch(console.error);
       },
 
       toggleTheme: () => {
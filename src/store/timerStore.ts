@@ -1,5 +1,10 @@
 import { create } from 'zustand';
-import { db, generateId } from '@/lib/database';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import { toast } from 'sonner';
+import { db, generateId, isTauri } from '@/lib/database';
+import { commands, type SleepPolicy } from '@/lib/commands';
+import { getDateKey } from '@/lib/utils';
 import { TimerSession, TimerState, TimerType, PomodoroSettings, CreateTimerSessionInput } from '@/types';
 import { useTasksStore } from './tasksStore';
 import { useSkillsStore } from './skillsStore';
@@ -58,6 +63,47 @@ const defaultState: TimerState = {
 
 let timerInterval: number | null = null;
 
+// On desktop the Rust backend owns the countdown; these mirror its payloads.
+interface TimerSnapshot {
+  status: 'idle' | 'running' | 'paused';
+  type: TimerType;
+  remainingSeconds: number;
+  totalSeconds: number;
+  currentTaskId: string | null;
+  currentSkillId: string | null;
+  sessionId: string | null;
+  completedPomodoros: number;
+}
+
+interface TimerInterruption {
+  sessionId: string;
+  since: string;
+  gapSeconds: number;
+  policy: SleepPolicy;
+  next: TimerSnapshot;
+}
+
+interface CompletedTimerSession {
+  sessionId: string;
+  type: TimerType;
+  taskId: string | null;
+  skillId: string;
+  minutes: number;
+  next: TimerSnapshot;
+}
+
+function fromSnapshot(snapshot: TimerSnapshot): TimerState {
+  return {
+    status: snapshot.status,
+    type: snapshot.type,
+    remainingSeconds: snapshot.remainingSeconds,
+    totalSeconds: snapshot.totalSeconds,
+    currentTaskId: snapshot.currentTaskId ?? undefined,
+    currentSkillId: snapshot.currentSkillId ?? undefined,
+    sessionId: snapshot.sessionId ?? undefined,
+  };
+}
+
 export const useTimerStore = create<TimerStore>((set, get) => ({
   ...defaultState,
   settings: defaultSettings,
@@ -68,6 +114,17 @@ export const useTimerStore = create<TimerStore>((set, get) => ({
   error: null,
 
   startTimer: async (type, taskId, skillId) => {
+    if (isTauri) {
+      try {
+        const snapshot = await invoke<TimerSnapshot>('timer_start', { kind: type, taskId, skillId });
+        set({ ...fromSnapshot(snapshot), error: null });
+      } catch (error) {
+        set({ error: String(error) });
+        throw error;
+      }
+      return;
+    }
+
     const { settings } = get();
     const durationMinutes = type === 'pomodoro' 
       ? settings.pomodoroDuration 
@@ -111,6 +168,13 @@ export const useTimerStore = create<TimerStore>((set, get) => ({
   },
 
   pauseTimer: () => {
+    if (isTauri) {
+      invoke<TimerSnapshot>('timer_pause')
+        .then((snapshot) => set(fromSnapshot(snapshot)))
+        .catch((error) => set({ error: String(error) }));
+      return;
+    }
+
     if (timerInterval) {
       clearInterval(timerInterval);
       timerInterval = null;
@@ -119,6 +183,13 @@ export const useTimerStore = create<TimerStore>((set, get) => ({
   },
 
   resumeTimer: () => {
+    if (isTauri) {
+      invoke<TimerSnapshot>('timer_resume')
+        .then((snapshot) => set(fromSnapshot(snapshot)))
+        .catch((error) => set({ error: String(error) }));
+      return;
+    }
+
     const { status } = get();
     if (status === 'paused') {
       set({ status: 'running' });
@@ -129,6 +200,19 @@ export const useTimerStore = create<TimerStore>((set, get) => ({
   },
 
   stopTimer: async () => {
+    if (isTauri) {
+      try {
+        const snapshot = await invoke<TimerSnapshot>('timer_stop');
+        set(fromSnapshot(snapshot));
+        await get().fetchTodayActivity();
+        useTasksStore.getState().fetchTasks();
+        useSkillsStore.getState().fetchSkills();
+      } catch (error) {
+        console.error('[stopTimer] Failed:', error);
+      }
+      return;
+    }
+
     if (timerInterval) {
       clearInterval(timerInterval);
       timerInterval = null;
@@ -280,7 +364,9 @@ export const useTimerStore = create<TimerStore>((set, get) => ({
         ? 'SELECT * FROM timer_sessions WHERE skill_id = $1 ORDER BY start_time DESC'
         : 'SELECT * FROM timer_sessions ORDER BY start_time DESC';
       const params = skillId ? [skillId] : [];
-      const sessions = await db.select<any[]>(query, params);
+      const sessions: any[] = isTauri
+        ? await commands.listSessions(skillId)
+        : await db.select<any[]>(query, params);
       
       const mappedSessions: TimerSession[] = sessions.map((s: any) => ({
         id: s.id,
@@ -301,6 +387,11 @@ export const useTimerStore = create<TimerStore>((set, get) => ({
   },
 
   createSession: async (input) => {
+    if (isTauri) {
+      // The backend timer writes its own session rows on desktop.
+      throw new Error('Timer sessions are created by timer_start on desktop');
+    }
+
     const id = generateId('session');
     try {
       await db.execute(
@@ -320,6 +411,17 @@ export const useTimerStore = create<TimerStore>((set, get) => ({
 
   updateSession: async (sessionId, updates) => {
     try {
+      if (isTauri) {
+        await commands.updateSession({
+          id: sessionId,
+          endTime: updates.endTime,
+          completed: updates.completed,
+          duration: updates.duration,
+        });
+        await get().fetchSessions();
+        return;
+      }
+
       const updateFields: string[] = [];
       const values: any[] = [];
 
@@ -374,6 +476,14 @@ export const useTimerStore = create<TimerStore>((set, get) => ({
     if (minutes === 0) return;
     
     try {
+      if (isTauri) {
+        await commands.recordManualSession(skillId, taskId, minutes);
+        await get().fetchTodayActivity();
+        await get().fetchYearlyActivity();
+        useSkillsStore.getState().fetchSkills();
+        return;
+      }
+
       const now = new Date();
       const sessionId = generateId('session');
       
@@ -407,9 +517,9 @@ export const useTimerStore = create<TimerStore>((set, get) => ({
 
   fetchTodayActivity: async () => {
     try {
-      const today = new Date().toISOString().split('T')[0];
+      const today = getDateKey();
       // Get today's completed pomodoro sessions grouped by skill
-      const sessions = await db.select<any[]>(
+      const sessions: any[] = isTauri ? await commands.minutesBySkillOn(today) : await db.select<any[]>(
         `SELECT skill_id, SUM(duration) as total_minutes 
          FROM timer_sessions 
          WHERE completed = 1 
@@ -433,7 +543,7 @@ export const useTimerStore = create<TimerStore>((set, get) => ({
   fetchYearlyActivity: async () => {
     try {
       // Get last 365 days of activity
-      const sessions = await db.select<any[]>(
+      const sessions: any[] = isTauri ? await commands.minutesByDay(365) : await db.select<any[]>(
         `SELECT date(start_time) as activity_date, SUM(duration) as total_minutes 
          FROM timer_sessions 
          WHERE completed = 1 
@@ -453,3 +563,62 @@ export const useTimerStore = create<TimerStore>((set, get) => ({
     }
   },
 }));
+
+if (isTauri) {
+  // Pick up a session that kept running across a webview reload.
+  invoke<TimerSnapshot>('timer_state')
+    .then((snapshot) => useTimerStore.setState(fromSnapshot(snapshot)))
+    .catch((error) => console.error('Failed to restore timer state:', error));
+
+  listen<TimerSnapshot>('timer-tick', (event) => {
+    useTimerStore.setState(fromSnapshot(event.payload));
+  });
+
+  // The computer slept through part of a session; the backend has already
+  // paused or trimmed it.
+  listen<TimerInterruption>('timer-interrupted', async (event) => {
+    const { next, policy, gapSeconds } = event.payload;
+    useTimerStore.setState(fromSnapshot(next));
+    const away = `${Math.round(gapSeconds / 60)} min away was not counted.`;
+    const { resumeTimer, stopTimer } = useTimerStore.getState();
+
+    if (policy === 'trim') {
+      toast.info('Session ended when your computer went to sleep', { description: away });
+      await useTimerStore.getState().fetchTodayActivity();
+      useTasksStore.getState().fetchTasks();
+      useSkillsStore.getState().fetchSkills();
+    } else if (policy === 'ask') {
+      toast.warning('Your computer slept during this session', {
+        description: away,
+        duration: Infinity,
+        action: { label: 'Resume', onClick: resumeTimer },
+        cancel: { label: 'End session', onClick: () => { stopTimer(); } },
+      });
+    } else {
+      toast.info('Timer paused while your computer slept', {
+        description: away,
+        action: { label: 'Resume', onClick: resumeTimer },
+      });
+    }
+  });
+
+  listen<CompletedTimerSession>('timer-complete', async (event) => {
+    const { next } = event.payload;
+    useTimerStore.setState({
+      ...fromSnapshot(next),
+      status: next.status === 'idle' ? 'completed' : next.status,
+    });
+
+    if (event.payload.type === 'pomodoro') {
+      await useTimerStore.getState().fetchTodayActivity();
+      useTasksStore.getState().fetchTasks();
+      useSkillsStore.getState().fetchSkills();
+    }
+
+    setTimeout(() => {
+      if (useTimerStore.getState().status === 'completed') {
+        useTimerStore.setState({ status: 'idle' });
+      }
+    }, 2000);
+  });
+}
@@ -1,5 +1,6 @@
 import { create } from 'zustand';
-import { db } from '@/lib/database';
+import { db, isTauri } from '@/lib/database';
+import { commands } from '@/lib/commands';
 import { UserProfile, UserSettings, UpdateUserSettingsInput } from '@/types';
 
 interface UserState {
@@ -16,6 +17,61 @@ interface UserState {
   importData: (data: string) => Promise<void>;
 }
 
+function toProfile(
+  s: any,
+  totalMinutes: number,
+  currentStreak: number,
+  longestStreak: number
+): { profile: UserProfile; settings: UserSettings } {
+  // Parse settings
+  const settings: UserSettings = {
+    pomodoroMinutes: s.pomodoro_duration || 25,
+    shortBreakMinutes: s.short_break_duration || 5,
+    longBreakMinutes: s.long_break_duration || 15,
+    dailyGoalHours: (s.daily_goal_minutes || 240) / 60,
+    weeklyGoalHours: (s.weekly_goal_minutes || 420) / 60,
+    soundEnabled: Boolean(s.sound_enabled ?? true),
+    notificationsEnabled: Boolean(s.notifications_enabled ?? true),
+    autoStartBreaks: Boolean(s.auto_start_breaks),
+    autoStartPomodoros: Boolean(s.auto_start_pomodoros),
+    longBreakInterval: s.long_break_interval || 4,
+    theme: s.theme || 'system',
+  };
+  
+  const profile: UserProfile = {
+    id: '1',
+    name: s.name,
+    email: s.email,
+    avatar: s.avatar,
+    totalHours: Math.floor(totalMinutes / 60),
+    totalMinutes: totalMinutes,
+    currentStreak: currentStreak,
+    longestStreak: Math.max(longestStreak, currentStreak),
+    dailyGoalMinutes: s.daily_goal_minutes || 240,
+    weeklyGoalMinutes: s.weekly_goal_minutes || 420,
+    dailyGoalHours: (s.daily_goal_minutes || 240) / 60,
+    weeklyGoalHours: (s.weekly_goal_minutes || 420) / 60,
+    createdAt: s.created_at,
+    settings: {
+      theme: s.theme,
+      soundEnabled: Boolean(s.sound_enabled),
+      notificationsEnabled: Boolean(s.notifications_enabled),
+      pomodoroDuration: s.pomodoro_duration,
+      shortBreakDuration: s.short_break_duration,
+      longBreakDuration: s.long_break_duration,
+      autoStartBreaks: Boolean(s.auto_start_breaks),
+      autoStartPomodoros: Boolean(s.auto_start_pomodoros),
+      longBreakInterval: s.long_break_interval,
+      spotifyEnabled: Boolean(s.spotify_enabled),
+      spotifyAccessToken: s.spotify_access_token,
+      spotifyRefreshToken: s.spotify_refresh_token,
+      spotifyTokenExpiry: s.spotify_token_expiry,
+    },
+  };
+
+  return { profile, settings };
+}
+
 export const useUserStore = create<UserState>((set, get) => ({
   profile: null,
   settings: null,
@@ -25,6 +81,15 @@ export const useUserStore = create<UserState>((set, get) => ({
   fetchProfile: async () => {
     set({ loading: true, error: null });
     try {
+      if (isTauri) {
+        const [s, stats] = await Promise.all([commands.getSettings(), commands.getProfileStats()]);
+        set({
+          ...toProfile(s, stats.total_minutes, stats.current_streak, stats.longest_streak),
+          loading: false,
+        });
+        return;
+      }
+
       const settingsResult = await db.select<any>('SELECT * FROM user_settings LIMIT 1');
       
       // Calculate stats from skills and daily_activities
@@ -82,53 +147,7 @@ export const useUserStore = create<UserState>((set, get) => ({
       if (settingsResult.length > 0) {
         const s = settingsResult[0];
         
-        // Parse settings
-        const settings: UserSettings = {
-          pomodoroMinutes: s.pomodoro_duration || 25,
-          shortBreakMinutes: s.short_break_duration || 5,
-          longBreakMinutes: s.long_break_duration || 15,
-          dailyGoalHours: (s.daily_goal_minutes || 240) / 60,
-          weeklyGoalHours: (s.weekly_goal_minutes || 420) / 60,
-          soundEnabled: Boolean(s.sound_enabled ?? true),
-          notificationsEnabled: Boolean(s.notifications_enabled ?? true),
-          autoStartBreaks: Boolean(s.auto_start_breaks),
-          autoStartPomodoros: Boolean(s.auto_start_pomodoros),
-          longBreakInterval: s.long_break_interval || 4,
-          theme: s.theme || 'system',
-        };
-        
-        const profile: UserProfile = {
-          id: '1',
-          name: s.name,
-          email: s.email,
-          avatar: s.avatar,
-          totalHours: Math.floor(totalMinutes / 60),
-          totalMinutes: totalMinutes,
-          currentStreak: currentStreak,
-          longestStreak: Math.max(longestStreak, currentStreak),
-          dailyGoalMinutes: s.daily_goal_minutes || 240,
-          weeklyGoalMinutes: s.weekly_goal_minutes || 420,
-          dailyGoalHours: (s.daily_goal_minutes || 240) / 60,
-          weeklyGoalHours: (s.weekly_goal_minutes || 420) / 60,
-          createdAt: s.created_at,
-          settings: {
-            theme: s.theme,
-            soundEnabled: Boolean(s.sound_enabled),
-            notificationsEnabled: Boolean(s.notifications_enabled),
-            pomodoroDuration: s.pomodoro_duration,
-            shortBreakDuration: s.short_break_duration,
-            longBreakDuration: s.long_break_duration,
-            autoStartBreaks: Boolean(s.auto_start_breaks),
-            autoStartPomodoros: Boolean(s.auto_start_pomodoros),
-            longBreakInterval: s.long_break_interval,
-            spotifyEnabled: Boolean(s.spotify_enabled),
-            spotifyAccessToken: s.spotify_access_token,
-            spotifyRefreshToken: s.spotify_refresh_token,
-            spotifyTokenExpiry: s.spotify_token_expiry,
-          },
-        };
-
-        set({ profile, settings, loading: false });
+        set({ ...toProfile(s, totalMinutes, currentStreak, longestStreak), loading: false });
       } else {
         // This should not happen as migration inserts default settings
         // But just in case, use INSERT OR REPLACE
@@ -147,6 +166,21 @@ export const useUserStore = create<UserState>((set, get) => ({
   
   updateSettings: async (input) => {
     try {
+      if (isTauri) {
+        await commands.updateSettings({
+          pomodoroDuration: input.pomodoroMinutes,
+          shortBreakDuration: input.shortBreakMinutes,
+          longBreakDuration: input.longBreakMinutes,
+          dailyGoalMinutes: input.dailyGoalHours !== undefined ? input.dailyGoalHours * 60 : undefined,
+          weeklyGoalMinutes: input.weeklyGoalHours !== undefined ? input.weeklyGoalHours * 60 : undefined,
+          soundEnabled: input.soundEnabled,
+          notificationsEnabled: input.notificationsEnabled,
+          autoStartBreaks: input.autoStartBreaks,
+        });
+        await get().fetchProfile();
+        return;
+      }
+
       const updateParts: string[] = [];
       const values: any[] = [];
       
@@ -205,6 +239,20 @@ export const useUserStore = create<UserState>((set, get) => ({
 
       console.log('[updateProfile] Input:', updates);
 
+      if (isTauri) {
+        await commands.updateSettings({
+          name: updates.name,
+          avatar: updates.avatar,
+          dailyGoalMinutes: updates.dailyGoalMinutes,
+          weeklyGoalMinutes: updates.weeklyGoalMinutes,
+          theme: updates.settings?.theme,
+          soundEnabled: updates.settings?.soundEnabled,
+          notificationsEnabled: updates.settings?.notificationsEnabled,
+        });
+        await get().fetchProfile();
+        return;
+      }
+
       if (updates.name !== undefined) {
         updateParts.push('name = $' + (values.length + 1));
         values.push(updates.name);
@@ -262,6 +310,10 @@ export const useUserStore = create<UserState>((set, get) => ({
 
   exportData: async () => {
     try {
+      if (isTauri) {
+        return JSON.stringify(await commands.createBackup(), null, 2);
+      }
+
       const skills = await db.select<any>('SELECT * FROM skills');
       const tasks = await db.select<any>('SELECT * FROM tasks');
       const sessions = await db.select<any>('SELECT * FROM timer_sessions');
@@ -292,11 +344,23 @@ export const useUserStore = create<UserState>((set, get) => ({
   importData: async (jsonData) => {
     try {
       const data = JSON.parse(jsonData);
-      
+
+      if (isTauri && data.format === '10k-hours-backup') {
+        await commands.restoreBackup(data);
+        await get().fetchProfile();
+        return;
+      }
+
       if (!data.version) {
         throw new Error('Invalid export file format');
       }
 
+      if (isTauri) {
+        await commands.importData(data);
+        await get().fetchProfile();
+        return;
+      }
+
       // Import skills
       if (data.skills?.length) {
         for (const skill of data.skills) {
@@ -13,6 +13,16 @@ export interface SkillProgress {
   }[];
 }
 
+// Tags overlap, so percentages can add up to more than 100
+export interface TagShare {
+  tagId: string;
+  tagName: string;
+  color: string | null;
+  minutes: number;
+  sessions: number;
+  percentage: number;
+}
+
 export interface WeeklyStats {
   weekStart: string;
   totalMinutes: number;
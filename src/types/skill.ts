@@ -25,5 +25,6 @@ export interface UpdateSkillInput {
   name?: string;
   description?: string;
   goalHours?: number;
+  dailyGoalMinutes?: number;
   color?: string;
 }
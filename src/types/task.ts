@@ -15,6 +15,8 @@ export interface Task {
   order: number; // For kanban ordering
   createdAt: string;
   completedAt?: string;
+  recurrence?: string; // RRULE, e.g. FREQ=WEEKLY;BYDAY=MO,TH
+  seriesId?: string;
 }
 
 export interface CreateTaskInput {
@@ -25,6 +27,7 @@ export interface CreateTaskInput {
   priority?: TaskPriority;
   dueDate?: string;
   estimatedPomodoros?: number;
+  recurrence?: string;
 }
 
 export interface UpdateTaskInput {
@@ -38,4 +41,5 @@ export interface UpdateTaskInput {
   order?: number;
   pomodoroSessions?: number;
   totalMinutes?: number;
+  recurrence?: string; // '' stops the task repeating
 }